@@ -0,0 +1,329 @@
+/* Inteiros de precisão arbitrária (inteirogrande).
+ *
+ * Cada número é guardado em sinal e magnitude, com dígitos na base 10^9,
+ * do menos para o mais significativo. Todas as operações devolvem um
+ * número novo, sem modificar os argumentos. A divisão `/` é truncada em
+ * direção ao zero, e o resto tem o sinal do dividendo; o `div` arredonda
+ * para baixo. */
+
+#define ISI_BIG_BASE 1000000000u
+
+typedef struct {
+    int sign;
+    int len;
+    unsigned int* d;
+} isi_big;
+
+static inline isi_big isi_big_alloc(int len) {
+    isi_big r;
+    r.sign = 0;
+    r.len = len;
+    r.d = calloc(len > 0 ? len : 1, sizeof(unsigned int));
+    return r;
+}
+
+static inline void isi_big_free(isi_big a) {
+    free(a.d);
+}
+
+static inline void isi_big_trim(isi_big* a) {
+    while (a->len > 0 && a->d[a->len - 1] == 0) a->len--;
+    if (a->len == 0) a->sign = 0;
+}
+
+static inline isi_big isi_big_copy(isi_big a) {
+    isi_big r = isi_big_alloc(a.len);
+    memcpy(r.d, a.d, a.len * sizeof(unsigned int));
+    r.sign = a.sign;
+    return r;
+}
+
+static inline isi_big isi_big_from_int(long long x) {
+    /* A magnitude é calculada sem sinal, para que LLONG_MIN não dê overflow. */
+    unsigned long long m = x < 0 ? 0ull - (unsigned long long) x : (unsigned long long) x;
+    isi_big r = isi_big_alloc(3);
+    for (int i = 0; i < 3; i++) {
+        r.d[i] = m % ISI_BIG_BASE;
+        m /= ISI_BIG_BASE;
+    }
+    r.sign = x < 0 ? -1 : 1;
+    isi_big_trim(&r);
+    return r;
+}
+
+static inline isi_big isi_big_from_str(const char* s) {
+    int sign = 1;
+    while (*s == ' ' || *s == '\t' || *s == '\n') s++;
+    if (*s == '-' || *s == '+') {
+        if (*s == '-') sign = -1;
+        s++;
+    }
+
+    int n = 0;
+    while (s[n] >= '0' && s[n] <= '9') n++;
+
+    isi_big r = isi_big_alloc(n / 9 + 1);
+    int k = 0;
+    for (int end = n; end > 0; end -= 9) {
+        int start = end - 9 < 0 ? 0 : end - 9;
+        unsigned int v = 0;
+        for (int i = start; i < end; i++) v = v * 10 + (s[i] - '0');
+        r.d[k++] = v;
+    }
+    r.len = k;
+    r.sign = sign;
+    isi_big_trim(&r);
+    return r;
+}
+
+static inline char* isi_big_to_str(isi_big a) {
+    char* r = malloc(a.len * 9 + 3);
+    char* p = r;
+    if (a.sign == 0) {
+        strcpy(r, "0");
+        return r;
+    }
+    if (a.sign < 0) *p++ = '-';
+    p += sprintf(p, "%u", a.d[a.len - 1]);
+    for (int i = a.len - 2; i >= 0; i--) p += sprintf(p, "%09u", a.d[i]);
+    return r;
+}
+
+/* Fica só com os 64 bits menos significativos, como no interpretador. */
+static inline unsigned long long isi_big_to_int(isi_big a) {
+    unsigned long long v = 0;
+    for (int i = a.len - 1; i >= 0; i--) v = v * ISI_BIG_BASE + a.d[i];
+    return a.sign < 0 ? -v : v;
+}
+
+/* Passa pelo texto decimal porque o strtod arredonda corretamente, como o
+ * interpretador; somar os dígitos em double arredondaria a cada passo. */
+static inline double isi_big_to_real(isi_big a) {
+    char* s = malloc(a.len * 9 + 3);
+    char* p = s;
+    if (a.sign < 0) *p++ = '-';
+    p += sprintf(p, "%u", a.len > 0 ? a.d[a.len - 1] : 0);
+    for (int i = a.len - 2; i >= 0; i--) p += sprintf(p, "%09u", a.d[i]);
+    double v = strtod(s, NULL);
+    free(s);
+    return v;
+}
+
+static inline int isi_big_cmp_abs(isi_big a, isi_big b) {
+    if (a.len != b.len) return a.len < b.len ? -1 : 1;
+    for (int i = a.len - 1; i >= 0; i--) {
+        if (a.d[i] != b.d[i]) return a.d[i] < b.d[i] ? -1 : 1;
+    }
+    return 0;
+}
+
+static inline int isi_big_cmp(isi_big a, isi_big b) {
+    if (a.sign != b.sign) return a.sign < b.sign ? -1 : 1;
+    int c = isi_big_cmp_abs(a, b);
+    return a.sign < 0 ? -c : c;
+}
+
+static inline isi_big isi_big_add_abs(isi_big a, isi_big b) {
+    int n = (a.len > b.len ? a.len : b.len) + 1;
+    isi_big r = isi_big_alloc(n);
+    unsigned long long carry = 0;
+    for (int i = 0; i < n; i++) {
+        unsigned long long s = carry;
+        if (i < a.len) s += a.d[i];
+        if (i < b.len) s += b.d[i];
+        r.d[i] = s % ISI_BIG_BASE;
+        carry = s / ISI_BIG_BASE;
+    }
+    r.sign = 1;
+    isi_big_trim(&r);
+    return r;
+}
+
+/* Assume |a| >= |b|. */
+static inline isi_big isi_big_sub_abs(isi_big a, isi_big b) {
+    isi_big r = isi_big_alloc(a.len);
+    long long borrow = 0;
+    for (int i = 0; i < a.len; i++) {
+        long long s = (long long) a.d[i] - borrow - (i < b.len ? b.d[i] : 0);
+        if (s < 0) {
+            s += ISI_BIG_BASE;
+            borrow = 1;
+        } else {
+            borrow = 0;
+        }
+        r.d[i] = s;
+    }
+    r.sign = 1;
+    isi_big_trim(&r);
+    return r;
+}
+
+static inline isi_big isi_big_add(isi_big a, isi_big b) {
+    if (a.sign == 0) return isi_big_copy(b);
+    if (b.sign == 0) return isi_big_copy(a);
+
+    isi_big r;
+    if (a.sign == b.sign) {
+        r = isi_big_add_abs(a, b);
+        r.sign = a.sign;
+    } else if (isi_big_cmp_abs(a, b) >= 0) {
+        r = isi_big_sub_abs(a, b);
+        if (r.len > 0) r.sign = a.sign;
+    } else {
+        r = isi_big_sub_abs(b, a);
+        r.sign = b.sign;
+    }
+    return r;
+}
+
+static inline isi_big isi_big_sub(isi_big a, isi_big b) {
+    b.sign = -b.sign;
+    return isi_big_add(a, b);
+}
+
+static inline isi_big isi_big_mul(isi_big a, isi_big b) {
+    isi_big r = isi_big_alloc(a.len + b.len);
+    for (int i = 0; i < a.len; i++) {
+        unsigned long long carry = 0;
+        for (int j = 0; j < b.len || carry; j++) {
+            unsigned long long cur = r.d[i + j] + carry;
+            if (j < b.len) cur += (unsigned long long) a.d[i] * b.d[j];
+            r.d[i + j] = cur % ISI_BIG_BASE;
+            carry = cur / ISI_BIG_BASE;
+        }
+    }
+    r.sign = a.sign * b.sign;
+    isi_big_trim(&r);
+    return r;
+}
+
+static inline isi_big isi_big_mul_small(isi_big a, unsigned int m) {
+    isi_big r = isi_big_alloc(a.len + 1);
+    unsigned long long carry = 0;
+    for (int i = 0; i <= a.len; i++) {
+        unsigned long long cur = carry;
+        if (i < a.len) cur += (unsigned long long) a.d[i] * m;
+        r.d[i] = cur % ISI_BIG_BASE;
+        carry = cur / ISI_BIG_BASE;
+    }
+    r.sign = 1;
+    isi_big_trim(&r);
+    return r;
+}
+
+static inline void isi_big_divmod(isi_big a, isi_big b, isi_big* quo, isi_big* rem, int line) {
+    if (b.sign == 0) isi_trap("division by zero", line);
+
+    isi_big q = isi_big_alloc(a.len);
+    isi_big r = isi_big_alloc(0);
+    isi_big babs = b;
+    babs.sign = 1;
+
+    for (int i = a.len - 1; i >= 0; i--) {
+        /* r = r * BASE + a.d[i] */
+        isi_big cur = isi_big_alloc(r.len + 1);
+        cur.d[0] = a.d[i];
+        memcpy(cur.d + 1, r.d, r.len * sizeof(unsigned int));
+        cur.sign = 1;
+        isi_big_trim(&cur);
+        isi_big_free(r);
+
+        /* Maior dígito x tal que |b| * x <= cur. */
+        unsigned int lo = 0, hi = ISI_BIG_BASE - 1;
+        while (lo < hi) {
+            unsigned int mid = lo + (hi - lo + 1) / 2;
+            isi_big t = isi_big_mul_small(babs, mid);
+            if (isi_big_cmp_abs(t, cur) <= 0) lo = mid;
+            else hi = mid - 1;
+            isi_big_free(t);
+        }
+
+        isi_big t = isi_big_mul_small(babs, lo);
+        q.d[i] = lo;
+        r = isi_big_sub(cur, t);
+        isi_big_free(t);
+        isi_big_free(cur);
+    }
+
+    q.sign = a.sign * b.sign;
+    isi_big_trim(&q);
+    if (r.len > 0) r.sign = a.sign;
+
+    *quo = q;
+    *rem = r;
+}
+
+static inline isi_big isi_big_div(isi_big a, isi_big b, int line) {
+    isi_big q, r;
+    isi_big_divmod(a, b, &q, &r, line);
+    isi_big_free(r);
+    return q;
+}
+
+/* O `div` arredonda o quociente para baixo, como o isi_floordivi. */
+static inline isi_big isi_big_floordiv(isi_big a, isi_big b, int line) {
+    isi_big q, r;
+    isi_big_divmod(a, b, &q, &r, line);
+    if (r.len > 0 && a.sign != b.sign) {
+        isi_big one = isi_big_from_int(1);
+        isi_big t = isi_big_sub(q, one);
+        isi_big_free(q);
+        isi_big_free(one);
+        q = t;
+    }
+    isi_big_free(r);
+    return q;
+}
+
+static inline isi_big isi_big_mod(isi_big a, isi_big b, int line) {
+    isi_big q, r;
+    isi_big_divmod(a, b, &q, &r, line);
+    isi_big_free(q);
+    return r;
+}
+
+/* Como no interpretador, o expoente deve ser um inteiro de 32 bits sem
+ * sinal. */
+static inline isi_big isi_big_pow(isi_big a, isi_big b, int line) {
+    if (b.sign < 0) isi_trap("negative exponent", line);
+    if (b.len > 2 || isi_big_to_int(b) > 0xFFFFFFFFull) isi_trap("exponent too large", line);
+    unsigned long long e = isi_big_to_int(b);
+    isi_big r = isi_big_from_int(1);
+    isi_big base = isi_big_copy(a);
+    while (e > 0) {
+        if (e & 1) {
+            isi_big t = isi_big_mul(r, base);
+            isi_big_free(r);
+            r = t;
+        }
+        e >>= 1;
+        if (e > 0) {
+            isi_big t = isi_big_mul(base, base);
+            isi_big_free(base);
+            base = t;
+        }
+    }
+    isi_big_free(base);
+    return r;
+}
+
+static inline int isi_big_eq(isi_big a, isi_big b) { return isi_big_cmp(a, b) == 0; }
+static inline int isi_big_neq(isi_big a, isi_big b) { return isi_big_cmp(a, b) != 0; }
+static inline int isi_big_lt(isi_big a, isi_big b) { return isi_big_cmp(a, b) < 0; }
+static inline int isi_big_gt(isi_big a, isi_big b) { return isi_big_cmp(a, b) > 0; }
+static inline int isi_big_leq(isi_big a, isi_big b) { return isi_big_cmp(a, b) <= 0; }
+static inline int isi_big_geq(isi_big a, isi_big b) { return isi_big_cmp(a, b) >= 0; }
+
+static inline isi_big isi_big_parse(const char* s, int line) {
+    char* t = isi_str_number(s, 0, "BigInt", line);
+    isi_big r = isi_big_from_str(t);
+    free(t);
+    return r;
+}
+
+static inline isi_big isi_big_read(int line) {
+    char* s = isi_read_number(0, line);
+    isi_big r = isi_big_from_str(s);
+    free(s);
+    return r;
+}
@@ -1,10 +1,403 @@
 use std::{collections::HashMap, io::Write};
 
 use isic_front::{
-    ast::{Expr, Ident, IsiProgram},
+    ast::{BinaryOp, Contract, ContractKind, Expr, ExternFn, Ident, IsiProgram, Statement},
+    span::{LineIndex, Span},
     visitor::IsiVisitor,
 };
-use isic_middle::{CheckError, IsiType, SymbolInfo};
+use isic_middle::{
+    builtins::{self, Builtin},
+    ops::{self, OpEval},
+    format::{self, FmtPiece},
+    ArithMode, CheckError, InputMode, RuntimeOptions, IsiType, SymbolInfo,
+};
+
+use crate::mangle::mangle;
+
+/// Funções de runtime usadas pelas operações com OpEval::Runtime e
+/// OpEval::Checked da tabela de operadores e pelas funções de conversão.
+///
+/// As operações com `int` seguem exatamente o que o interpretador faz,
+/// de acordo com o ISI_ARITH_CHECKED definido no cabeçalho.
+const RUNTIME: &str = r#"/* O tente mais interno sendo executado, se houver. Um erro dentro dele
+ * volta para o capture, com a descrição do erro em isi_error. */
+static jmp_buf* isi_try = NULL;
+static char isi_error[256];
+
+static void isi_trap(const char* what, int line) {
+    if (isi_try) {
+        snprintf(isi_error, sizeof isi_error, "%s at line %d", what, line);
+        longjmp(*isi_try, 1);
+    }
+    fflush(stdout);
+    fprintf(stderr, "%s at line %d\n", what, line);
+    exit(1);
+}
+
+static inline long long isi_add(long long a, long long b, int line) {
+    long long r;
+    if (__builtin_add_overflow(a, b, &r) && ISI_ARITH_CHECKED) isi_trap("integer overflow", line);
+    return r;
+}
+
+static inline long long isi_sub(long long a, long long b, int line) {
+    long long r;
+    if (__builtin_sub_overflow(a, b, &r) && ISI_ARITH_CHECKED) isi_trap("integer overflow", line);
+    return r;
+}
+
+static inline long long isi_mul(long long a, long long b, int line) {
+    long long r;
+    if (__builtin_mul_overflow(a, b, &r) && ISI_ARITH_CHECKED) isi_trap("integer overflow", line);
+    return r;
+}
+
+static inline long long isi_div(long long a, long long b, int line) {
+    if (b == 0) isi_trap("division by zero", line);
+    if (a == LLONG_MIN && b == -1) {
+        if (ISI_ARITH_CHECKED) isi_trap("integer overflow", line);
+        return LLONG_MIN;
+    }
+    return a / b;
+}
+
+/* O `div` arredonda o quociente para baixo, e não para zero como o `/`. */
+static inline long long isi_floordivi(long long a, long long b, int line) {
+    long long q = isi_div(a, b, line);
+    if (b != -1 && a % b != 0 && (a < 0) != (b < 0)) q--;
+    return q;
+}
+
+static inline long long isi_mod(long long a, long long b, int line) {
+    if (b == 0) isi_trap("division by zero", line);
+    if (a == LLONG_MIN && b == -1) {
+        if (ISI_ARITH_CHECKED) isi_trap("integer overflow", line);
+        return 0;
+    }
+    return a % b;
+}
+
+static inline long long isi_shl(long long a, long long b, int line) {
+    if (b < 0 || b > 63) {
+        if (ISI_ARITH_CHECKED) isi_trap("integer overflow", line);
+        b &= 63;
+    }
+    return (long long) ((unsigned long long) a << b);
+}
+
+static inline long long isi_shr(long long a, long long b, int line) {
+    if (b < 0 || b > 63) {
+        if (ISI_ARITH_CHECKED) isi_trap("integer overflow", line);
+        b &= 63;
+    }
+    return a >> b;
+}
+
+static inline long long isi_powi(long long b, long long e, int line) {
+    if (e < 0) {
+        if (b == 0) isi_trap("division by zero", line);
+        if (b == 1) return 1;
+        if (b == -1) return e % 2 == 0 ? 1 : -1;
+        return 0;
+    }
+    long long r = 1;
+    while (e > 0) {
+        if (e & 1) r = isi_mul(r, b, line);
+        e >>= 1;
+        if (e > 0) b = isi_mul(b, b, line);
+    }
+    return r;
+}
+
+static inline long long isi_absi(long long a, int line) {
+    if (a == LLONG_MIN) {
+        if (ISI_ARITH_CHECKED) isi_trap("integer overflow", line);
+        return LLONG_MIN;
+    }
+    return a < 0 ? -a : a;
+}
+
+static inline long long isi_mini(long long a, long long b) {
+    return a < b ? a : b;
+}
+
+static inline long long isi_maxi(long long a, long long b) {
+    return a > b ? a : b;
+}
+
+static inline float isi_floordivf(float a, float b) {
+    return floorf(a / b);
+}
+
+static inline double isi_floordiv(double a, double b) {
+    return floor(a / b);
+}
+
+static inline char* isi_concat(const char* a, const char* b) {
+    char* r = malloc(strlen(a) + strlen(b) + 1);
+    strcpy(r, a);
+    strcat(r, b);
+    return r;
+}
+
+static inline int isi_streq(const char* a, const char* b) {
+    return strcmp(a, b) == 0;
+}
+
+static inline int isi_strneq(const char* a, const char* b) {
+    return strcmp(a, b) != 0;
+}
+
+/* Converte um real para int como o `as` do Rust: valores fora do intervalo
+ * saturam, e NaN vira 0. */
+static inline long long isi_real_to_int(double x) {
+    if (x != x) return 0;
+    if (x >= 9223372036854775808.0) return LLONG_MAX;
+    if (x <= -9223372036854775808.0) return LLONG_MIN;
+    return (long long) x;
+}
+
+static inline char* isi_int_to_str(long long x) {
+    char* r = malloc(32);
+    snprintf(r, 32, "%lld", x);
+    return r;
+}
+
+/* Números de ponto flutuante são escritos com a menor quantidade de
+ * dígitos que ainda representa o mesmo valor, como no interpretador. */
+static inline char* isi_real_to_str(double x) {
+    char* r = malloc(32);
+    if (isnan(x)) {
+        strcpy(r, "nan");
+    } else if (isinf(x)) {
+        strcpy(r, x < 0 ? "-inf" : "inf");
+    } else {
+        for (int p = 1; p <= 17; p++) {
+            snprintf(r, 32, "%.*g", p, x);
+            if (strtod(r, NULL) == x) break;
+        }
+        /* Escreve por extenso o que o %g escreveria como 1.5e+02. */
+        char* e = strchr(r, 'e');
+        if (e && atoi(e + 1) >= 0 && atoi(e + 1) < 17) snprintf(r, 32, "%.*g", atoi(e + 1) + 1, x);
+    }
+    return r;
+}
+
+static inline char* isi_float_to_str(float x) {
+    char* r = malloc(32);
+    if (isnan(x)) {
+        strcpy(r, "nan");
+    } else if (isinf(x)) {
+        strcpy(r, x < 0 ? "-inf" : "inf");
+    } else {
+        for (int p = 1; p <= 9; p++) {
+            snprintf(r, 32, "%.*g", p, x);
+            if (strtof(r, NULL) == x) break;
+        }
+        /* Escreve por extenso o que o %g escreveria como 1.5e+02. */
+        char* e = strchr(r, 'e');
+        if (e && atoi(e + 1) >= 0 && atoi(e + 1) < 9) snprintf(r, 32, "%.*g", atoi(e + 1) + 1, x);
+    }
+    return r;
+}
+
+/* O sinal de um NaN não é escrito, como no interpretador. */
+static inline double isi_fmt_real(double x) {
+    return isnan(x) ? fabs(x) : x;
+}
+
+/* Argumentos do programa, sem o nome do executável. */
+static int isi_argc;
+static char** isi_argv;
+
+static inline long long isi_numargs(void) {
+    return isi_argc - 1;
+}
+
+static inline char* isi_arg(long long i, int line) {
+    if (i < 0 || i >= isi_argc - 1) isi_trap("index out of bounds", line);
+    return isi_argv[i + 1];
+}
+
+static inline void isi_exit(long long code) {
+    exit((int) code);
+}
+
+/* Gerador de números pseudoaleatórios (SplitMix64), igual ao do
+ * interpretador. Sem semente, a sequência é a de isi_rng_seed(0). */
+static unsigned long long isi_rng_state = 0;
+
+static inline void isi_rng_seed(long long seed) {
+    isi_rng_state = (unsigned long long) seed;
+}
+
+static inline unsigned long long isi_rng_next(void) {
+    unsigned long long z = (isi_rng_state += 0x9E3779B97F4A7C15ULL);
+    z = (z ^ (z >> 30)) * 0xBF58476D1CE4E5B9ULL;
+    z = (z ^ (z >> 27)) * 0x94D049BB133111EBULL;
+    return z ^ (z >> 31);
+}
+
+/* Sorteia um número entre min e max, inclusive, descartando os valores
+ * que favoreceriam alguns números. */
+static inline long long isi_rng_int(long long min, long long max, int line) {
+    if (min > max) isi_trap("invalid range", line);
+    unsigned long long size = (unsigned long long) max - (unsigned long long) min + 1;
+    if (size == 0) return (long long) isi_rng_next();
+    unsigned long long threshold = -size % size;
+    for (;;) {
+        unsigned long long r = isi_rng_next();
+        if (r >= threshold) return (long long) ((unsigned long long) min + r % size);
+    }
+}
+
+/* Caracteres são code points Unicode, e strings são UTF-8. */
+
+static inline unsigned int isi_char_from_int(long long x) {
+    if (x < 0 || x > 0x10FFFF || (x >= 0xD800 && x <= 0xDFFF)) return 0xFFFD;
+    return (unsigned int) x;
+}
+
+static inline int isi_utf8_put(char* p, unsigned int c) {
+    if (c < 0x80) {
+        p[0] = c;
+        return 1;
+    } else if (c < 0x800) {
+        p[0] = 0xC0 | (c >> 6);
+        p[1] = 0x80 | (c & 0x3F);
+        return 2;
+    } else if (c < 0x10000) {
+        p[0] = 0xE0 | (c >> 12);
+        p[1] = 0x80 | ((c >> 6) & 0x3F);
+        p[2] = 0x80 | (c & 0x3F);
+        return 3;
+    }
+    p[0] = 0xF0 | (c >> 18);
+    p[1] = 0x80 | ((c >> 12) & 0x3F);
+    p[2] = 0x80 | ((c >> 6) & 0x3F);
+    p[3] = 0x80 | (c & 0x3F);
+    return 4;
+}
+
+static inline char* isi_char_to_str(unsigned int c) {
+    char* r = malloc(5);
+    r[isi_utf8_put(r, c)] = '\0';
+    return r;
+}
+
+/* Decodifica o caractere UTF-8 no início de *s, e avança o ponteiro. */
+static inline unsigned int isi_utf8_next(const char** s) {
+    const unsigned char* p = (const unsigned char*) *s;
+    unsigned int c;
+    int n;
+    if (p[0] < 0x80) {
+        c = p[0];
+        n = 1;
+    } else if ((p[0] & 0xE0) == 0xC0) {
+        c = p[0] & 0x1F;
+        n = 2;
+    } else if ((p[0] & 0xF0) == 0xE0) {
+        c = p[0] & 0x0F;
+        n = 3;
+    } else {
+        c = p[0] & 0x07;
+        n = 4;
+    }
+    int i = 1;
+    for (; i < n && p[i]; i++) c = (c << 6) | (p[i] & 0x3F);
+    *s += i;
+    return c;
+}
+
+static inline unsigned int isi_str_index(const char* s, long long i, int line) {
+    if (i < 0) isi_trap("index out of bounds", line);
+    for (; *s && i > 0; i--) isi_utf8_next(&s);
+    if (!*s) isi_trap("index out of bounds", line);
+    return isi_utf8_next(&s);
+}
+"#;
+
+/// Runtime de inteiros de precisão arbitrária, usado pelo tipo
+/// inteirogrande.
+const BIGINT_RUNTIME: &str = include_str!("bigint.c");
+
+/// Runtime das funções embutidas de strings.
+const STRINGS_RUNTIME: &str = include_str!("strings.c");
+
+/// Runtime da entrada lida por `leia`.
+const INPUT_RUNTIME: &str = include_str!("input.c");
+
+/// Runtime das funções embutidas de arquivos.
+const FILES_RUNTIME: &str = include_str!("files.c");
+
+/// Escapa `s` para ser usada dentro de um literal de string do C.
+fn c_escape(s: &str) -> String {
+    let mut out = String::new();
+
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\{:03o}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Um argumento de printf: a expressão, e o prefixo e o sufixo escritos em
+/// volta dela.
+type PrintfArg<'e> = (&'e Expr, String, String);
+
+/// Retorna true se avaliar `expr` pode ter efeitos colaterais, ou seja, se
+/// ela chama alguma função embutida com efeitos colaterais ou alguma função
+/// externa, das quais não sabemos nada.
+fn has_side_effects(expr: &Expr) -> bool {
+    match expr {
+        Expr::BinExpr(bexpr) => has_side_effects(&bexpr.1) || has_side_effects(&bexpr.2),
+        Expr::FnCall(call) => {
+            let name = call.fname.name.as_str();
+            let conversion = matches!(name, "inteiro" | "real" | "inteirogrande" | "caractere" | "texto");
+            let external = !conversion && !builtins::exists(name);
+
+            external || builtins::has_side_effects(name) || call.args.iter().any(has_side_effects)
+        }
+        Expr::Negation(neg) => has_side_effects(&neg.expr),
+        Expr::Ternary(tern) => {
+            has_side_effects(&tern.cond)
+                || has_side_effects(&tern.taken)
+                || has_side_effects(&tern.not_taken)
+        }
+        Expr::Index(idx) => has_side_effects(&idx.target) || has_side_effects(&idx.index),
+        _ => false,
+    }
+}
+
+/// Chama `f` para cada comando em `stmts`, inclusive os que estão dentro
+/// de blocos. Os blocos de teste não são visitados, já que não são
+/// emitidos.
+fn walk_statements<'p>(stmts: &'p [Statement], f: &mut impl FnMut(&'p Statement)) {
+    for stmt in stmts {
+        f(stmt);
+
+        match stmt {
+            Statement::Conditional(cond) => {
+                walk_statements(&cond.taken, f);
+                walk_statements(&cond.not_taken, f);
+            }
+            Statement::WhileLoop(l) => walk_statements(&l.body, f),
+            Statement::DoWhileLoop(l) => walk_statements(&l.body, f),
+            Statement::TryCatch(t) => {
+                walk_statements(&t.body, f);
+                walk_statements(&t.handler, f);
+            }
+            _ => {}
+        }
+    }
+}
 
 /// O emissor de código C da IsiLanguage.
 ///
@@ -20,10 +413,24 @@ pub struct CEmitter<'a, W: Write> {
     program: &'a IsiProgram,
     /// Tabela de tipos das variáveis do programa.
     sym_table: &'a HashMap<Ident, SymbolInfo>,
+    /// Tabela de tipos das expressões do programa.
+    expr_types: &'a HashMap<Span, IsiType>,
+    /// Conversões implícitas decididas pelo type checker.
+    coercions: &'a HashMap<Span, IsiType>,
+    /// Como tratar overflow e entradas inválidas.
+    options: RuntimeOptions,
+    /// Índice de linhas do código fonte, para localizar erros.
+    lines: &'a LineIndex,
     /// Referência a saída onde o código C será escrito.
     output: &'a mut W,
     /// Nível de identação atual do código C.
     id_level: usize,
+    /// Se o programa usa `tente`. Nesse caso, as variáveis são declaradas
+    /// como static, para que mantenham seus valores depois de um longjmp.
+    uses_try: bool,
+    /// Quantas variáveis temporárias já foram criadas, para dar nomes
+    /// únicos às próximas.
+    temps: usize,
 }
 
 impl<'a, W: Write> CEmitter<'a, W> {
@@ -31,13 +438,28 @@ impl<'a, W: Write> CEmitter<'a, W> {
     pub fn new(
         program: &'a IsiProgram,
         sym_table: &'a HashMap<Ident, SymbolInfo>,
+        expr_types: &'a HashMap<Span, IsiType>,
+        coercions: &'a HashMap<Span, IsiType>,
+        options: RuntimeOptions,
+        lines: &'a LineIndex,
         output: &'a mut W,
     ) -> CEmitter<'a, W> {
+        let mut uses_try = false;
+        walk_statements(&program.statements, &mut |stmt| {
+            uses_try |= matches!(stmt, Statement::TryCatch(_));
+        });
+
         CEmitter {
             program,
             sym_table,
+            expr_types,
+            coercions,
+            options,
+            lines,
             output,
             id_level: 4,
+            uses_try,
+            temps: 0,
         }
     }
 
@@ -47,7 +469,7 @@ impl<'a, W: Write> CEmitter<'a, W> {
 
         self.visit_program(self.program);
 
-        self.write_footers();
+        self.write_footers()?;
 
         Ok(())
     }
@@ -56,81 +478,588 @@ impl<'a, W: Write> CEmitter<'a, W> {
         writeln!(self.output, "/* !!! auto-gerado por isic-back !!! */").unwrap();
         writeln!(self.output, "#include <stdio.h>").unwrap();
         writeln!(self.output, "#include <stdlib.h>").unwrap();
-        writeln!(self.output, "").unwrap();
-        writeln!(self.output, "int main() {{").unwrap();
+        writeln!(self.output, "#include <string.h>").unwrap();
+        writeln!(self.output, "#include <math.h>").unwrap();
+        writeln!(self.output, "#include <limits.h>").unwrap();
+        writeln!(self.output, "#include <errno.h>").unwrap();
+        writeln!(self.output, "#include <setjmp.h>").unwrap();
+
+        // Os headers das funções externas. Um header entre <> é do sistema,
+        // e os outros são procurados a partir do diretório do código C.
+        let mut externs = vec![];
+        walk_statements(&self.program.statements, &mut |stmt| {
+            if let Statement::ExternFn(ext) = stmt {
+                externs.push(ext);
+            }
+        });
+
+        let mut headers: Vec<&str> = vec![];
+        for ext in externs {
+            if !headers.contains(&ext.header.0.as_str()) {
+                headers.push(&ext.header.0);
+            }
+        }
+
+        for header in headers {
+            if header.starts_with('<') {
+                writeln!(self.output, "#include {}", header).unwrap();
+            } else {
+                writeln!(self.output, "#include \"{}\"", c_escape(header)).unwrap();
+            }
+        }
+
+        writeln!(self.output).unwrap();
+        writeln!(
+            self.output,
+            "#define ISI_ARITH_CHECKED {}",
+            (self.options.arith == ArithMode::Checked) as u8
+        )
+        .unwrap();
+        writeln!(
+            self.output,
+            "#define ISI_READ_REPROMPT {}",
+            (self.options.input == InputMode::Reprompt) as u8
+        )
+        .unwrap();
+        writeln!(self.output).unwrap();
+        write!(self.output, "{}", RUNTIME).unwrap();
+        writeln!(self.output).unwrap();
+        write!(self.output, "{}", STRINGS_RUNTIME).unwrap();
+        writeln!(self.output).unwrap();
+        write!(self.output, "{}", INPUT_RUNTIME).unwrap();
+
+        // O runtime de inteirogrande é grande, então só é incluído
+        // quando o programa usa o tipo.
+        if self.expr_types.values().any(|ty| *ty == IsiType::BigInt)
+            || self.sym_table.values().any(|sym| sym.ty == IsiType::BigInt)
+        {
+            writeln!(self.output).unwrap();
+            write!(self.output, "{}", BIGINT_RUNTIME).unwrap();
+        }
+        if self.expr_types.values().any(|ty| *ty == IsiType::File)
+            || self.sym_table.values().any(|sym| sym.ty == IsiType::File)
+        {
+            writeln!(self.output).unwrap();
+            write!(self.output, "{}", FILES_RUNTIME).unwrap();
+        }
+        writeln!(self.output).unwrap();
+        // Os parâmetros do main têm o prefixo do runtime, para não esconder
+        // nem serem escondidos por variáveis do programa chamadas argc.
+        writeln!(self.output, "int main(int isi_main_argc, char** isi_main_argv) {{").unwrap();
+        writeln!(self.output, "    isi_argc = isi_main_argc;").unwrap();
+        writeln!(self.output, "    isi_argv = isi_main_argv;").unwrap();
     }
 
-    fn write_footers(&mut self) {
+    fn write_footers(&mut self) -> Result<(), CheckError> {
+        // Os `garante` são verificados quando o programa chega ao fim. Um
+        // programa que termina com `saia` não passa por aqui.
+        if !self.options.strip_asserts {
+            for stmt in &self.program.statements {
+                if let Statement::Contract(c @ Contract { kind: ContractKind::Ensures, .. }) = stmt {
+                    self.emit_contract(c)?;
+                }
+            }
+        }
+
         writeln!(self.output, "}}").unwrap();
+
+        Ok(())
     }
 
     fn pad(&self) -> String {
         " ".repeat(self.id_level)
     }
 
-    fn emit_print(&mut self, call: &isic_front::ast::FnCall) {
-        let arg = &call.args[0];
+    /// O tipo de uma expressão, já considerando conversões implícitas.
+    fn expr_type(&self, expr: &Expr) -> IsiType {
+        let span = expr.get_span();
+
+        match self.coercions.get(&span) {
+            Some(ty) => *ty,
+            None => *self.expr_types.get(&span).unwrap(),
+        }
+    }
+
+    fn c_type(ty: IsiType) -> &'static str {
+        match ty {
+            IsiType::Int => "long long",
+            IsiType::Float => "float",
+            IsiType::Real => "double",
+            IsiType::BigInt => "isi_big",
+            IsiType::Char => "unsigned int",
+            IsiType::String => "char*",
+            IsiType::File => "isi_file*",
+            IsiType::Bool => "int",
+            IsiType::Unit => unreachable!("Unexpected: no C type for Unit"),
+        }
+    }
+
+    /// O código C que converte um valor do tipo `from` para o tipo `to`,
+    /// como um par (prefixo, sufixo) a ser escrito em volta da expressão.
+    /// Usado tanto nas conversões implícitas quanto nas explícitas.
+    fn conversion(from: IsiType, to: IsiType) -> (&'static str, &'static str) {
+        match (from, to) {
+            (from, to) if from == to => ("(", ")"),
+            (IsiType::Bool, IsiType::String) => ("((", ") ? \"true\" : \"false\")"),
+            (IsiType::Int, IsiType::String) => ("isi_int_to_str(", ")"),
+            (IsiType::Float, IsiType::String) => ("isi_float_to_str(", ")"),
+            (IsiType::Real, IsiType::String) => ("isi_real_to_str(", ")"),
+            (IsiType::Int, IsiType::BigInt) => ("isi_big_from_int(", ")"),
+            (IsiType::BigInt, IsiType::Int) => ("((long long) isi_big_to_int(", "))"),
+            (IsiType::BigInt, IsiType::Real) => ("isi_big_to_real(", ")"),
+            (IsiType::BigInt, IsiType::String) => ("isi_big_to_str(", ")"),
+            (IsiType::Int, IsiType::Char) => ("isi_char_from_int(", ")"),
+            (IsiType::Char, IsiType::String) => ("isi_char_to_str(", ")"),
+            (IsiType::Char, IsiType::Int) => ("((long long) (", "))"),
+            (IsiType::Float | IsiType::Real, IsiType::Int) => ("isi_real_to_int(", ")"),
+            (IsiType::Int | IsiType::Real, IsiType::Float) => ("((float) (", "))"),
+            (IsiType::Int | IsiType::Float, IsiType::Real) => ("((double) (", "))"),
+            (from, to) => unreachable!("Unexpected: no conversion from {:?} to {:?}", from, to),
+        }
+    }
+
+    /// As conversões de texto para número, que falham se o texto não é um
+    /// número válido: o nome da função do runtime, que também recebe a
+    /// linha da conversão.
+    fn checked_conversion(from: IsiType, to: IsiType) -> Option<&'static str> {
+        match (from, to) {
+            (IsiType::String, IsiType::Int) => Some("isi_str_to_int"),
+            (IsiType::String, IsiType::Real) => Some("isi_str_to_real"),
+            (IsiType::String, IsiType::BigInt) => Some("isi_big_parse"),
+            _ => None,
+        }
+    }
+
+    /// A ordem em que os operandos de uma operação ou os argumentos de uma
+    /// função são avaliados não é definida em C. Se mais de um deles tem
+    /// efeitos colaterais, como em `aleatorio(1, 6) - aleatorio(1, 6)`, abre
+    /// uma expressão composta (`({ ... })`) que guarda cada um numa
+    /// variável temporária, da esquerda para a direita, como no
+    /// interpretador. Retorna os nomes das variáveis, que são usados no
+    /// lugar dos operandos por `emit_operand`. A expressão é fechada por
+    /// `end_sequenced`.
+    fn begin_sequenced(&mut self, operands: &[&Expr]) -> Result<Option<Vec<String>>, CheckError> {
+        if operands.iter().filter(|e| has_side_effects(e)).count() < 2 {
+            return Ok(None);
+        }
+
+        write!(self.output, "({{ ").unwrap();
+
+        let mut names = vec![];
+
+        for operand in operands {
+            let name = format!("isi_t{}", self.temps);
+            self.temps += 1;
+
+            write!(self.output, "{} {} = ", Self::c_type(self.expr_type(operand)), name).unwrap();
+            self.visit_expr(operand)?;
+            write!(self.output, "; ").unwrap();
+
+            names.push(name);
+        }
+
+        Ok(Some(names))
+    }
+
+    /// Emite o `i`-ésimo operando, ou a variável em que ele foi guardado por
+    /// `begin_sequenced`.
+    fn emit_operand(&mut self, temps: &Option<Vec<String>>, i: usize, expr: &Expr) -> Result<(), CheckError> {
+        match temps {
+            Some(names) => {
+                write!(self.output, "{}", names[i]).unwrap();
 
-        match arg {
-            Expr::Ident(ref ident) => {
-                let sym = self.sym_table.get(ident).unwrap();
-
-                let fmt = match sym.ty {
-                    IsiType::Int => "%d",
-                    IsiType::Float => "%f",
-                    IsiType::String => "%s",
-                    _ => todo!(),
-                };
-
-                writeln!(
-                    self.output,
-                    "{}printf(\"{}\\n\", {});",
-                    self.pad(),
-                    fmt,
-                    ident.name
-                )
-                .unwrap();
+                Ok(())
             }
-            Expr::ImmInt(ref imm) => {
-                writeln!(self.output, "{}printf(\"%d\\n\", {});", self.pad(), imm.0).unwrap();
+            None => self.visit_expr(expr),
+        }
+    }
+
+    fn end_sequenced(&mut self, temps: &Option<Vec<String>>) {
+        if temps.is_some() {
+            write!(self.output, "; }})").unwrap();
+        }
+    }
+
+    fn emit_conversion(&mut self, call: &isic_front::ast::FnCall) -> Result<(), CheckError> {
+        let arg = &call.args[0];
+        let from = self.expr_type(arg);
+
+        let to = match call.fname.name.as_str() {
+            "inteiro" => IsiType::Int,
+            "real" => IsiType::Real,
+            "inteirogrande" => IsiType::BigInt,
+            "caractere" => IsiType::Char,
+            _ => IsiType::String,
+        };
+
+        if let Some(func) = Self::checked_conversion(from, to) {
+            write!(self.output, "{}(", func).unwrap();
+            self.visit_expr(arg)?;
+            write!(self.output, ", {})", self.lines.line(call.get_span().start)).unwrap();
+
+            return Ok(());
+        }
+
+        let (prefix, suffix) = Self::conversion(from, to);
+
+        write!(self.output, "{}", prefix).unwrap();
+
+        self.visit_expr(arg)?;
+
+        write!(self.output, "{}", suffix).unwrap();
+
+        Ok(())
+    }
+
+    /// Emite uma chamada de função usada como expressão: uma função
+    /// embutida, uma conversão ou uma função externa.
+    fn emit_call(&mut self, call: &isic_front::ast::FnCall) -> Result<(), CheckError> {
+        let name = call.fname.name.as_str();
+        let args: Vec<_> = call.args.iter().map(|arg| self.expr_type(arg)).collect();
+
+        let builtin = builtins::lookup(name, &args);
+
+        let func = match builtin.map(|b| b.eval) {
+            Some(OpEval::Runtime(func) | OpEval::Checked(func)) => func,
+            Some(_) => unreachable!(),
+            None if matches!(name, "inteiro" | "real" | "inteirogrande" | "caractere" | "texto") => {
+                return self.emit_conversion(call);
             }
-            Expr::ImmFloat(ref imm) => {
-                writeln!(self.output, "{}printf(\"%f\\n\", {});", self.pad(), imm.0).unwrap();
+            // As funções externas são chamadas diretamente, com o nome
+            // dado na declaração.
+            None => name,
+        };
+
+        let temps = self.begin_sequenced(&call.args.iter().collect::<Vec<_>>())?;
+
+        write!(self.output, "{}(", func).unwrap();
+
+        for (i, arg) in call.args.iter().enumerate() {
+            if i > 0 {
+                write!(self.output, ", ").unwrap();
             }
-            Expr::ImmString(ref imm) => {
-                writeln!(self.output, "{}printf(\"{}\\n\");", self.pad(), imm.0).unwrap();
+
+            self.emit_operand(&temps, i, arg)?;
+        }
+
+        if let Some(Builtin { eval: OpEval::Checked(_), .. }) = builtin {
+            write!(self.output, ", {}", self.lines.line(call.get_span().start)).unwrap();
+        }
+
+        write!(self.output, ")").unwrap();
+
+        self.end_sequenced(&temps);
+
+        Ok(())
+    }
+
+    /// Emite um printf com o formato `fmt`, e os argumentos dados como
+    /// pares (expressão, prefixo, sufixo). Se `stream` for dado, emite um
+    /// fprintf para ele.
+    ///
+    /// Todos os argumentos são avaliados antes de qualquer coisa ser
+    /// escrita, como no interpretador: um erro num deles não deixa a saída
+    /// pela metade. Se mais de um tem efeitos colaterais, eles são avaliados
+    /// da esquerda para a direita por `begin_sequenced`.
+    fn emit_printf(
+        &mut self,
+        stream: Option<&str>,
+        fmt: &str,
+        args: &[PrintfArg],
+    ) -> Result<(), CheckError> {
+        if fmt.is_empty() {
+            return Ok(());
+        }
+
+        write!(self.output, "{}", self.pad()).unwrap();
+
+        let exprs: Vec<&Expr> = args.iter().map(|(arg, _, _)| *arg).collect();
+        let temps = self.begin_sequenced(&exprs)?;
+
+        match stream {
+            Some(stream) => write!(self.output, "fprintf({}, \"{}\"", stream, fmt),
+            None => write!(self.output, "printf(\"{}\"", fmt),
+        }
+        .unwrap();
+
+        for (i, (arg, prefix, suffix)) in args.iter().enumerate() {
+            write!(self.output, ", {}", prefix).unwrap();
+
+            self.emit_operand(&temps, i, arg)?;
+
+            write!(self.output, "{}", suffix).unwrap();
+        }
+
+        write!(self.output, ")").unwrap();
+
+        self.end_sequenced(&temps);
+
+        writeln!(self.output, ";").unwrap();
+
+        Ok(())
+    }
+
+    /// Emite o printf de uma sequência de partes, cada uma com um trecho do
+    /// formato e, opcionalmente, um argumento.
+    fn emit_printfs(
+        &mut self,
+        stream: Option<&str>,
+        parts: Vec<(String, Option<PrintfArg>)>,
+    ) -> Result<(), CheckError> {
+        let mut fmt = String::new();
+        let mut args = vec![];
+
+        for (piece, arg) in parts {
+            args.extend(arg);
+            fmt.push_str(&piece);
+        }
+
+        self.emit_printf(stream, &fmt, &args)
+    }
+
+    /// O formato do printf e o prefixo e o sufixo que escrevem um valor do
+    /// tipo `ty` da mesma forma que o interpretador.
+    fn print_spec(ty: IsiType) -> (&'static str, &'static str, &'static str) {
+        match ty {
+            IsiType::Int => ("%lld", "", ""),
+            IsiType::Float => ("%s", "isi_float_to_str(", ")"),
+            IsiType::Real => ("%s", "isi_real_to_str(", ")"),
+            IsiType::BigInt => ("%s", "isi_big_to_str(", ")"),
+            IsiType::Char => ("%s", "isi_char_to_str(", ")"),
+            IsiType::String => ("%s", "", ""),
+            IsiType::Bool => ("%s", "(", ") ? \"true\" : \"false\""),
+            ty => unreachable!("Unexpected: writing a value of type {:?}", ty),
+        }
+    }
+
+    /// Emite uma verificação de `cond`: se ela for falsa, `header` emite a
+    /// primeira linha do erro, e em seguida são escritos no stderr os
+    /// valores das variáveis da condição, e o programa é interrompido.
+    fn emit_check(
+        &mut self,
+        cond: &Expr,
+        header: impl FnOnce(&mut Self) -> Result<(), CheckError>,
+    ) -> Result<(), CheckError> {
+        write!(self.output, "{}if (!(", self.pad()).unwrap();
+        self.visit_expr(cond)?;
+        writeln!(self.output, ")) {{").unwrap();
+
+        self.id_level += 4;
+
+        writeln!(self.output, "{}fflush(stdout);", self.pad()).unwrap();
+
+        header(self)?;
+
+        // As variáveis são escritas com o tipo declarado, sem as conversões
+        // que a condição possa ter feito. O tipo vem de expr_types, e não da
+        // tabela de símbolos, que não tem a variável do capture.
+        for id in cond.vars() {
+            let ty = *self.expr_types.get(&id.span).unwrap();
+            if ty == IsiType::File {
+                continue;
             }
-            _ => todo!(),
+
+            let (spec, prefix, suffix) = Self::print_spec(ty);
+
+            write!(
+                self.output,
+                "{}fprintf(stderr, \"  {} = {}\\n\", {}",
+                self.pad(),
+                id.name,
+                spec,
+                prefix
+            )
+            .unwrap();
+            self.visit_ident(id)?;
+            writeln!(self.output, "{});", suffix).unwrap();
         }
+
+        writeln!(self.output, "{}exit(1);", self.pad()).unwrap();
+
+        self.id_level -= 4;
+
+        writeln!(self.output, "{}}}", self.pad()).unwrap();
+
+        Ok(())
     }
 
-    fn emit_scan(&mut self, call: &isic_front::ast::FnCall) {
-        // TODO(edu): C shenanigans, precisamos mallocar e depois
-        // freear no caso de ser uma string...
+    /// Emite o `afirme`, que escreve a linha e a mensagem dada quando a
+    /// condição é falsa.
+    fn emit_assert(&mut self, call: &isic_front::ast::FnCall) -> Result<(), CheckError> {
+        if self.options.strip_asserts {
+            return Ok(());
+        }
 
-        let arg = &call.args[0];
+        let line = self.lines.line(call.get_span().start);
+
+        self.emit_check(&call.args[0], |this| {
+            let mut parts = vec![(format!("assertion failed at line {}", line), None)];
 
-        match arg {
-            Expr::Ident(ref ident) => {
-                let sym = self.sym_table.get(ident).unwrap();
-
-                let fmt = match sym.ty {
-                    IsiType::Int => "%d",
-                    IsiType::Float => "%f",
-                    IsiType::String => "%s",
-                    _ => todo!(),
-                };
-
-                writeln!(
-                    self.output,
-                    "{}scanf(\"{}\", &{});",
-                    self.pad(),
-                    fmt,
-                    ident.name
-                )
-                .unwrap();
+            if let Some(msg) = call.args.get(1) {
+                parts.push((": %s".to_string(), Some((msg, String::new(), String::new()))));
             }
-            _ => todo!(),
+
+            parts.push(("\\n".to_string(), None));
+
+            this.emit_printfs(Some("stderr"), parts)
+        })
+    }
+
+    /// Emite a verificação do invariante de um laço, usando o contador de
+    /// iterações `isi_iter` declarado em volta do laço.
+    fn emit_invariant(&mut self, inv: &Expr) -> Result<(), CheckError> {
+        let line = self.lines.line(inv.get_span().start);
+
+        self.emit_check(inv, |this| {
+            writeln!(
+                this.output,
+                "{}fprintf(stderr, \"loop invariant violated at line {}, iteration %lld\\n\", isi_iter);",
+                this.pad(),
+                line
+            )
+            .unwrap();
+
+            Ok(())
+        })
+    }
+
+    /// Emite a verificação de uma pré ou pós-condição.
+    fn emit_contract(&mut self, contract: &isic_front::ast::Contract) -> Result<(), CheckError> {
+        let what = match contract.kind {
+            ContractKind::Requires => "precondition",
+            ContractKind::Ensures => "postcondition",
+        };
+        let line = self.lines.line(contract.span.start);
+
+        self.emit_check(&contract.cond, |this| {
+            writeln!(
+                this.output,
+                "{}fprintf(stderr, \"{} violated at line {}\\n\");",
+                this.pad(),
+                what,
+                line
+            )
+            .unwrap();
+
+            Ok(())
+        })
+    }
+
+    /// Emite o `escreva`, o `escrevasl` e o `escrevaarq`. Todos os valores
+    /// são escritos como strings, exceto os `int`s, para que a saída seja
+    /// igual à do interpretador.
+    fn emit_print(&mut self, call: &isic_front::ast::FnCall) -> Result<(), CheckError> {
+        let name = call.fname.name.as_str();
+
+        // escrevaarq recebe o arquivo antes dos valores.
+        let values = if name == "escrevaarq" {
+            &call.args[1..]
+        } else {
+            &call.args[..]
+        };
+
+        let mut parts = vec![];
+
+        for arg in values {
+            let (spec, prefix, suffix) = Self::print_spec(self.expr_type(arg));
+
+            parts.push((spec.to_string(), Some((arg, prefix.to_string(), suffix.to_string()))));
+        }
+
+        if name != "escrevasl" {
+            parts.push(("\\n".to_string(), None));
+        }
+
+        if name != "escrevaarq" {
+            return self.emit_printfs(None, parts);
+        }
+
+        // O arquivo vai para uma variável, para que a expressão seja
+        // avaliada uma vez só.
+        writeln!(self.output, "{}{{", self.pad()).unwrap();
+        self.id_level += 4;
+
+        let line = self.lines.line(call.get_span().start);
+
+        write!(self.output, "{}FILE* isi_f = isi_file_get(", self.pad()).unwrap();
+        self.visit_expr(&call.args[0])?;
+        writeln!(self.output, ", {});", line).unwrap();
+
+        self.emit_printfs(Some("isi_f"), parts)?;
+
+        writeln!(self.output, "{}isi_file_check(isi_f, {});", self.pad(), line).unwrap();
+
+        self.id_level -= 4;
+        writeln!(self.output, "{}}}", self.pad()).unwrap();
+
+        Ok(())
+    }
+
+    /// Emite o `escrevaf`. O formato já foi validado pelo type checker, e
+    /// os argumentos já foram convertidos para os tipos esperados.
+    fn emit_printf_call(&mut self, call: &isic_front::ast::FnCall) -> Result<(), CheckError> {
+        let Expr::ImmString(ref lit) = call.args[0] else {
+            unreachable!()
+        };
+
+        let mut parts = vec![];
+        let mut call_args = call.args[1..].iter();
+
+        for piece in format::parse(&lit.0).unwrap() {
+            let spec = match piece {
+                FmtPiece::Text(t) => {
+                    parts.push((c_escape(&t).replace('%', "%%"), None));
+                    continue;
+                }
+                FmtPiece::Spec(spec) => spec,
+            };
+
+            let arg = call_args.next().unwrap();
+
+            // Strings e caracteres são alinhados pelo runtime, que conta
+            // caracteres, e não bytes como o printf.
+            let (conv, prefix, suffix) = match spec.conv {
+                'd' => (format!("{}lld", spec.c_flags()), String::new(), String::new()),
+                'f' | 'e' => (
+                    format!("{}{}", spec.c_flags(), spec.conv),
+                    "isi_fmt_real(".to_string(),
+                    ")".to_string(),
+                ),
+                conv => {
+                    let to_str = if conv == 'c' { "isi_char_to_str(" } else { "(" };
+
+                    (
+                        "%s".to_string(),
+                        format!("isi_pad({}", to_str),
+                        format!("), {}, {})", spec.width.unwrap_or(0), spec.left as u8),
+                    )
+                }
+            };
+
+            parts.push((conv, Some((arg, prefix, suffix))));
+        }
+
+        self.emit_printfs(None, parts)
+    }
+
+    fn emit_scan(&mut self, call: &isic_front::ast::FnCall) {
+        let line = self.lines.line(call.get_span().start);
+
+        for arg in &call.args {
+            let Expr::Ident(ref ident) = arg else {
+                unreachable!("Unexpected: leia on an expression")
+            };
+            let read = match *self.expr_types.get(&ident.span).unwrap() {
+                IsiType::Int => "isi_read_int",
+                IsiType::Float => "isi_read_float",
+                IsiType::Real => "isi_read_real",
+                IsiType::BigInt => "isi_big_read",
+                IsiType::Char => "isi_read_char",
+                IsiType::String => "isi_read_str",
+                ty => unreachable!("Unexpected: leia on a variable of type {:?}", ty),
+            };
+
+            writeln!(self.output, "{}{} = {}({});", self.pad(), mangle(&ident.name), read, line).unwrap();
         }
     }
 }
@@ -148,7 +1077,7 @@ impl<'a, W: Write> IsiVisitor for CEmitter<'a, W> {
         &mut self,
         lit: &isic_front::ast::FloatLiteral,
     ) -> Result<(), CheckError> {
-        write!(self.output, "{}f", lit.0).unwrap();
+        write!(self.output, "{:?}f", lit.0 as f32).unwrap();
 
         Ok(())
     }
@@ -157,35 +1086,85 @@ impl<'a, W: Write> IsiVisitor for CEmitter<'a, W> {
         &mut self,
         lit: &isic_front::ast::StringLiteral,
     ) -> Result<(), CheckError> {
-        write!(self.output, "{}", lit.0).unwrap();
+        write!(self.output, "\"{}\"", c_escape(&lit.0)).unwrap();
+
+        Ok(())
+    }
+
+    fn visit_char_literal(&mut self, lit: &isic_front::ast::CharLiteral) -> Self::Ret {
+        // Caracteres imprimíveis do ASCII são escritos como literais do
+        // C, para facilitar a leitura; o resto como o code point.
+        match lit.0 {
+            '\\' | '\'' => write!(self.output, "'\\{}'", lit.0).unwrap(),
+            c if c.is_ascii_graphic() || c == ' ' => write!(self.output, "'{}'", c).unwrap(),
+            c => write!(self.output, "{}u", c as u32).unwrap(),
+        }
 
         Ok(())
     }
 
     fn visit_ident(&mut self, id: &Ident) -> Result<(), CheckError> {
-        write!(self.output, "{}", id.name).unwrap();
+        write!(self.output, "{}", mangle(&id.name)).unwrap();
 
         Ok(())
     }
 
     fn visit_decl(&mut self, decl: &isic_front::ast::VarDecl) -> Result<(), CheckError> {
-        let ty = match self.sym_table.get(&decl.var_name).unwrap().ty {
-            IsiType::Int => "int",
-            IsiType::Float => "float",
-            IsiType::String => "char*",
-            _ => todo!(),
+        let ty = Self::c_type(self.sym_table.get(&decl.var_name).unwrap().ty);
+        let storage = if self.uses_try { "static " } else { "" };
+
+        writeln!(self.output, "{}{}{} {};", self.pad(), storage, ty, mangle(&decl.var_name.name)).unwrap();
+
+        Ok(())
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) -> Self::Ret {
+        let coercion = self.coercions.get(&expr.get_span()).copied();
+
+        // Literais promovidos para real são escritos com a precisão
+        // completa, ao invés de passar por um float.
+        if let (Expr::ImmFloat(lit), Some(IsiType::Real)) = (expr, coercion) {
+            write!(self.output, "{:?}", lit.0).unwrap();
+
+            return Ok(());
+        }
+
+        let (prefix, suffix) = match coercion {
+            Some(ty) => Self::conversion(*self.expr_types.get(&expr.get_span()).unwrap(), ty),
+            None => ("", ""),
         };
 
-        writeln!(self.output, "{}{} {};", self.pad(), ty, decl.var_name.name).unwrap();
+        write!(self.output, "{}", prefix).unwrap();
+
+        match expr {
+            Expr::Ident(ident) => self.visit_ident(ident),
+            Expr::ImmInt(imm) => self.visit_int_literal(imm),
+            Expr::ImmFloat(imm) => self.visit_float_literal(imm),
+            Expr::ImmString(imm) => self.visit_string_literal(imm),
+            Expr::ImmChar(imm) => self.visit_char_literal(imm),
+            Expr::BinExpr(bexp) => self.visit_bin_expr(bexp),
+            Expr::FnCall(call) => self.emit_call(call),
+            Expr::Negation(neg) => self.visit_negation(neg),
+            Expr::Ternary(tern) => self.visit_ternary(tern),
+            Expr::Index(idx) => self.visit_index(idx),
+        }?;
+
+        write!(self.output, "{}", suffix).unwrap();
 
         Ok(())
     }
 
     fn visit_fn_call(&mut self, call: &isic_front::ast::FnCall) -> Result<(), CheckError> {
         match call.fname.name.as_str() {
-            "escreva" => self.emit_print(call),
+            "escreva" | "escrevasl" | "escrevaarq" => self.emit_print(call)?,
+            "escrevaf" => self.emit_printf_call(call)?,
+            "afirme" => self.emit_assert(call)?,
             "leia" => self.emit_scan(call),
-            _ => todo!(),
+            _ => {
+                write!(self.output, "{}", self.pad()).unwrap();
+                self.emit_call(call)?;
+                writeln!(self.output, ";").unwrap();
+            }
         };
 
         Ok(())
@@ -195,7 +1174,7 @@ impl<'a, W: Write> IsiVisitor for CEmitter<'a, W> {
         &mut self,
         assignment: &isic_front::ast::Assignment,
     ) -> Result<(), CheckError> {
-        write!(self.output, "{}{} = ", self.pad(), assignment.ident.name).unwrap();
+        write!(self.output, "{}{} = ", self.pad(), mangle(&assignment.ident.name)).unwrap();
 
         self.visit_expr(&assignment.val)?;
 
@@ -205,32 +1184,89 @@ impl<'a, W: Write> IsiVisitor for CEmitter<'a, W> {
     }
 
     fn visit_bin_expr(&mut self, bexpr: &isic_front::ast::BinExpr) -> Result<(), CheckError> {
+        let lhs_ty = self.expr_type(&bexpr.1);
+        let rhs_ty = self.expr_type(&bexpr.2);
+
+        let sig = ops::lookup(bexpr.0, lhs_ty, rhs_ty).unwrap();
+
+        // O `&&` e o `||` do C já avaliam o lado esquerdo primeiro, e fazem
+        // curto-circuito, então OpEval::ShortCircuit não precisa de
+        // tratamento especial.
+        let temps = match sig.eval {
+            OpEval::ShortCircuit => None,
+            _ => self.begin_sequenced(&[&bexpr.1, &bexpr.2])?,
+        };
+
+        if let OpEval::Runtime(func) = sig.eval {
+            // Operações sem equivalente nativo em C viram
+            // chamadas para o runtime.
+            write!(self.output, "{}(", func).unwrap();
+
+            self.emit_operand(&temps, 0, &bexpr.1)?;
+
+            write!(self.output, ", ").unwrap();
+
+            self.emit_operand(&temps, 1, &bexpr.2)?;
+
+            write!(self.output, ")").unwrap();
+
+            self.end_sequenced(&temps);
+
+            return Ok(());
+        }
+
+        if let OpEval::Checked(func) = sig.eval {
+            // Operações que podem falhar recebem também a linha, para
+            // que o runtime possa reportar o erro.
+            write!(self.output, "{}(", func).unwrap();
+
+            self.emit_operand(&temps, 0, &bexpr.1)?;
+
+            write!(self.output, ", ").unwrap();
+
+            self.emit_operand(&temps, 1, &bexpr.2)?;
+
+            write!(self.output, ", {})", self.lines.line(bexpr.get_span().start)).unwrap();
+
+            self.end_sequenced(&temps);
+
+            return Ok(());
+        }
+
         write!(self.output, "(").unwrap();
 
-        self.visit_expr(&bexpr.1)?;
+        self.emit_operand(&temps, 0, &bexpr.1)?;
 
         let op = match bexpr.0 {
-            isic_front::ast::BinaryOp::Add => "+",
-            isic_front::ast::BinaryOp::Sub => "-",
-            isic_front::ast::BinaryOp::Mul => "*",
-            isic_front::ast::BinaryOp::Div => "/",
-            isic_front::ast::BinaryOp::Mod => "%",
-            isic_front::ast::BinaryOp::Gt => ">",
-            isic_front::ast::BinaryOp::Lt => "<",
-            isic_front::ast::BinaryOp::Geq => ">=",
-            isic_front::ast::BinaryOp::Leq => "<=",
-            isic_front::ast::BinaryOp::Eq => "==",
-            isic_front::ast::BinaryOp::Neq => "!=",
-            isic_front::ast::BinaryOp::And => "&&",
-            isic_front::ast::BinaryOp::Or => "||",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div | BinaryOp::IntDiv => "/",
+            BinaryOp::Mod => "%",
+            BinaryOp::Gt => ">",
+            BinaryOp::Lt => "<",
+            BinaryOp::Geq => ">=",
+            BinaryOp::Leq => "<=",
+            BinaryOp::Eq => "==",
+            BinaryOp::Neq => "!=",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+            BinaryOp::BitAnd => "&",
+            BinaryOp::BitOr => "|",
+            BinaryOp::BitXor => "^",
+            BinaryOp::Shl => "<<",
+            BinaryOp::Shr => ">>",
+            BinaryOp::Pow => unreachable!(),
         };
 
         write!(self.output, " {} ", op).unwrap();
 
-        self.visit_expr(&bexpr.2)?;
+        self.emit_operand(&temps, 1, &bexpr.2)?;
 
         write!(self.output, ")").unwrap();
 
+        self.end_sequenced(&temps);
+
         Ok(())
     }
 
@@ -269,6 +1305,19 @@ impl<'a, W: Write> IsiVisitor for CEmitter<'a, W> {
     }
 
     fn visit_while_loop(&mut self, while_loop: &isic_front::ast::WhileLoop) -> Self::Ret {
+        // Com um invariante, o laço fica num bloco com o contador de
+        // iterações, e o invariante é verificado antes do laço e no fim de
+        // cada iteração.
+        let invariant = while_loop.invariant.as_ref().filter(|_| !self.options.strip_asserts);
+
+        if let Some(inv) = invariant {
+            writeln!(self.output, "{}{{", self.pad()).unwrap();
+            self.id_level += 4;
+
+            writeln!(self.output, "{}long long isi_iter = 0;", self.pad()).unwrap();
+            self.emit_invariant(inv)?;
+        }
+
         write!(self.output, "{}while (", self.pad()).unwrap();
 
         self.visit_expr(&while_loop.cond)?;
@@ -281,14 +1330,34 @@ impl<'a, W: Write> IsiVisitor for CEmitter<'a, W> {
             self.visit_statement(stmt)?;
         }
 
+        if let Some(inv) = invariant {
+            writeln!(self.output, "{}isi_iter++;", self.pad()).unwrap();
+            self.emit_invariant(inv)?;
+        }
+
         self.id_level -= 4;
 
         writeln!(self.output, "{}}}", self.pad()).unwrap();
 
+        if invariant.is_some() {
+            self.id_level -= 4;
+            writeln!(self.output, "{}}}", self.pad()).unwrap();
+        }
+
         Ok(())
     }
 
     fn visit_do_while_loop(&mut self, do_while_loop: &isic_front::ast::DoWhileLoop) -> Self::Ret {
+        let invariant = do_while_loop.invariant.as_ref().filter(|_| !self.options.strip_asserts);
+
+        if let Some(inv) = invariant {
+            writeln!(self.output, "{}{{", self.pad()).unwrap();
+            self.id_level += 4;
+
+            writeln!(self.output, "{}long long isi_iter = 0;", self.pad()).unwrap();
+            self.emit_invariant(inv)?;
+        }
+
         writeln!(self.output, "{}do {{", self.pad()).unwrap();
 
         self.id_level += 4;
@@ -296,6 +1365,12 @@ impl<'a, W: Write> IsiVisitor for CEmitter<'a, W> {
         for stmt in &do_while_loop.body {
             self.visit_statement(stmt)?;
         }
+
+        if let Some(inv) = invariant {
+            writeln!(self.output, "{}isi_iter++;", self.pad()).unwrap();
+            self.emit_invariant(inv)?;
+        }
+
         self.id_level -= 4;
 
         write!(self.output, "{}}} while (", self.pad()).unwrap();
@@ -304,6 +1379,11 @@ impl<'a, W: Write> IsiVisitor for CEmitter<'a, W> {
 
         writeln!(self.output, ");").unwrap();
 
+        if invariant.is_some() {
+            self.id_level -= 4;
+            writeln!(self.output, "{}}}", self.pad()).unwrap();
+        }
+
         Ok(())
     }
 
@@ -317,6 +1397,42 @@ impl<'a, W: Write> IsiVisitor for CEmitter<'a, W> {
         Ok(())
     }
 
+    fn visit_ternary(&mut self, tern: &isic_front::ast::Ternary) -> Self::Ret {
+        write!(self.output, "(").unwrap();
+
+        self.visit_expr(&tern.cond)?;
+
+        write!(self.output, " ? ").unwrap();
+
+        self.visit_expr(&tern.taken)?;
+
+        write!(self.output, " : ").unwrap();
+
+        self.visit_expr(&tern.not_taken)?;
+
+        write!(self.output, ")").unwrap();
+
+        Ok(())
+    }
+
+    fn visit_index(&mut self, idx: &isic_front::ast::Index) -> Self::Ret {
+        let temps = self.begin_sequenced(&[&idx.target, &idx.index])?;
+
+        write!(self.output, "isi_str_index(").unwrap();
+
+        self.emit_operand(&temps, 0, &idx.target)?;
+
+        write!(self.output, ", ").unwrap();
+
+        self.emit_operand(&temps, 1, &idx.index)?;
+
+        write!(self.output, ", {})", self.lines.line(idx.get_span().start)).unwrap();
+
+        self.end_sequenced(&temps);
+
+        Ok(())
+    }
+
     fn visit_multi_decl(&mut self, decls: &isic_front::ast::MultiVarDecl) -> Self::Ret {
         for decl in &decls.0 {
             self.visit_decl(decl)?;
@@ -324,4 +1440,83 @@ impl<'a, W: Write> IsiVisitor for CEmitter<'a, W> {
 
         Ok(())
     }
+
+    fn visit_extern_fn(&mut self, _ext: &ExternFn) -> Self::Ret {
+        // O protótipo da função vem do header, incluído no começo do código.
+        Ok(())
+    }
+
+    fn visit_test(&mut self, _test: &isic_front::ast::TestBlock) -> Self::Ret {
+        // Os testes só são executados pelo interpretador, com o isic test.
+        Ok(())
+    }
+
+    fn visit_contract(&mut self, contract: &isic_front::ast::Contract) -> Self::Ret {
+        // Os `garante` são emitidos no fim do main, pelo write_footers.
+        if contract.kind == ContractKind::Ensures || self.options.strip_asserts {
+            return Ok(());
+        }
+
+        self.emit_contract(contract)
+    }
+
+    fn visit_try_catch(&mut self, try_catch: &isic_front::ast::TryCatch) -> Self::Ret {
+        // O isi_trap volta para o setjmp do tente mais interno. O tente
+        // anterior é restaurado ao fim do bloco, com ou sem erro.
+        writeln!(self.output, "{}{{", self.pad()).unwrap();
+        self.id_level += 4;
+
+        writeln!(self.output, "{}jmp_buf isi_jb;", self.pad()).unwrap();
+        writeln!(self.output, "{}jmp_buf* isi_prev = isi_try;", self.pad()).unwrap();
+        writeln!(self.output, "{}isi_try = &isi_jb;", self.pad()).unwrap();
+        writeln!(self.output, "{}if (!setjmp(isi_jb)) {{", self.pad()).unwrap();
+
+        self.id_level += 4;
+
+        for stmt in &try_catch.body {
+            self.visit_statement(stmt)?;
+        }
+
+        writeln!(self.output, "{}isi_try = isi_prev;", self.pad()).unwrap();
+
+        self.id_level -= 4;
+
+        writeln!(self.output, "{}}}", self.pad()).unwrap();
+        writeln!(self.output, "{}else {{", self.pad()).unwrap();
+
+        self.id_level += 4;
+
+        writeln!(self.output, "{}isi_try = isi_prev;", self.pad()).unwrap();
+
+        // A variável do capture só existe dentro dele, como no type checker.
+        // Ela é static pelo mesmo motivo que as outras variáveis: um tente
+        // dentro do capture pode voltar para cá com um longjmp.
+        writeln!(
+            self.output,
+            "{}static char* {} = NULL;",
+            self.pad(),
+            mangle(&try_catch.error_var.name)
+        )
+        .unwrap();
+        writeln!(
+            self.output,
+            "{}{} = isi_str_copy(isi_error, strlen(isi_error));",
+            self.pad(),
+            mangle(&try_catch.error_var.name)
+        )
+        .unwrap();
+
+        for stmt in &try_catch.handler {
+            self.visit_statement(stmt)?;
+        }
+
+        self.id_level -= 4;
+
+        writeln!(self.output, "{}}}", self.pad()).unwrap();
+
+        self.id_level -= 4;
+        writeln!(self.output, "{}}}", self.pad()).unwrap();
+
+        Ok(())
+    }
 }
@@ -0,0 +1,67 @@
+/* Arquivos abertos por abra, no modo "l" (leitura), "e" (escrita) ou "a"
+ * (escrita no fim do arquivo). Os erros são os mesmos do interpretador.
+ *
+ * O valor de um arquivo é um handle, e não o FILE* em si: feche só marca o
+ * handle como fechado, sem liberá-lo, para que qualquer uso do arquivo
+ * depois disso interrompa o programa. */
+
+typedef struct {
+    FILE* f;
+} isi_file;
+
+/* O FILE* de um arquivo ainda aberto. */
+static inline FILE* isi_file_get(isi_file* h, int line) {
+    if (!h || !h->f) isi_trap("invalid file operation", line);
+    return h->f;
+}
+
+static inline isi_file* isi_file_open(const char* path, const char* mode, int line) {
+    const char* m = strcmp(mode, "l") == 0 ? "r"
+        : strcmp(mode, "e") == 0 ? "w"
+        : strcmp(mode, "a") == 0 ? "a"
+        : NULL;
+    if (!m) isi_trap("invalid file mode", line);
+    FILE* f = fopen(path, m);
+    if (!f) isi_trap("could not open file", line);
+    isi_file* h = malloc(sizeof *h);
+    h->f = f;
+    return h;
+}
+
+/* Interrompe o programa se a última operação com o arquivo falhou, como
+ * ler de um arquivo aberto para escrita. */
+static inline void isi_file_check(FILE* f, int line) {
+    if (ferror(f)) isi_trap("invalid file operation", line);
+}
+
+/* Lê a próxima linha do arquivo, sem a quebra de linha. */
+static inline char* isi_file_readline(isi_file* h, int line) {
+    FILE* f = isi_file_get(h, line);
+    size_t n = 0, cap = 64;
+    char* r = malloc(cap);
+    int c;
+    while ((c = getc(f)) != EOF && c != '\n') {
+        if (n + 1 == cap) r = realloc(r, cap *= 2);
+        r[n++] = c;
+    }
+    isi_file_check(f, line);
+    if (c == EOF && n == 0) isi_trap("end of file", line);
+    if (n > 0 && r[n - 1] == '\r') n--;
+    r[n] = '\0';
+    return r;
+}
+
+static inline int isi_file_eof(isi_file* h, int line) {
+    FILE* f = isi_file_get(h, line);
+    int c = getc(f);
+    isi_file_check(f, line);
+    if (c == EOF) return 1;
+    ungetc(c, f);
+    return 0;
+}
+
+static inline void isi_file_close(isi_file* h, int line) {
+    FILE* f = isi_file_get(h, line);
+    h->f = NULL;
+    if (fclose(f) != 0) isi_trap("invalid file operation", line);
+}
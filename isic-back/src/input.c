@@ -0,0 +1,176 @@
+/* Entrada lida por leia: valores separados por espaços, em uma mesma linha
+ * ou em linhas diferentes. Números aceitam tanto a vírgula quanto o ponto
+ * como separador decimal, e entradas inválidas interrompem o programa ou
+ * são lidas novamente, dependendo de ISI_READ_REPROMPT. */
+
+static inline int isi_is_space(int c) {
+    return c == ' ' || c == '\t' || c == '\n' || c == '\r' || c == '\f';
+}
+
+/* Pula os espaços até o próximo caractere. O fim da entrada sempre
+ * interrompe o programa. */
+static inline void isi_read_skip(int line) {
+    int c;
+    do c = getchar(); while (isi_is_space(c));
+    if (c == EOF) isi_trap("unexpected end of input", line);
+    ungetc(c, stdin);
+}
+
+/* Lê caracteres até encontrar um para o qual stop(c) é verdadeiro, ou o
+ * fim da entrada. O caractere que parou a leitura não é consumido. */
+static inline char* isi_read_until(int (*stop)(int)) {
+    size_t n = 0, cap = 16;
+    char* r = malloc(cap);
+    int c;
+    while ((c = getchar()) != EOF && !stop(c)) {
+        if (n + 1 == cap) r = realloc(r, cap *= 2);
+        r[n++] = c;
+    }
+    if (c != EOF) ungetc(c, stdin);
+    r[n] = '\0';
+    return r;
+}
+
+static inline int isi_is_newline(int c) {
+    return c == '\n';
+}
+
+/* Lida com uma entrada inválida: descarta o resto da linha e pede o valor
+ * novamente, ou interrompe o programa. */
+static inline void isi_read_invalid(int line) {
+#if ISI_READ_REPROMPT
+    int c;
+    while ((c = getchar()) != '\n' && c != EOF);
+    printf("invalid input at line %d, try again\n", line);
+#else
+    isi_trap("invalid input", line);
+#endif
+}
+
+static inline const char* isi_read_digits(const char* s) {
+    const char* p = s;
+    while (*p >= '0' && *p <= '9') p++;
+    return p == s ? NULL : p;
+}
+
+/* Verifica se s é um número válido: um inteiro com sinal opcional, ou, se
+ * real, também com parte decimal e expoente. */
+static inline int isi_is_number(const char* s, int real) {
+    if (*s == '+' || *s == '-') s++;
+    if (!(s = isi_read_digits(s))) return 0;
+    if (real && (*s == ',' || *s == '.')) {
+        if (!(s = isi_read_digits(s + 1))) return 0;
+    }
+    if (real && (*s == 'e' || *s == 'E')) {
+        s++;
+        if (*s == '+' || *s == '-') s++;
+        if (!(s = isi_read_digits(s))) return 0;
+    }
+    return *s == '\0';
+}
+
+/* Interrompe a conversão do texto s, que não é um número do tipo ty. */
+static inline void isi_str_invalid(const char* s, const char* ty, int line) {
+    char* what = malloc(strlen(s) + strlen(ty) + 32);
+    sprintf(what, "Cannot convert \"%s\" to %s", s, ty);
+    isi_trap(what, line);
+}
+
+/* Prepara a conversão do texto s para um número: aceita os mesmos números
+ * que a entrada, com espaços nas pontas, e devolve uma cópia sem os espaços
+ * e com o separador decimal trocado por ponto. */
+static inline char* isi_str_number(const char* s, int real, const char* ty, int line) {
+    const char* start = s;
+    while (isi_is_space(*start)) start++;
+    size_t n = strlen(start);
+    while (n > 0 && isi_is_space(start[n - 1])) n--;
+
+    char* r = malloc(n + 1);
+    memcpy(r, start, n);
+    r[n] = '\0';
+    if (!isi_is_number(r, real)) {
+        free(r);
+        isi_str_invalid(s, ty, line);
+    }
+    for (char* p = r; *p; p++) if (*p == ',') *p = '.';
+    return r;
+}
+
+static inline long long isi_str_to_int(const char* s, int line) {
+    char* t = isi_str_number(s, 0, "Int", line);
+    errno = 0;
+    long long r = strtoll(t, NULL, 10);
+    int ok = errno != ERANGE;
+    free(t);
+    if (!ok) isi_str_invalid(s, "Int", line);
+    return r;
+}
+
+static inline double isi_str_to_real(const char* s, int line) {
+    char* t = isi_str_number(s, 1, "Real", line);
+    double r = strtod(t, NULL);
+    free(t);
+    return r;
+}
+
+/* Lê o próximo número válido, com o separador decimal trocado por ponto. */
+static inline char* isi_read_number(int real, int line) {
+    for (;;) {
+        isi_read_skip(line);
+        char* s = isi_read_until(isi_is_space);
+        if (isi_is_number(s, real)) {
+            for (char* p = s; *p; p++) if (*p == ',') *p = '.';
+            return s;
+        }
+        free(s);
+        isi_read_invalid(line);
+    }
+}
+
+static inline long long isi_read_int(int line) {
+    for (;;) {
+        char* s = isi_read_number(0, line);
+        errno = 0;
+        long long r = strtoll(s, NULL, 10);
+        int ok = errno != ERANGE;
+        free(s);
+        /* Um int fora do intervalo também é inválido. */
+        if (ok) return r;
+        isi_read_invalid(line);
+    }
+}
+
+static inline float isi_read_float(int line) {
+    char* s = isi_read_number(1, line);
+    float r = strtof(s, NULL);
+    free(s);
+    return r;
+}
+
+static inline double isi_read_real(int line) {
+    char* s = isi_read_number(1, line);
+    double r = strtod(s, NULL);
+    free(s);
+    return r;
+}
+
+static inline unsigned int isi_read_char(int line) {
+    char buf[5] = {0};
+    isi_read_skip(line);
+    int ch = getchar();
+    buf[0] = ch;
+    int n = (ch & 0xE0) == 0xC0 ? 2 : (ch & 0xF0) == 0xE0 ? 3 : (ch & 0xF8) == 0xF0 ? 4 : 1;
+    for (int i = 1; i < n && (ch = getchar()) != EOF; i++) buf[i] = ch;
+    const char* p = buf;
+    return isi_utf8_next(&p);
+}
+
+/* Strings ocupam o resto da linha, sem os espaços do fim. */
+static inline char* isi_read_str(int line) {
+    isi_read_skip(line);
+    char* r = isi_read_until(isi_is_newline);
+    getchar();
+    size_t n = strlen(r);
+    while (n > 0 && isi_is_space(r[n - 1])) r[--n] = '\0';
+    return r;
+}
@@ -1 +1,2 @@
 pub mod cemitter;
+pub mod mangle;
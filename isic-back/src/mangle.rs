@@ -0,0 +1,105 @@
+//! # isic_back::mangle
+//!
+//! Os nomes das variáveis no código C gerado.
+//!
+//! Todo nome ganha o prefixo `isi_v_`, e cada caractere que não é uma letra
+//! ou dígito ASCII, como um acento ou `_`, vira `_<código em hexadecimal>_`:
+//! `x` vira `isi_v_x`, e `média` vira `isi_v_m_e9_dia`. Com o prefixo, uma
+//! variável nunca tem o nome de uma palavra-chave do C (`double`), de uma
+//! função da libc (`sqrt`, `exit`) ou do runtime, nem de algo declarado no
+//! header de uma função externa. A codificação pode ser desfeita com
+//! `demangle`.
+
+use std::fmt::Write;
+
+const PREFIX: &str = "isi_v_";
+
+/// O nome em C da variável `name`.
+pub fn mangle(name: &str) -> String {
+    let mut out = PREFIX.to_string();
+
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            out.push(c);
+        } else {
+            write!(out, "_{:x}_", c as u32).unwrap();
+        }
+    }
+
+    out
+}
+
+/// O nome da variável que foi codificada como `c_name`, ou None se
+/// `c_name` não foi gerado por `mangle`.
+pub fn demangle(c_name: &str) -> Option<String> {
+    let encoded = c_name.strip_prefix(PREFIX)?;
+
+    let mut out = String::new();
+    let mut rest = encoded;
+
+    while let Some(c) = rest.chars().next() {
+        if c.is_ascii_alphanumeric() {
+            out.push(c);
+            rest = &rest[1..];
+        } else if c == '_' {
+            let end = rest[1..].find('_')? + 1;
+            let code = u32::from_str_radix(&rest[1..end], 16).ok()?;
+
+            out.push(char::from_u32(code)?);
+            rest = &rest[end + 1..];
+        } else {
+            return None;
+        }
+    }
+
+    // Recusa as codificações que o mangle não gera, como `_05f_` ou `_61_`
+    // (um `a`), para que cada variável tenha um único nome em C.
+    (mangle(&out) == c_name).then_some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMES: &[&str] = &[
+        "x", "média", "a_b", "a5f", "a_5f_", "a5f_", "_", "__", "_5f_", "ação", "x1", "Ω", "🎉", "a_b_c", "ab",
+    ];
+
+    #[test]
+    fn demangle_undoes_mangle() {
+        for name in NAMES {
+            assert_eq!(demangle(&mangle(name)).as_deref(), Some(*name), "{}", mangle(name));
+        }
+    }
+
+    #[test]
+    fn mangle_is_injective() {
+        for (i, a) in NAMES.iter().enumerate() {
+            for b in &NAMES[i + 1..] {
+                assert_ne!(mangle(a), mangle(b), "{} and {}", a, b);
+            }
+        }
+
+        assert_eq!(mangle("a_b"), "isi_v_a_5f_b");
+        assert_eq!(mangle("a5f"), "isi_v_a5f");
+    }
+
+    #[test]
+    fn demangle_rejects_names_not_made_by_mangle() {
+        let c_names = [
+            "x",
+            "isi_v",
+            "isi_v_a_",
+            "isi_v_a_5f",
+            "isi_v__05f_",
+            "isi_v__61_",
+            "isi_v___",
+            "isi_v__+5f_",
+            "isi_v__110000_",
+        ];
+
+        for c_name in c_names {
+            assert_eq!(demangle(c_name), None, "{}", c_name);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+/* Biblioteca de strings (tamanho, maiusculas, sub, ...).
+ *
+ * Strings são UTF-8, e todas as posições e tamanhos contam caracteres
+ * (code points), e não bytes, assim como no interpretador. */
+
+/* Avança n caracteres em s, parando no fim da string. */
+static inline const char* isi_utf8_skip(const char* s, long long n) {
+    for (; *s && n > 0; n--) isi_utf8_next(&s);
+    return s;
+}
+
+static inline char* isi_str_copy(const char* s, size_t n) {
+    char* r = malloc(n + 1);
+    memcpy(r, s, n);
+    r[n] = '\0';
+    return r;
+}
+
+static inline long long isi_str_len(const char* s) {
+    long long n = 0;
+    for (; *s; s++) if ((*s & 0xC0) != 0x80) n++;
+    return n;
+}
+
+/* Completa s com espaços até ter width caracteres. Usado no escrevaf. */
+static inline char* isi_pad(const char* s, int width, int left) {
+    long long fill = width - isi_str_len(s);
+    if (fill < 0) fill = 0;
+    char* r = malloc(strlen(s) + fill + 1);
+    if (left) {
+        strcpy(r, s);
+        memset(r + strlen(s), ' ', fill);
+    } else {
+        memset(r, ' ', fill);
+        strcpy(r + fill, s);
+    }
+    r[strlen(s) + fill] = '\0';
+    return r;
+}
+
+/* Só o ASCII e as letras do Latin-1, como no interpretador. */
+static inline char* isi_str_case(const char* s, int upper) {
+    char* r = malloc(strlen(s) + 1);
+    char* p = r;
+    while (*s) {
+        unsigned int c = isi_utf8_next(&s);
+        if (upper && ((c >= 'a' && c <= 'z') || (c >= 0xE0 && c <= 0xFE && c != 0xF7))) c -= 32;
+        if (!upper && ((c >= 'A' && c <= 'Z') || (c >= 0xC0 && c <= 0xDE && c != 0xD7))) c += 32;
+        p += isi_utf8_put(p, c);
+    }
+    *p = '\0';
+    return r;
+}
+
+static inline char* isi_str_upper(const char* s) {
+    return isi_str_case(s, 1);
+}
+
+static inline char* isi_str_lower(const char* s) {
+    return isi_str_case(s, 0);
+}
+
+static inline char* isi_str_sub(const char* s, long long i, long long n, int line) {
+    if (i < 0 || i > isi_str_len(s) || n < 0) isi_trap("index out of bounds", line);
+    const char* start = isi_utf8_skip(s, i);
+    const char* end = isi_utf8_skip(start, n);
+    return isi_str_copy(start, end - start);
+}
+
+static inline long long isi_str_find(const char* s, const char* t) {
+    const char* p = strstr(s, t);
+    if (!p) return -1;
+    long long n = 0;
+    for (; s < p; s++) if ((*s & 0xC0) != 0x80) n++;
+    return n;
+}
+
+static inline char* isi_str_replace(const char* s, const char* from, const char* to) {
+    size_t nf = strlen(from), nt = strlen(to), count = 0;
+    if (nf == 0) return isi_str_copy(s, strlen(s));
+    for (const char* p = s; (p = strstr(p, from)); p += nf) count++;
+
+    char* r = malloc(strlen(s) + count * nt - count * nf + 1);
+    char* o = r;
+    const char* p;
+    while ((p = strstr(s, from))) {
+        memcpy(o, s, p - s);
+        o += p - s;
+        memcpy(o, to, nt);
+        o += nt;
+        s = p + nf;
+    }
+    strcpy(o, s);
+    return r;
+}
+
+static inline char* isi_str_split(const char* s, const char* sep, long long i, int line) {
+    size_t ns = strlen(sep);
+    if (i < 0 || (ns == 0 && i > 0)) isi_trap("index out of bounds", line);
+    if (ns == 0) return isi_str_copy(s, strlen(s));
+    for (; i > 0; i--) {
+        const char* p = strstr(s, sep);
+        if (!p) isi_trap("index out of bounds", line);
+        s = p + ns;
+    }
+    const char* end = strstr(s, sep);
+    return isi_str_copy(s, end ? (size_t) (end - s) : strlen(s));
+}
+
+/* Comparam byte a byte, o que no UTF-8 é o mesmo que comparar os code
+ * points, como o interpretador faz. */
+static inline int isi_strlt(const char* a, const char* b) {
+    return strcmp(a, b) < 0;
+}
+
+static inline int isi_strgt(const char* a, const char* b) {
+    return strcmp(a, b) > 0;
+}
+
+static inline int isi_strleq(const char* a, const char* b) {
+    return strcmp(a, b) <= 0;
+}
+
+static inline int isi_strgeq(const char* a, const char* b) {
+    return strcmp(a, b) >= 0;
+}
@@ -1,19 +1,29 @@
-use std::io::Read;
+use std::process::Command;
 use std::{error::Error, fs::File, path::PathBuf};
 
 use ariadne::{Label, Report, Source};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use isic_back::cemitter::CEmitter;
-use isic_interpreter::interpreter::IsiInterpreter;
+use isic_interpreter::{fs::StdFs, interpreter::IsiInterpreter};
+use isic_front::dialect::{self, Dialect};
+use isic_front::source::SourceText;
+use isic_front::span::LineIndex;
+use isic_middle::levelck::{Level, LevelCk};
 use isic_middle::typeck::TypeCk;
+use isic_middle::{ArithMode, InputMode, RuntimeOptions};
 use isic_middle::usageck::UsageCk;
 
+mod test;
+
 #[derive(Parser)]
-#[command()]
+#[command(subcommand_negates_reqs = true, args_conflicts_with_subcommands = true)]
 struct CliArgs {
-    #[arg(short = 'i', long = "input")]
+    #[command(subcommand)]
+    command: Option<CliCommand>,
+
+    #[arg(short = 'i', long = "input", required = true)]
     /// O arquivo de entrada.
-    pub input_file: PathBuf,
+    pub input_file: Option<PathBuf>,
 
     #[arg(short = 'o', long = "output")]
     /// O arquivo de saída. Padrão: <arquivo de entrada>.c
@@ -22,14 +32,86 @@ struct CliArgs {
     #[arg(short = 'e', long = "execute", default_value = "false")]
     /// Interpreta o arquivo ao invés de transpilar para C.
     pub execute: bool,
+
+    #[arg(short = 'n', long = "native", default_value = "false")]
+    /// Além de emitir o código C, compila ele para um executável usando o
+    /// compilador C do sistema ($CC, ou cc).
+    pub native: bool,
+
+    #[arg(long = "arith", default_value = "checked")]
+    /// Como tratar overflow em operações com int: checked ou wrapping.
+    pub arith: ArithMode,
+
+    #[arg(long = "bad-input", default_value = "error")]
+    /// O que fazer quando leia recebe uma entrada inválida: error, para
+    /// interromper o programa, ou reprompt, para ler o valor novamente.
+    pub bad_input: InputMode,
+
+    #[arg(long = "strip-asserts", default_value = "false")]
+    /// Remove os afirme, invariantes, requer e garante do programa, como numa
+    /// versão final. As condições não são avaliadas.
+    pub strip_asserts: bool,
+
+    #[arg(long = "nivel", global = true)]
+    /// Restringe o programa aos recursos de um nível da linguagem (1, 2 ou
+    /// 3), para as primeiras etapas de um curso.
+    pub nivel: Option<Level>,
+
+    #[arg(long = "dialeto", global = true, value_parser = Dialect::by_name, default_value = "pt")]
+    /// O dialeto das palavras-chave (pt ou en), para arquivos sem o pragma
+    /// #dialeto.
+    pub dialeto: &'static Dialect,
+
+    #[arg(trailing_var_arg = true)]
+    /// Os argumentos passados ao programa, quando ele é interpretado com
+    /// -e (depois de um --, se algum deles começar com -).
+    pub program_args: Vec<String>,
+}
+
+#[derive(Subcommand)]
+enum CliCommand {
+    /// Executa os blocos teste do programa no interpretador.
+    Test {
+        /// O arquivo com o programa e os testes.
+        file: PathBuf,
+    },
+    /// Reescreve o programa em outro dialeto, mantendo os nomes declarados.
+    Translate {
+        /// O arquivo com o programa.
+        file: PathBuf,
+
+        #[arg(long = "to", value_parser = Dialect::by_name)]
+        /// O dialeto de destino: pt ou en.
+        to: &'static Dialect,
+
+        #[arg(short = 'o', long = "output")]
+        /// Onde escrever o programa traduzido. Padrão: a saída padrão.
+        output: Option<PathBuf>,
+    },
 }
 
 impl CliArgs {
+    /// O arquivo com o código fonte, passado com -i ou para o subcomando.
+    pub fn source_file(&self) -> &PathBuf {
+        match &self.command {
+            Some(CliCommand::Test { file }) | Some(CliCommand::Translate { file, .. }) => file,
+            None => self.input_file.as_ref().unwrap(),
+        }
+    }
+
+    pub fn runtime_options(&self) -> RuntimeOptions {
+        RuntimeOptions {
+            arith: self.arith,
+            input: self.bad_input,
+            strip_asserts: self.strip_asserts,
+        }
+    }
+
     pub fn get_output_file(&self) -> PathBuf {
         match &self.output_file {
             Some(f) => f.to_owned(),
             None => {
-                let mut f = self.input_file.clone();
+                let mut f = self.source_file().clone();
                 f.set_extension("c");
 
                 f
@@ -41,33 +123,78 @@ impl CliArgs {
 fn main() -> Result<(), Box<dyn Error>> {
     let args = CliArgs::parse();
 
-    let mut input = File::open(&args.input_file)?;
-    let mut output = File::create(&args.get_output_file())?;
+    // O arquivo pode estar em Latin-1, ou ter BOM e quebras de linha \r\n.
+    let source = SourceText::load(&std::fs::read(args.source_file())?);
+    let input_text = &source.text;
+
+    if let Some(warning) = source.warning() {
+        eprintln!("Warning: {}", warning);
+    }
 
-    let mut input_text = String::new();
-    input.read_to_string(&mut input_text)?;
+    // O pragma #dialeto tem precedência sobre a flag.
+    let dialect = Dialect::detect(input_text, args.dialeto).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
 
     // Faz o parse do código fonte.
-    let parse_result = isic_front::parser::isilang_parser::program(&input_text);
+    let parse_result = isic_front::parser::parse(input_text, dialect);
 
-    let mut reporter_src = Source::from(&input_text);
+    let mut reporter_src = Source::from(input_text);
+    let lines = LineIndex::new(input_text);
 
     match parse_result {
         Ok(ast) => 'a: {
             // Código foi parseado e temos a AST com sucesso.
 
-            // Vamos primeiro rodar o type checker.
+            if let Some(CliCommand::Translate { to, output, .. }) = &args.command {
+                // O isic translate só precisa da AST para saber quais nomes
+                // foram declarados.
+                let translated = dialect::translate(input_text, &ast, dialect, to)
+                    .unwrap_or_else(|e| {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    });
+
+                match output {
+                    Some(path) => std::fs::write(path, translated)?,
+                    None => print!("{}", translated),
+                }
+
+                break 'a;
+            }
+
+            // Se um nível foi escolhido, vamos primeiro verificar se o
+            // programa só usa os recursos dele.
+            if let Some(level) = args.nivel {
+                if let Err(errors) = LevelCk::new(&ast, level).check() {
+                    for desc in errors {
+                        Report::build(ariadne::ReportKind::Error, (), source.char_offset(desc.span.start))
+                            .with_message("Level error")
+                            .with_label(
+                                Label::new(((), source.char_range(desc.span)))
+                                    .with_color(ariadne::Color::Red)
+                                    .with_message(desc.desc),
+                            )
+                            .finish()
+                            .print(&mut reporter_src)
+                            .unwrap();
+                    }
+
+                    std::process::exit(1);
+                }
+            }
+
+            // Agora vamos rodar o type checker.
             let mut typeck = TypeCk::new(&ast);
             if let Err(errors) = typeck.check() {
                 // Existem type errors. Vamos mostrar eles
                 // ao usuário e sair.
                 for desc in errors {
-                    let offset = reporter_src.get_offset_line(desc.span.start).unwrap();
-
-                    let _report = Report::build(ariadne::ReportKind::Error, (), offset.1)
+                    Report::build(ariadne::ReportKind::Error, (), source.char_offset(desc.span.start))
                         .with_message("Type error")
                         .with_label(
-                            Label::new(((), desc.span.start..desc.span.end))
+                            Label::new(((), source.char_range(desc.span)))
                                 .with_color(ariadne::Color::Red)
                                 .with_message(desc.desc),
                         )
@@ -76,7 +203,22 @@ fn main() -> Result<(), Box<dyn Error>> {
                         .unwrap();
                 }
 
-                break 'a;
+                std::process::exit(1);
+            }
+
+            for desc in &typeck.warnings {
+                // O type checker também pode gerar warnings, como
+                // conversões que perdem informação.
+                Report::build(ariadne::ReportKind::Warning, (), source.char_offset(desc.span.start))
+                    .with_message("Type warning")
+                    .with_label(
+                        Label::new(((), source.char_range(desc.span)))
+                            .with_color(ariadne::Color::Yellow)
+                            .with_message(&desc.desc),
+                    )
+                    .finish()
+                    .print(&mut reporter_src)
+                    .unwrap();
             }
 
             // Não houveram type errors -- vamos agora chamar
@@ -86,12 +228,10 @@ fn main() -> Result<(), Box<dyn Error>> {
 
             for desc in warns {
                 // Houveram warnings -- vamos mostrar eles ao usuário.
-                let offset = reporter_src.get_offset_line(desc.span.start).unwrap();
-
-                let _report = Report::build(ariadne::ReportKind::Warning, (), offset.1)
+                Report::build(ariadne::ReportKind::Warning, (), source.char_offset(desc.span.start))
                     .with_message("Usage pattern warning")
                     .with_label(
-                        Label::new(((), desc.span.start..desc.span.end))
+                        Label::new(((), source.char_range(desc.span)))
                             .with_color(ariadne::Color::Yellow)
                             .with_message(desc.desc),
                     )
@@ -100,35 +240,96 @@ fn main() -> Result<(), Box<dyn Error>> {
                     .unwrap();
             }
 
-            if args.execute {
+            if let Some(CliCommand::Test { .. }) = args.command {
+                // O isic test executa somente os testes.
+                let ok = test::run_tests(&ast, &typeck.coercions, args.runtime_options(), &lines);
+
+                if !ok {
+                    std::process::exit(1);
+                }
+            } else if args.execute {
                 // Se a flag -e foi passada, vamos executar no interpretador.
 
                 let mut stdin = std::io::stdin().lock();
                 let mut stdout = std::io::stdout();
+                let mut fs = StdFs;
+
+                let mut interpreter = IsiInterpreter::new(
+                    &ast,
+                    &typeck.coercions,
+                    args.runtime_options(),
+                    &lines,
+                    &mut stdin,
+                    &mut stdout,
+                    &mut fs,
+                )
+                .with_args(args.program_args.clone());
 
-                let mut interpreter = IsiInterpreter::new(&ast, &mut stdin, &mut stdout);
+                let result = interpreter.exec();
 
-                interpreter.exec();
+                // Fecha os arquivos que o programa deixou abertos, já que o
+                // process::exit não roda os destrutores.
+                drop(interpreter);
+
+                match result {
+                    Ok(0) => {}
+                    Ok(code) => std::process::exit(code),
+                    Err(e) => {
+                        // Erro em tempo de execução, como um overflow. Assim
+                        // como o programa C, saímos com código 1.
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                }
             } else {
                 // Senao, vamos emitir o código C.
-                let emitter = CEmitter::new(&ast, &typeck.sym_table, &mut output);
+                let mut output = File::create(args.get_output_file())?;
+
+                let emitter = CEmitter::new(
+                    &ast,
+                    &typeck.sym_table,
+                    &typeck.expr_types,
+                    &typeck.coercions,
+                    args.runtime_options(),
+                    &lines,
+                    &mut output,
+                );
                 emitter.emit().unwrap();
+
+                if args.native {
+                    // O runtime usa a <math.h>, então precisamos da -lm.
+                    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+                    let mut exe = args.get_output_file();
+                    exe.set_extension("");
+
+                    let status = Command::new(cc)
+                        .arg(args.get_output_file())
+                        .arg("-o")
+                        .arg(exe)
+                        .arg("-lm")
+                        .status()?;
+
+                    if !status.success() {
+                        eprintln!("C compiler failed with {}", status);
+                        std::process::exit(1);
+                    }
+                }
             }
         }
         Err(e) => {
             // Houve erro de sintaxe, então vamos reportar.
-            let offset = reporter_src.get_offset_line(e.location.offset).unwrap();
-
-            let _report = Report::build(ariadne::ReportKind::Error, (), offset.1)
+            Report::build(ariadne::ReportKind::Error, (), source.char_offset(e.span.start))
                 .with_message("Syntax error")
                 .with_label(
-                    Label::new(((), e.location.offset..e.location.offset+1))
+                    Label::new(((), source.char_range(e.span)))
                         .with_color(ariadne::Color::Red)
-                        .with_message(format!("Expected {}", e.expected)),
+                        .with_message(e.message),
                 )
                 .finish()
                 .print(&mut reporter_src)
                 .unwrap();
+
+            std::process::exit(1);
         }
     }
 
@@ -0,0 +1,111 @@
+//! O `isic test`: executa os blocos `teste` de um programa no interpretador,
+//! com a entrada e a saída capturadas, e compara a saída com a esperada.
+//!
+//! Os arquivos também ficam na memória: cada teste começa com um MemFs
+//! vazio, então um teste não vê os arquivos escritos por outro, e nada é
+//! escrito no disco.
+
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use isic_front::{
+    ast::{IsiProgram, Statement, TestBlock},
+    span::{LineIndex, Span},
+};
+use isic_interpreter::{fs::MemFs, interpreter::IsiInterpreter};
+use isic_middle::{IsiType, RuntimeOptions};
+
+/// Executa um teste. Retorna Err(motivo) se ele falhou.
+fn run_test(
+    program: &IsiProgram,
+    test: &TestBlock,
+    coercions: &HashMap<Span, IsiType>,
+    options: RuntimeOptions,
+    lines: &LineIndex,
+) -> Result<(), String> {
+    let input = test.input.as_ref().map(|i| i.0.as_str()).unwrap_or_default();
+
+    let mut stdin = Cursor::new(input);
+    let mut stdout: Vec<u8> = vec![];
+    let mut fs = MemFs::new();
+
+    let mut interpreter =
+        IsiInterpreter::new(program, coercions, options, lines, &mut stdin, &mut stdout, &mut fs);
+
+    let result = interpreter.exec_test(test);
+    drop(interpreter);
+
+    match result {
+        Ok(0) => {}
+        Ok(code) => return Err(format!("program exited with code {}", code)),
+        Err(e) => return Err(e),
+    }
+
+    let output = String::from_utf8_lossy(&stdout);
+
+    match &test.output {
+        Some(expected) if expected.0 != output => Err(diff(&expected.0, &output)),
+        _ => Ok(()),
+    }
+}
+
+/// Mostra as diferenças entre a saída esperada e a obtida, linha a linha.
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<_> = expected.split('\n').collect();
+    let actual: Vec<_> = actual.split('\n').collect();
+
+    let mut out = "output differs (- expected, + actual):".to_string();
+
+    for i in 0..expected.len().max(actual.len()) {
+        match (expected.get(i), actual.get(i)) {
+            (Some(e), Some(a)) if e == a => out.push_str(&format!("\n  {}", e)),
+            (e, a) => {
+                if let Some(e) = e {
+                    out.push_str(&format!("\n- {}", e));
+                }
+                if let Some(a) = a {
+                    out.push_str(&format!("\n+ {}", a));
+                }
+            }
+        }
+    }
+
+    out
+}
+
+/// Executa todos os testes do programa, escrevendo o resultado de cada um.
+/// Retorna true se todos passaram.
+pub fn run_tests(
+    program: &IsiProgram,
+    coercions: &HashMap<Span, IsiType>,
+    options: RuntimeOptions,
+    lines: &LineIndex,
+) -> bool {
+    let mut passed = 0;
+    let mut failed = 0;
+
+    for stmt in &program.statements {
+        let Statement::Test(test) = stmt else {
+            continue;
+        };
+
+        match run_test(program, test, coercions, options, lines) {
+            Ok(()) => {
+                println!("test {} ... ok", test.name.0);
+                passed += 1;
+            }
+            Err(e) => {
+                println!("test {} ... FAILED", test.name.0);
+                for line in e.lines() {
+                    println!("    {}", line);
+                }
+                failed += 1;
+            }
+        }
+    }
+
+    println!();
+    println!("{} passed, {} failed", passed, failed);
+
+    failed == 0
+}
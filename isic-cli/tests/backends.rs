@@ -0,0 +1,491 @@
+//! Testes diferenciais: o mesmo programa, executado pelo interpretador e
+//! compilado para C, deve escrever a mesma saída.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+use isic_front::ast::BinaryOp;
+use isic_middle::{ops, IsiType};
+
+const ISIC: &str = env!("CARGO_BIN_EXE_isic-cli");
+
+/// Escreve o programa num arquivo `name`.isi, e retorna o caminho dele.
+fn write_program(name: &str, program: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.isi", name));
+    std::fs::write(&path, program).unwrap();
+
+    path
+}
+
+/// Executa o comando com `input` na entrada padrão.
+fn run(cmd: &mut Command, input: &str) -> Output {
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+
+    child.wait_with_output().unwrap()
+}
+
+/// Executa o programa nos dois backends, com as opções `flags` e a entrada
+/// `input`, e verifica que o código de saída, a saída e os erros são iguais.
+/// Retorna o resultado do interpretador.
+fn check_with(name: &str, program: &str, flags: &[&str], input: &str) -> Output {
+    let path = write_program(name, program);
+
+    let interpreted = run(Command::new(ISIC).arg("-i").arg(&path).args(flags).arg("-e"), input);
+
+    let exe = path.with_extension("");
+    let _ = std::fs::remove_file(&exe);
+
+    let compiled = run(Command::new(ISIC).arg("-i").arg(&path).args(flags).arg("-n"), "");
+    assert!(compiled.status.success(), "{}", String::from_utf8_lossy(&compiled.stderr));
+    assert!(exe.exists(), "the program did not compile");
+
+    let native = run(&mut Command::new(&exe), input);
+
+    assert_eq!(
+        String::from_utf8_lossy(&interpreted.stdout),
+        String::from_utf8_lossy(&native.stdout),
+        "{}",
+        program
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&interpreted.stderr),
+        String::from_utf8_lossy(&native.stderr),
+        "{}",
+        program
+    );
+    assert_eq!(interpreted.status.code(), native.status.code(), "{}", program);
+
+    interpreted
+}
+
+/// Executa o programa nos dois backends, e verifica que os dois terminam
+/// sem erros e com a mesma saída.
+fn check(name: &str, program: &str) {
+    let out = check_with(name, program, &[], "");
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+}
+
+#[test]
+fn side_effects_happen_in_source_order() {
+    check(
+        "side_effects",
+        r#"programa
+    declare x: int, s: string, c: caractere.
+    semente(7).
+    x := aleatorio(1, 100) - aleatorio(1, 100).
+    escreva(x).
+    x := max(aleatorio(1, 100), aleatorio(1, 100) * 2) + aleatorio(1, 9) ^ aleatorio(1, 3).
+    escreva(x).
+    s := texto(aleatorio(1, 9)) + texto(aleatorio(1, 9)).
+    escreva(s).
+    c := "abcdefghij"[aleatorio(0, 9)].
+    escreva(c).
+    c := sub("abcdefghij", aleatorio(0, 4), aleatorio(5, 9))[aleatorio(0, 3)].
+    escreva(c).
+    se (aleatorio(1, 10) > aleatorio(1, 10)) entao {
+        escreva("maior").
+    } senao {
+        escreva("menor").
+    }
+    x := 0.
+    enquanto (aleatorio(1, 10) - aleatorio(1, 10) != 0 && x < 20) {
+        x := x + 1.
+    }
+    escreva(x, " ", aleatorio(1, 6) * 10 + aleatorio(1, 6)).
+fimprog.
+"#,
+    );
+}
+
+#[test]
+fn capture_variables_are_local_to_the_handler() {
+    check(
+        "two_handlers",
+        r#"programa
+    declare x: int.
+    x := 0.
+    tente {
+        x := 1 / x.
+    } capture (erro) {
+        escreva("primeiro: ", erro).
+    }
+    tente {
+        x := x % 0.
+    } capture (erro) {
+        afirme(erro != "", "sem descrição").
+        escreva("segundo: ", erro).
+    }
+fimprog.
+"#,
+    );
+}
+
+#[test]
+fn variables_can_be_named_like_the_parameters_of_main() {
+    check(
+        "argc",
+        r#"programa
+    declare argc: int, argv: string.
+    argc := numargs().
+    argv := "a".
+    escreva(argc, argv).
+fimprog.
+"#,
+    );
+}
+
+#[test]
+fn variables_can_be_named_like_c_keywords_and_functions() {
+    check(
+        "c_names",
+        r#"programa
+    declare double: int, sqrt: real, exit: int, isi_t0: int, printf: string.
+    double := 2.
+    sqrt := raiz(2).
+    exit := 3.
+    isi_t0 := aleatorio(1, 6) + aleatorio(1, 6).
+    printf := "%d".
+    escreva(double, " ", sqrt, " ", exit, " ", isi_t0, " ", printf).
+fimprog.
+"#,
+    );
+}
+
+/// Uma expressão do tipo `ty`, com um valor diferente para cada lado da
+/// operação, que não faz nenhuma operação falhar. Com `negative`, o valor é
+/// negativo, ou None se o tipo não tem valores negativos.
+fn operand(ty: IsiType, lhs: bool, negative: bool) -> Option<&'static str> {
+    let expr = match (ty, lhs, negative) {
+        (IsiType::Int, true, false) => "7",
+        (IsiType::Int, false, false) => "2",
+        (IsiType::Int, true, true) => "(0 - 7)",
+        (IsiType::Int, false, true) => "(0 - 2)",
+        (IsiType::Float, true, false) => "7,5",
+        (IsiType::Float, false, false) => "2,0",
+        (IsiType::Float, true, true) => "(0,0 - 7,5)",
+        (IsiType::Float, false, true) => "(0,0 - 2,0)",
+        (IsiType::Real, true, false) => "real(7,5)",
+        (IsiType::Real, false, false) => "real(2)",
+        (IsiType::Real, true, true) => "real(0,0 - 7,5)",
+        (IsiType::Real, false, true) => "real(0 - 2)",
+        (IsiType::BigInt, true, false) => "inteirogrande(7)",
+        (IsiType::BigInt, false, false) => "inteirogrande(2)",
+        (IsiType::BigInt, true, true) => "inteirogrande(0 - 7)",
+        (IsiType::BigInt, false, true) => "inteirogrande(0 - 2)",
+        (IsiType::Char, true, false) => "'b'",
+        (IsiType::Char, false, false) => "'a'",
+        (IsiType::String, true, false) => "\"b\"",
+        (IsiType::String, false, false) => "\"a\"",
+        (IsiType::Bool, true, false) => "(1 < 2)",
+        (IsiType::Bool, false, false) => "(2 < 1)",
+        (IsiType::Char | IsiType::String | IsiType::Bool, _, true) => return None,
+        (ty, _, _) => unreachable!("no operator takes a {:?}", ty),
+    };
+
+    Some(expr)
+}
+
+fn symbol(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "+",
+        BinaryOp::Sub => "-",
+        BinaryOp::Mul => "*",
+        BinaryOp::Div => "/",
+        BinaryOp::IntDiv => "div",
+        BinaryOp::Mod => "%",
+        BinaryOp::Pow => "^",
+        BinaryOp::Gt => ">",
+        BinaryOp::Lt => "<",
+        BinaryOp::Geq => ">=",
+        BinaryOp::Leq => "<=",
+        BinaryOp::Eq => "==",
+        BinaryOp::Neq => "!=",
+        BinaryOp::And => "&&",
+        BinaryOp::Or => "||",
+        BinaryOp::BitAnd => "&",
+        BinaryOp::BitOr => "|",
+        BinaryOp::BitXor => "xor",
+        BinaryOp::Shl => "<<",
+        BinaryOp::Shr => ">>",
+    }
+}
+
+/// Toda assinatura da tabela de operadores é aceita pelo type checker e
+/// dá o mesmo resultado nos dois backends, também com operandos negativos.
+#[test]
+fn every_operator_signature_matches() {
+    let mut program = String::from("programa\n");
+
+    for (lhs_negative, rhs_negative) in [(false, false), (true, false), (false, true)] {
+        for sig in ops::OPERATORS {
+            // Deslocamentos e expoentes de inteirogrande negativos são um
+            // erro.
+            if rhs_negative
+                && (matches!(sig.op, BinaryOp::Shl | BinaryOp::Shr)
+                    || (sig.op == BinaryOp::Pow && sig.rhs == IsiType::BigInt))
+            {
+                continue;
+            }
+
+            let lhs = operand(sig.lhs, true, lhs_negative);
+            let rhs = operand(sig.rhs, false, rhs_negative);
+
+            if let (Some(lhs), Some(rhs)) = (lhs, rhs) {
+                program.push_str(&format!("    escreva({} {} {}).\n", lhs, symbol(sig.op), rhs));
+            }
+        }
+    }
+
+    program.push_str("fimprog.\n");
+
+    check("operators", &program);
+}
+
+/// Conversões de texto para número aceitam os mesmos números que `leia`,
+/// e textos inválidos dão o mesmo erro nos dois backends. Reais fora do
+/// intervalo de um int saturam, e NaN vira 0.
+#[test]
+fn conversions_match() {
+    let mut program = String::from(
+        r#"programa
+    declare i: int, r: real, g: inteirogrande.
+    escreva(inteiro(" 42 "), " ", inteiro("+7"), " ", inteiro("-9223372036854775808")).
+    escreva(real("3,5"), " ", real("-2.5e3"), " ", real(" 1 ")).
+    escreva(inteirogrande("-123456789012345678901234567890")).
+    escreva(inteiro(real(10) ^ real(300)), " ", inteiro(real(0) - real(10) ^ real(300))).
+    escreva(inteiro(real(0) / real(0)), " ", inteiro(7,9), " ", inteiro(0,0 - 7,9)).
+"#,
+    );
+
+    let texts = ["abc", "", "1 2", "9223372036854775808", "1,5", "0x10", "1_000", "nan", "+-1", "1e3"];
+    for text in texts {
+        for (var, func) in [("i", "inteiro"), ("r", "real"), ("g", "inteirogrande")] {
+            program.push_str(&format!(
+                "    tente {{\n        {v} := {f}(\"{t}\").\n        escreva({v}).\n    }} capture (erro) {{\n        escreva(erro).\n    }}\n",
+                v = var,
+                f = func,
+                t = text,
+            ));
+        }
+    }
+
+    program.push_str("fimprog.\n");
+
+    check("conversions", &program);
+}
+
+/// Casos de borda de inteirogrande: valores muito maiores que um int, os
+/// extremos de um int, sinais misturados e erros de `^`.
+#[test]
+fn bigint_edge_cases_match() {
+    check(
+        "bigint_edges",
+        r#"programa
+    declare a: inteirogrande, b: inteirogrande, z: inteirogrande.
+    a := inteirogrande("123456789012345678901234567890").
+    b := inteirogrande("-987654321098765432109876543210").
+    z := inteirogrande(0).
+    escreva(a + b, " ", a - b, " ", a * b).
+    escreva(b / a, " ", b div a, " ", b % a).
+    escreva(a / (0 - a), " ", a div (0 - a), " ", z - a + a).
+    escreva(inteirogrande(9223372036854775807) + 1, " ", inteirogrande(0 - 9223372036854775807 - 1) - 1).
+    escreva(inteiro(a), " ", inteiro(b), " ", real(b)).
+    escreva(inteirogrande(2) ^ inteirogrande(200), " ", b ^ inteirogrande(3), " ", z ^ z).
+    escreva(a == 0 - b, " ", b < a, " ", z == 0).
+    tente {
+        a := a ^ inteirogrande(0 - 1).
+    } capture (erro) {
+        escreva(erro).
+    }
+    tente {
+        a := a ^ inteirogrande("4294967296").
+    } capture (erro) {
+        escreva(erro).
+    }
+    tente {
+        a := b div z.
+    } capture (erro) {
+        escreva(erro).
+    }
+fimprog.
+"#,
+    );
+}
+
+/// Caracteres são code points: literais fora do ASCII, índices em strings
+/// com acentos, conversões de e para int e comparações.
+#[test]
+fn characters_match() {
+    check(
+        "characters",
+        r#"programa
+    declare c: caractere, s: string, i: int.
+    s := "ação 🎉".
+    escreva(s[0], s[1], s[2], s[3], " ", s[5], " ", tamanho(s)).
+    c := 'ç'.
+    escreva(c, " ", inteiro(c), " ", inteiro('🎉'), " ", inteiro('\n'), " ", '\'', '\\').
+    escreva(caractere(233), caractere(65), caractere(128512)).
+    escreva(inteiro(caractere(0 - 1)), " ", inteiro(caractere(55296)), " ", inteiro(caractere(1114112))).
+    escreva('a' < 'b', " ", 'é' > 'z', " ", s[1] == 'ç', " ", c != 'c').
+    escreva(c + "a", " ", "a" + c, " ", texto(c)).
+    i := 0.
+    enquanto (i < tamanho(s)) {
+        escreva(inteiro(s[i])).
+        i := i + 1.
+    }
+    tente {
+        c := s[tamanho(s)].
+    } capture (erro) {
+        escreva(erro).
+    }
+    tente {
+        c := s[0 - 1].
+    } capture (erro) {
+        escreva(erro).
+    }
+fimprog.
+"#,
+    );
+}
+
+/// `escreva` com vários argumentos de todos os tipos, `escrevasl` e os
+/// formatos de `escrevaf`, incluindo reais especiais e larguras em textos
+/// com acentos.
+#[test]
+fn output_matches() {
+    check(
+        "output",
+        r#"programa
+    declare f: float, r: real, g: inteirogrande.
+    f := 0,1.
+    r := real(1) / real(3).
+    g := inteirogrande("123456789012345678901234567890").
+    escreva(1, " ", f, " ", r, " ", g, " ", 'é', " ", "ç", " ", 1 < 2).
+    escreva(real(0) / real(0), " ", real(1) / real(0), " ", real(0) - real(1) / real(0), " ", real(0) - real(0)).
+    escreva(real(10) ^ real(21), " ", real(10) ^ real(0 - 7), " ", real(100), " ", f * f, " ", real(2,5)).
+    escrevasl("sem ").
+    escrevasl("quebra").
+    escreva().
+    escrevaf("[%d|%5d|%-5d|%05d]\n", 42, 42, 42, 0 - 42).
+    escrevaf("[%f|%.2f|%8.3f|%-8.1f|%08.2f]\n", r, r, f, 2, 0 - 2,5).
+    escrevaf("[%e|%.3e|%12.2e]\n", r, real(10) ^ real(21), 0 - 0,000123).
+    escrevaf("[%s|%6s|%-6s|%s|%s]\n", "ação", "ação", "é", g, 1 < 2).
+    escrevaf("[%c|%3c|%-3c] 100%%\n", 'ç', 'a', '🎉').
+    escrevaf("[%f|%f|%.1f]\n", real(0) / real(0), real(1) / real(0), real(0,05)).
+    semente(3).
+    tente {
+        escreva("antes ", aleatorio(1, 6), aleatorio(1, 6), " ", 1 div (aleatorio(1, 6) - aleatorio(1, 6) + 100 - 100) + inteiro("x"[0 - 1])).
+    } capture (erro) {
+        escreva(erro).
+    }
+    tente {
+        escrevasl("antes ", aleatorio(1, 6), aleatorio(1, 6), "abc"[3]).
+    } capture (erro) {
+        escreva(erro).
+    }
+    tente {
+        escrevaf("antes %d %d %d\n", aleatorio(1, 6), aleatorio(1, 6), 1 % (aleatorio(1, 6) * 0)).
+    } capture (erro) {
+        escreva(erro).
+    }
+fimprog.
+"#,
+    );
+}
+
+/// `leia` aceita vírgula ou ponto nos números e vários valores por linha,
+/// e entradas inválidas ou o fim da entrada dão o mesmo erro, ou pedem o
+/// valor de novo, nos dois backends.
+#[test]
+fn input_matches() {
+    let program = r#"programa
+    declare i: int, r: real, g: inteirogrande, c: caractere, s: string.
+    leia(i, r).
+    escreva(i, " ", r).
+    leia(g, c, s).
+    escreva(g, " ", c, " [", s, "]").
+    leia(i).
+    escreva(i).
+fimprog.
+"#;
+
+    let valid = check_with("input", program, &[], "42 3,5\n-123456789012345678901 é resto  da linha  \n\n  +7\n");
+    assert!(valid.status.success());
+
+    let cases = [
+        ("input_eof", "1 2,5\n"),
+        ("input_empty", ""),
+        ("input_bad_int", "1,5 2\n"),
+        ("input_bad_real", "1 abc\n"),
+        ("input_int_overflow", "9223372036854775808 1\n"),
+        ("input_bad_bigint", "1 2\n12a x s\n"),
+    ];
+    for (name, input) in cases {
+        let out = check_with(name, program, &[], input);
+        assert!(!out.status.success(), "{}", input);
+    }
+
+    let reprompt = ["--bad-input", "reprompt"];
+    let out = check_with("input_reprompt", program, &reprompt, "x 1\n1 y\n1 2\nz\n3 a b\n1e3\n5\n");
+    assert!(out.status.success());
+    let out = check_with("input_reprompt_eof", program, &reprompt, "x\n");
+    assert!(!out.status.success());
+}
+
+/// Arquivos escritos e lidos nos dois backends, e erros de arquivo, como
+/// qualquer uso depois do `feche`.
+#[test]
+fn files_match() {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("files.txt");
+    let path = path.to_str().unwrap();
+
+    let mut program = format!(
+        r#"programa
+    declare f: arquivo, g: arquivo, linha: string.
+    f := abra("{path}", "e").
+    escrevaarq(f, "um,", 2, ",", 3,5).
+    escrevaarq(f, 'ç', " ", 1 < 2).
+    feche(f).
+    f := abra("{path}", "a").
+    escrevaarq(f, "fim").
+    feche(f).
+    f := abra("{path}", "l").
+    enquanto (!fimdearquivo(f)) {{
+        linha := leialinha(f).
+        escreva("[", linha, "] ", divide(linha, ",", 0)).
+    }}
+    g := f.
+"#,
+        path = path
+    );
+
+    let errors = [
+        "linha := leialinha(f).".to_string(),
+        "escreva(fimdearquivo(g)).".to_string(),
+        "escrevaarq(f, 1).".to_string(),
+        "feche(f).\n        feche(g).".to_string(),
+        "f := abra(\"/nao/existe\", \"l\").".to_string(),
+        format!("f := abra(\"{}\", \"x\").", path),
+        format!("f := abra(\"{}\", \"e\").\n        linha := leialinha(f).", path),
+        "escreva(fimdearquivo(f)).".to_string(),
+        "escrevaarq(g, 1).".to_string(),
+        "feche(g).".to_string(),
+    ];
+    for stmt in errors {
+        program.push_str(&format!(
+            "    tente {{\n        {}\n    }} capture (erro) {{\n        escreva(erro).\n    }}\n",
+            stmt
+        ));
+    }
+
+    program.push_str("fimprog.\n");
+
+    check("files", &program);
+}
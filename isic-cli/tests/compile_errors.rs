@@ -0,0 +1,36 @@
+//! Testes do código de saída do isic-cli com programas que não compilam.
+
+use std::path::PathBuf;
+use std::process::Command;
+
+const ISIC: &str = env!("CARGO_BIN_EXE_isic-cli");
+
+/// Erros de sintaxe, de tipo e de nível terminam o isic-cli com o código 1
+/// em todos os modos, e nenhum código C é gerado.
+#[test]
+fn compile_errors_exit_with_an_error() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("compile_errors");
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let programs = [
+        ("syntax", "programa\n    escreva(1\nfimprog.\n", &[][..]),
+        ("types", "programa\n    escreva(1 + \"a\").\nfimprog.\n", &[][..]),
+        ("level", "programa\n    escreva(7 div 2).\nfimprog.\n", &["--nivel", "1"][..]),
+    ];
+
+    for (name, program, flags) in programs {
+        let path = dir.join(format!("{}.isi", name));
+        std::fs::write(&path, program).unwrap();
+
+        for mode in [&[][..], &["-e"][..], &["-n"][..]] {
+            let c = path.with_extension("c");
+            let _ = std::fs::remove_file(&c);
+
+            let out = Command::new(ISIC).arg("-i").arg(&path).args(flags).args(mode).output().unwrap();
+
+            assert_eq!(out.status.code(), Some(1), "{} {:?}", name, mode);
+            assert!(String::from_utf8_lossy(&out.stdout).contains("error"), "{} {:?}", name, mode);
+            assert!(!c.exists(), "{} {:?}", name, mode);
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! Testes do subcomando `test`.
+
+use std::path::PathBuf;
+use std::process::Command;
+
+const ISIC: &str = env!("CARGO_BIN_EXE_isic-cli");
+
+#[test]
+fn tests_use_their_own_files_in_memory() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("test_command");
+    std::fs::create_dir_all(&dir).unwrap();
+    let _ = std::fs::remove_file(dir.join("saida.txt"));
+
+    let path = dir.join("arquivos.isi");
+    std::fs::write(
+        &path,
+        r#"programa
+    declare f: arquivo, n: int.
+    f := abra("saida.txt", "a").
+    escrevaarq(f, "linha").
+    feche(f).
+
+    n := 0.
+    f := abra("saida.txt", "l").
+    enquanto (!fimdearquivo(f)) {
+        leialinha(f).
+        n := n + 1.
+    }
+    feche(f).
+    escreva(n).
+
+    teste "primeiro" {
+        saida "1\n".
+    }
+
+    teste "segundo" {
+        saida "1\n".
+    }
+fimprog.
+"#,
+    )
+    .unwrap();
+
+    let out = Command::new(ISIC).arg("test").arg(&path).current_dir(&dir).output().unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+
+    assert!(out.status.success(), "{}", stdout);
+    assert!(stdout.contains("2 passed, 0 failed"), "{}", stdout);
+    assert!(!dir.join("saida.txt").exists());
+}
@@ -6,6 +6,8 @@
 
 use std::hash::Hash;
 
+use unicode_normalization::UnicodeNormalization;
+
 use crate::{
     impl_visitable,
     span::Span,
@@ -21,13 +23,16 @@ pub struct Spanned<T: std::fmt::Debug + PartialEq + Eq> {
 pub struct IntLiteral(pub u64, pub Span);
 
 #[derive(Debug, PartialEq)]
-pub struct FloatLiteral(pub f32, pub Span);
+pub struct FloatLiteral(pub f64, pub Span);
 
 impl std::cmp::Eq for FloatLiteral {} // cheat...
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct StringLiteral(pub String, pub Span);
 
+#[derive(Debug, PartialEq, Eq)]
+pub struct CharLiteral(pub char, pub Span);
+
 #[derive(Clone, Debug, Eq)]
 pub struct Ident {
     pub name: String,
@@ -48,9 +53,11 @@ impl Hash for Ident {
 }
 
 impl Ident {
+    /// Cria um nome, normalizado na forma NFC. Assim, `média` é o mesmo nome
+    /// com o `é` escrito como um caractere ou como `e` seguido do acento.
     pub fn new(name: &str, span: Span) -> Ident {
         Ident {
-            name: name.to_string(),
+            name: name.nfc().collect(),
             span,
         }
     }
@@ -76,7 +83,7 @@ impl VarDecl {
 #[derive(Debug, PartialEq, Eq)]
 pub struct MultiVarDecl(pub Vec<VarDecl>);
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum BinaryOp {
     Add,
     Sub,
@@ -91,6 +98,41 @@ pub enum BinaryOp {
     And,
     Or,
     Mod,
+    Pow,
+    IntDiv,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+}
+
+impl BinaryOp {
+    /// O operador como ele é escrito no código fonte.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Gt => ">",
+            BinaryOp::Lt => "<",
+            BinaryOp::Geq => ">=",
+            BinaryOp::Leq => "<=",
+            BinaryOp::Eq => "==",
+            BinaryOp::Neq => "!=",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+            BinaryOp::Mod => "%",
+            BinaryOp::Pow => "^",
+            BinaryOp::IntDiv => "div",
+            BinaryOp::BitAnd => "&",
+            BinaryOp::BitOr => "|",
+            BinaryOp::BitXor => "xor",
+            BinaryOp::Shl => "<<",
+            BinaryOp::Shr => ">>",
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -124,15 +166,62 @@ impl Negation {
     }
 }
 
+/// A expressão condicional `(c ? a : b)`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Ternary {
+    pub cond: Box<Expr>,
+    pub taken: Box<Expr>,
+    pub not_taken: Box<Expr>,
+}
+
+impl Ternary {
+    pub fn new(cond: Box<Expr>, taken: Box<Expr>, not_taken: Box<Expr>) -> Ternary {
+        Ternary {
+            cond,
+            taken,
+            not_taken,
+        }
+    }
+
+    pub fn get_span(&self) -> Span {
+        self.cond.get_span().merge(&self.not_taken.get_span())
+    }
+}
+
+/// O acesso `s[i]` ao i-ésimo caractere de uma string.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Index {
+    pub target: Box<Expr>,
+    pub index: Box<Expr>,
+    pub span: Span,
+}
+
+impl Index {
+    pub fn new(target: Box<Expr>, index: Box<Expr>, span: Span) -> Index {
+        Index {
+            target,
+            index,
+            span,
+        }
+    }
+
+    pub fn get_span(&self) -> Span {
+        self.span.merge(&self.target.get_span())
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum Expr {
     Ident(Ident),
     ImmInt(IntLiteral),
     ImmFloat(FloatLiteral),
     ImmString(StringLiteral),
+    ImmChar(CharLiteral),
     BinExpr(BinExpr),
     FnCall(FnCall),
     Negation(Negation),
+    Ternary(Ternary),
+    Index(Index),
 }
 
 impl Expr {
@@ -142,10 +231,44 @@ impl Expr {
             Expr::ImmInt(ref imm) => imm.1,
             Expr::ImmString(ref imm) => imm.1,
             Expr::ImmFloat(ref imm) => imm.1,
+            Expr::ImmChar(ref imm) => imm.1,
             Expr::BinExpr(ref bexpr) => bexpr.get_span(),
             Expr::FnCall(ref fcall) => fcall.get_span(),
             Expr::Negation(ref neg) => neg.get_span(),
+            Expr::Ternary(ref tern) => tern.get_span(),
+            Expr::Index(ref idx) => idx.get_span(),
+        }
+    }
+
+    /// As variáveis usadas na expressão, sem repetições, na ordem em que
+    /// aparecem.
+    pub fn vars(&self) -> Vec<&Ident> {
+        fn collect<'e>(expr: &'e Expr, out: &mut Vec<&'e Ident>) {
+            match expr {
+                Expr::Ident(id) if !out.contains(&id) => out.push(id),
+                Expr::BinExpr(bexpr) => {
+                    collect(&bexpr.1, out);
+                    collect(&bexpr.2, out);
+                }
+                Expr::FnCall(call) => call.args.iter().for_each(|arg| collect(arg, out)),
+                Expr::Negation(neg) => collect(&neg.expr, out),
+                Expr::Ternary(tern) => {
+                    collect(&tern.cond, out);
+                    collect(&tern.taken, out);
+                    collect(&tern.not_taken, out);
+                }
+                Expr::Index(idx) => {
+                    collect(&idx.target, out);
+                    collect(&idx.index, out);
+                }
+                _ => {}
+            }
         }
+
+        let mut out = vec![];
+        collect(self, &mut out);
+
+        out
     }
 }
 
@@ -197,23 +320,93 @@ pub struct Conditional {
 #[derive(Debug, PartialEq, Eq)]
 pub struct WhileLoop {
     pub cond: Expr,
+    /// A condição declarada com `invariante`, que deve valer antes da
+    /// primeira iteração e depois de cada uma.
+    pub invariant: Option<Expr>,
     pub body: Vec<Statement>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct DoWhileLoop {
     pub cond: Expr,
+    /// A condição declarada com `invariante`, como no WhileLoop.
+    pub invariant: Option<Expr>,
+    pub body: Vec<Statement>,
+}
+
+/// Um parâmetro de uma função externa, como `a: int`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Param {
+    pub name: Ident,
+    pub ty: Ident,
+}
+
+/// A declaração de uma função escrita em C, que pode ser chamada pelo
+/// programa: `externo funcao nome(a: int): float de "header.h".`
+#[derive(Debug, PartialEq, Eq)]
+pub struct ExternFn {
+    pub name: Ident,
+    pub params: Vec<Param>,
+    /// O tipo de retorno, ou None se a função não retorna nada.
+    pub ret: Option<Ident>,
+    /// O header do C que declara a função.
+    pub header: StringLiteral,
+    pub span: Span,
+}
+
+/// `tente { ... } capture (erro) { ... }`: se um erro em tempo de execução
+/// interromper o `body`, o `handler` é executado, com a descrição do erro
+/// na variável `error_var`, do tipo string.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TryCatch {
+    pub body: Vec<Statement>,
+    pub error_var: Ident,
+    pub handler: Vec<Statement>,
+    pub span: Span,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ContractKind {
+    /// `requer (cond).`: verificada quando o programa chega nela.
+    Requires,
+    /// `garante (cond).`: verificada quando o programa chega ao fim.
+    Ensures,
+}
+
+/// Uma pré ou pós-condição do programa.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Contract {
+    pub kind: ContractKind,
+    pub cond: Expr,
+    pub span: Span,
+}
+
+/// Um bloco de teste, executado somente pelo `isic test`:
+/// `teste "nome" { entrada "3\n4\n". saida "7\n". }`
+#[derive(Debug, PartialEq, Eq)]
+pub struct TestBlock {
+    pub name: StringLiteral,
+    /// O que o programa lê da entrada durante o teste.
+    pub input: Option<StringLiteral>,
+    /// A saída esperada do programa, se ela deve ser comparada.
+    pub output: Option<StringLiteral>,
+    /// Comandos executados depois do programa, como `afirme`s.
     pub body: Vec<Statement>,
+    pub span: Span,
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum Statement {
     Decl(MultiVarDecl),
+    ExternFn(ExternFn),
     FnCall(FnCall),
     Assignment(Assignment),
     Conditional(Conditional),
     WhileLoop(WhileLoop),
     DoWhileLoop(DoWhileLoop),
+    TryCatch(TryCatch),
+    Test(TestBlock),
+    Contract(Contract),
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -230,15 +423,22 @@ impl IsiProgram {
 impl_visitable!(IntLiteral, visit_int_literal);
 impl_visitable!(FloatLiteral, visit_float_literal);
 impl_visitable!(StringLiteral, visit_string_literal);
+impl_visitable!(CharLiteral, visit_char_literal);
 impl_visitable!(Ident, visit_ident);
 impl_visitable!(VarDecl, visit_decl);
 impl_visitable!(MultiVarDecl, visit_multi_decl);
+impl_visitable!(ExternFn, visit_extern_fn);
 impl_visitable!(Expr, visit_expr);
 impl_visitable!(FnCall, visit_fn_call);
 impl_visitable!(Negation, visit_negation);
+impl_visitable!(Ternary, visit_ternary);
+impl_visitable!(Index, visit_index);
 impl_visitable!(Assignment, visit_assignment);
 impl_visitable!(Conditional, visit_conditional);
 impl_visitable!(WhileLoop, visit_while_loop);
 impl_visitable!(DoWhileLoop, visit_do_while_loop);
+impl_visitable!(TryCatch, visit_try_catch);
+impl_visitable!(TestBlock, visit_test);
+impl_visitable!(Contract, visit_contract);
 impl_visitable!(Statement, visit_statement);
 impl_visitable!(BinExpr, visit_bin_expr);
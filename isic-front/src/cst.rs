@@ -0,0 +1,448 @@
+//! # isic_front::cst
+//!
+//! A árvore de sintaxe concreta (CST) da IsiLang. Diferente da AST, ela
+//! guarda todos os bytes do código fonte, incluindo espaços, comentários e
+//! tokens inválidos, então `cst.to_string()` é sempre igual ao código
+//! parseado. Ela é a base para formatadores, refatorações e ferramentas de
+//! editor, e a AST é gerada a partir dela por `crate::lower`.
+//!
+//! Como no Roslyn e no rust-analyzer, a árvore tem duas camadas:
+//!
+//! - A árvore verde (`GreenNode`) é imutável e não guarda posições, só o
+//!   tipo de cada nó, os filhos e o tamanho do texto. Por isso uma subárvore
+//!   pode ser compartilhada entre duas versões do código.
+//! - A árvore vermelha (`SyntaxNode` e `SyntaxToken`) é criada sob demanda
+//!   por cima da verde, e sabe a posição de cada nó e qual é o seu pai.
+//!
+//! Os espaços e comentários são tokens como os outros, e ficam no nó mais
+//! interno que contém os tokens em volta deles. Um nó nunca começa nem
+//! termina com eles, exceto a raiz.
+
+use std::fmt;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::lexer::{Token, TokenKind};
+use crate::span::Span;
+
+/// O tipo de um nó da CST. Os tokens usam o TokenKind do lexer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    /// O programa inteiro, de `programa` a `fimprog.`, com o pragma e os
+    /// espaços em volta.
+    Program,
+    /// `declare a: int, b: float.`
+    Decl,
+    /// `a: int`, dentro de um Decl.
+    VarDecl,
+    /// `externo funcao nome(a: int): float de "header.h".`
+    ExternFn,
+    /// `a: int`, dentro de um ExternFn.
+    Param,
+    /// Uma chamada usada como comando: `escreva(x).`
+    CallStmt,
+    /// `x := expr.`
+    Assignment,
+    /// `se (c) entao { ... } senao { ... }`
+    Conditional,
+    /// `enquanto (c) invariante (i) { ... }`
+    WhileLoop,
+    /// `faca invariante (i) { ... } enquanto (c).`
+    DoWhileLoop,
+    /// `invariante (i)`, dentro de um laço.
+    Invariant,
+    /// `tente { ... } capture (erro) { ... }`
+    TryCatch,
+    /// Uma lista de comandos entre chaves.
+    Block,
+    /// `teste "nome" { ... }`
+    Test,
+    /// `entrada "...".`, dentro de um Test.
+    TestInput,
+    /// `saida "...".`, dentro de um Test.
+    TestOutput,
+    /// `requer (c).` ou `garante (c).`
+    Contract,
+    /// Um nome, tanto onde ele é declarado quanto onde é usado.
+    Name,
+    /// Um número, string ou caractere.
+    Literal,
+    /// `a + b`, com qualquer operador binário.
+    BinExpr,
+    /// `c ? a : b`
+    Ternary,
+    /// `s[i]`
+    Index,
+    /// `!e`
+    Negation,
+    /// `(e)`
+    Paren,
+    /// `f(a, b)`
+    Call,
+    /// Um trecho que não pôde ser parseado.
+    Error,
+}
+
+impl SyntaxKind {
+    /// Retorna true para os nós que são expressões.
+    pub fn is_expr(&self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::Name
+                | SyntaxKind::Literal
+                | SyntaxKind::BinExpr
+                | SyntaxKind::Ternary
+                | SyntaxKind::Index
+                | SyntaxKind::Negation
+                | SyntaxKind::Paren
+                | SyntaxKind::Call
+        )
+    }
+}
+
+/// Um token da árvore verde: o tipo e o texto.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GreenToken {
+    pub kind: TokenKind,
+    pub text: String,
+}
+
+/// Um filho de um nó da árvore verde.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GreenElement {
+    Node(Arc<GreenNode>),
+    Token(Arc<GreenToken>),
+}
+
+impl GreenElement {
+    /// O tamanho do texto, em bytes.
+    pub fn len(&self) -> usize {
+        match self {
+            GreenElement::Node(n) => n.len,
+            GreenElement::Token(t) => t.text.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Um nó da árvore verde.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GreenNode {
+    kind: SyntaxKind,
+    len: usize,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNode {
+    pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> GreenNode {
+        let len = children.iter().map(GreenElement::len).sum();
+
+        GreenNode {
+            kind,
+            len,
+            children,
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    /// O tamanho do texto, em bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+
+    fn write_text(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for child in &self.children {
+            match child {
+                GreenElement::Node(n) => n.write_text(f)?,
+                GreenElement::Token(t) => f.write_str(&t.text)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for GreenNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_text(f)
+    }
+}
+
+#[derive(Debug)]
+struct NodeData {
+    green: Arc<GreenNode>,
+    offset: usize,
+    parent: Option<SyntaxNode>,
+}
+
+/// Um nó da árvore vermelha, que sabe onde ele está no código.
+#[derive(Debug, Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+/// Um token da árvore vermelha.
+#[derive(Debug, Clone)]
+pub struct SyntaxToken {
+    green: Arc<GreenToken>,
+    offset: usize,
+    parent: SyntaxNode,
+}
+
+/// Um filho de um SyntaxNode.
+#[derive(Debug, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    /// Cria a raiz da árvore vermelha, na posição 0.
+    pub fn new_root(green: Arc<GreenNode>) -> SyntaxNode {
+        SyntaxNode(Rc::new(NodeData {
+            green,
+            offset: 0,
+            parent: None,
+        }))
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind
+    }
+
+    pub fn green(&self) -> &Arc<GreenNode> {
+        &self.0.green
+    }
+
+    pub fn span(&self) -> Span {
+        Span {
+            start: self.0.offset,
+            end: self.0.offset + self.0.green.len,
+        }
+    }
+
+    pub fn parent(&self) -> Option<&SyntaxNode> {
+        self.0.parent.as_ref()
+    }
+
+    /// Os filhos do nó, nós e tokens, na ordem do código.
+    pub fn children_with_tokens(&self) -> impl Iterator<Item = SyntaxElement> + '_ {
+        let mut offset = self.0.offset;
+
+        self.0.green.children.iter().map(move |child| {
+            let start = offset;
+            offset += child.len();
+
+            match child {
+                GreenElement::Node(n) => SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                    green: n.clone(),
+                    offset: start,
+                    parent: Some(self.clone()),
+                }))),
+                GreenElement::Token(t) => SyntaxElement::Token(SyntaxToken {
+                    green: t.clone(),
+                    offset: start,
+                    parent: self.clone(),
+                }),
+            }
+        })
+    }
+
+    /// Os nós filhos, sem os tokens.
+    pub fn children(&self) -> impl Iterator<Item = SyntaxNode> + '_ {
+        self.children_with_tokens().filter_map(|c| match c {
+            SyntaxElement::Node(n) => Some(n),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// Os tokens filhos, sem os espaços e comentários.
+    pub fn child_tokens(&self) -> impl Iterator<Item = SyntaxToken> + '_ {
+        self.children_with_tokens().filter_map(|c| match c {
+            SyntaxElement::Token(t) if !t.kind().is_trivia() => Some(t),
+            _ => None,
+        })
+    }
+
+    /// Todos os tokens do nó e dos seus descendentes, incluindo espaços e
+    /// comentários, na ordem do código.
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        let mut out = vec![];
+
+        for child in self.children_with_tokens() {
+            match child {
+                SyntaxElement::Node(n) => out.extend(n.tokens()),
+                SyntaxElement::Token(t) => out.push(t),
+            }
+        }
+
+        out
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.green.write_text(f)
+    }
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> TokenKind {
+        self.green.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+
+    pub fn span(&self) -> Span {
+        Span {
+            start: self.offset,
+            end: self.offset + self.green.text.len(),
+        }
+    }
+
+    pub fn parent(&self) -> &SyntaxNode {
+        &self.parent
+    }
+}
+
+impl fmt::Display for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.green.text)
+    }
+}
+
+/// Um nó como o parser monta, só com os tokens que não são espaços nem
+/// comentários. O `build` insere esses tokens depois.
+#[derive(Debug)]
+pub(crate) enum Draft {
+    Node(SyntaxKind, Vec<Draft>),
+    Token(Token),
+}
+
+impl Draft {
+    fn first_token(&self) -> Option<&Token> {
+        match self {
+            Draft::Node(_, children) => children.iter().find_map(Draft::first_token),
+            Draft::Token(t) => Some(t),
+        }
+    }
+}
+
+impl From<Token> for Draft {
+    fn from(t: Token) -> Draft {
+        Draft::Token(t)
+    }
+}
+
+/// Monta a árvore verde a partir dos tokens, com os espaços e comentários.
+struct Builder<'a> {
+    source: &'a str,
+    tokens: &'a [Token],
+    /// O próximo token a ser adicionado.
+    pos: usize,
+}
+
+impl<'a> Builder<'a> {
+    fn next_token(&mut self) -> GreenElement {
+        let t = self.tokens[self.pos];
+        self.pos += 1;
+
+        GreenElement::Token(Arc::new(GreenToken {
+            kind: t.kind,
+            text: t.text(self.source).to_string(),
+        }))
+    }
+
+    /// Adiciona a `out` os tokens que começam antes de `offset`, que são
+    /// espaços e comentários.
+    fn trivia(&mut self, offset: usize, out: &mut Vec<GreenElement>) {
+        while self.tokens.get(self.pos).is_some_and(|t| t.span.start < offset) {
+            out.push(self.next_token());
+        }
+    }
+
+    fn child(&mut self, draft: Draft, out: &mut Vec<GreenElement>) {
+        match draft {
+            Draft::Node(kind, children) => {
+                // Os espaços antes do nó ficam fora dele.
+                if let Some(t) = children.iter().find_map(Draft::first_token) {
+                    self.trivia(t.span.start, out);
+                }
+
+                let mut elements = vec![];
+
+                for child in children {
+                    self.child(child, &mut elements);
+                }
+
+                out.push(GreenElement::Node(Arc::new(GreenNode::new(kind, elements))));
+            }
+            Draft::Token(t) => {
+                self.trivia(t.span.start, out);
+                out.push(self.next_token());
+            }
+        }
+    }
+}
+
+/// Monta a árvore verde de `draft`, que foi parseado de `tokens`. Todos os
+/// tokens que não estão em `draft` devem ser espaços ou comentários, e os
+/// que estão antes ou depois dele ficam na raiz.
+pub(crate) fn build(source: &str, tokens: &[Token], draft: Draft) -> GreenNode {
+    let mut builder = Builder {
+        source,
+        tokens,
+        pos: 0,
+    };
+
+    let Draft::Node(kind, children) = draft else {
+        unreachable!("a raiz da CST deve ser um nó");
+    };
+
+    let mut elements = vec![];
+
+    for child in children {
+        builder.child(child, &mut elements);
+    }
+
+    builder.trivia(usize::MAX, &mut elements);
+
+    GreenNode::new(kind, elements)
+}
+
+/// Uma árvore com todos os tokens dentro de um nó Error, para códigos que
+/// não puderam ser parseados.
+pub(crate) fn error_tree(source: &str, tokens: &[Token]) -> GreenNode {
+    let error = Draft::Node(SyntaxKind::Error, tokens.iter().map(|&t| t.into()).collect());
+
+    build(source, tokens, Draft::Node(SyntaxKind::Program, vec![error]))
+}
+
+/// Uma CST, com o erro de sintaxe, se houve um. Se houve, a árvore tem um
+/// único nó Error com todos os tokens, e ainda é igual ao código fonte.
+#[derive(Debug, Clone)]
+pub struct Parse {
+    pub green: Arc<GreenNode>,
+    pub error: Option<crate::parser::SyntaxError>,
+}
+
+impl Parse {
+    /// A raiz da árvore vermelha.
+    pub fn syntax(&self) -> SyntaxNode {
+        SyntaxNode::new_root(self.green.clone())
+    }
+}
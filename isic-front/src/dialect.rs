@@ -0,0 +1,303 @@
+//! # isic_front::dialect
+//!
+//! Os dialetos da IsiLang: tabelas com as palavras-chave e os nomes de
+//! algumas funções embutidas, que o parser usa no lugar de palavras fixas.
+//!
+//! O dialeto de um arquivo é escolhido pelo pragma `#dialeto`, na primeira
+//! linha, ou por quem chama o parser. Independente do dialeto, a AST usa
+//! sempre os nomes em português das funções embutidas, então o resto do
+//! compilador não precisa saber qual dialeto foi usado.
+
+use std::collections::HashSet;
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::ast::{IsiProgram, Statement};
+use crate::lexer::{tokenize, TokenKind};
+use crate::parser::parse;
+
+/// As palavras-chave da IsiLang.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Keyword {
+    Program,
+    EndProgram,
+    Declare,
+    If,
+    Then,
+    Else,
+    While,
+    Do,
+    Invariant,
+    Try,
+    Catch,
+    Test,
+    Input,
+    Output,
+    Requires,
+    Ensures,
+    Extern,
+    Function,
+    From,
+}
+
+impl Keyword {
+    pub const ALL: &'static [Keyword] = &[
+        Keyword::Program,
+        Keyword::EndProgram,
+        Keyword::Declare,
+        Keyword::If,
+        Keyword::Then,
+        Keyword::Else,
+        Keyword::While,
+        Keyword::Do,
+        Keyword::Invariant,
+        Keyword::Try,
+        Keyword::Catch,
+        Keyword::Test,
+        Keyword::Input,
+        Keyword::Output,
+        Keyword::Requires,
+        Keyword::Ensures,
+        Keyword::Extern,
+        Keyword::Function,
+        Keyword::From,
+    ];
+
+    /// Retorna true se a palavra-chave não pode ser usada como nome de uma
+    /// variável. As outras só são palavras-chave onde aparecem, como
+    /// `entrada` e `saida` num bloco `teste`, já que são nomes comuns.
+    pub fn is_reserved(&self) -> bool {
+        !matches!(
+            self,
+            Keyword::Invariant
+                | Keyword::Test
+                | Keyword::Input
+                | Keyword::Output
+                | Keyword::Function
+                | Keyword::From
+        )
+    }
+}
+
+/// Um dialeto da IsiLang.
+///
+/// Novos dialetos podem ser criados fora desta crate, preenchendo a tabela
+/// de palavras-chave e a de funções.
+#[derive(Debug)]
+pub struct Dialect {
+    /// O nome do dialeto, usado no pragma `#dialeto` e na linha de comando.
+    pub name: &'static str,
+    /// Como cada palavra-chave é escrita.
+    pub keyword: fn(Keyword) -> &'static str,
+    /// Os nomes das funções embutidas que mudam no dialeto, como pares
+    /// (nome em português, nome no dialeto).
+    pub functions: &'static [(&'static str, &'static str)],
+}
+
+/// O dialeto padrão, em português.
+pub const PT: Dialect = Dialect {
+    name: "pt",
+    keyword: |k| match k {
+        Keyword::Program => "programa",
+        Keyword::EndProgram => "fimprog",
+        Keyword::Declare => "declare",
+        Keyword::If => "se",
+        Keyword::Then => "entao",
+        Keyword::Else => "senao",
+        Keyword::While => "enquanto",
+        Keyword::Do => "faca",
+        Keyword::Invariant => "invariante",
+        Keyword::Try => "tente",
+        Keyword::Catch => "capture",
+        Keyword::Test => "teste",
+        Keyword::Input => "entrada",
+        Keyword::Output => "saida",
+        Keyword::Requires => "requer",
+        Keyword::Ensures => "garante",
+        Keyword::Extern => "externo",
+        Keyword::Function => "funcao",
+        Keyword::From => "de",
+    },
+    functions: &[],
+};
+
+/// O dialeto em inglês.
+pub const EN: Dialect = Dialect {
+    name: "en",
+    keyword: |k| match k {
+        Keyword::Program => "program",
+        Keyword::EndProgram => "end",
+        Keyword::Declare => "declare",
+        Keyword::If => "if",
+        Keyword::Then => "then",
+        Keyword::Else => "else",
+        Keyword::While => "while",
+        Keyword::Do => "do",
+        Keyword::Invariant => "invariant",
+        Keyword::Try => "try",
+        Keyword::Catch => "catch",
+        Keyword::Test => "test",
+        Keyword::Input => "input",
+        Keyword::Output => "output",
+        Keyword::Requires => "requires",
+        Keyword::Ensures => "ensures",
+        Keyword::Extern => "extern",
+        Keyword::Function => "function",
+        Keyword::From => "from",
+    },
+    functions: &[("escreva", "print"), ("escrevasl", "println"), ("leia", "read")],
+};
+
+/// Todos os dialetos disponíveis.
+pub const DIALECTS: &[&Dialect] = &[&PT, &EN];
+
+impl Dialect {
+    /// Busca um dialeto pelo nome.
+    pub fn by_name(name: &str) -> Result<&'static Dialect, String> {
+        DIALECTS
+            .iter()
+            .find(|d| d.name == name)
+            .copied()
+            .ok_or_else(|| format!("Unknown dialect {} (expected pt or en)", name))
+    }
+
+    /// O dialeto de um código fonte: o do pragma `#dialeto`, se houver, ou
+    /// `default`.
+    pub fn detect(source: &str, default: &'static Dialect) -> Result<&'static Dialect, String> {
+        match pragma(source) {
+            Some(name) => Dialect::by_name(name),
+            None => Ok(default),
+        }
+    }
+
+    pub fn keyword(&self, k: Keyword) -> &'static str {
+        (self.keyword)(k)
+    }
+
+    /// A palavra-chave escrita como `word` no dialeto, se houver uma.
+    pub fn keyword_of(&self, word: &str) -> Option<Keyword> {
+        Keyword::ALL.iter().copied().find(|&k| self.keyword(k) == word)
+    }
+
+    /// O nome em português da função chamada `name` no dialeto, se ele for
+    /// diferente.
+    pub fn canonical_function(&self, name: &str) -> Option<&'static str> {
+        self.functions.iter().find(|f| f.1 == name).map(|f| f.0)
+    }
+
+    /// Como a função embutida `canonical` se chama no dialeto.
+    pub fn function_name<'a>(&self, canonical: &'a str) -> &'a str {
+        match self.functions.iter().find(|f| f.0 == canonical) {
+            Some(f) => f.1,
+            None => canonical,
+        }
+    }
+}
+
+/// O nome do dialeto dado pelo pragma `#dialeto` no início do código.
+pub fn pragma(source: &str) -> Option<&str> {
+    let rest = source.trim_start().strip_prefix("#dialeto")?;
+
+    rest.split('\n').next().map(str::trim)
+}
+
+/// Os nomes declarados pelo programa: variáveis, funções externas e seus
+/// parâmetros.
+fn declared_names<'a>(stmts: &'a [Statement], names: &mut HashSet<&'a str>) {
+    for stmt in stmts {
+        match stmt {
+            Statement::Decl(decls) => {
+                names.extend(decls.0.iter().map(|d| d.var_name.name.as_str()));
+            }
+            Statement::ExternFn(ext) => {
+                names.insert(&ext.name.name);
+                names.extend(ext.params.iter().map(|p| p.name.name.as_str()));
+            }
+            Statement::Conditional(cond) => {
+                declared_names(&cond.taken, names);
+                declared_names(&cond.not_taken, names);
+            }
+            Statement::WhileLoop(l) => declared_names(&l.body, names),
+            Statement::DoWhileLoop(l) => declared_names(&l.body, names),
+            Statement::TryCatch(t) => {
+                names.insert(&t.error_var.name);
+                declared_names(&t.body, names);
+                declared_names(&t.handler, names);
+            }
+            Statement::Test(t) => declared_names(&t.body, names),
+            Statement::FnCall(_) | Statement::Assignment(_) | Statement::Contract(_) => {}
+        }
+    }
+}
+
+/// Reescreve `source`, um programa no dialeto `from` já parseado como
+/// `program`, no dialeto `to`.
+///
+/// Só as palavras-chave e os nomes das funções embutidas mudam: os nomes
+/// declarados pelo programa, o conteúdo de strings e a formatação são
+/// mantidos. O pragma `#dialeto` é atualizado, ou removido se `to` é o
+/// dialeto padrão.
+pub fn translate(
+    source: &str,
+    program: &IsiProgram,
+    from: &Dialect,
+    to: &'static Dialect,
+) -> Result<String, String> {
+    let mut declared = HashSet::new();
+    declared_names(&program.statements, &mut declared);
+
+    // Um nome declarado que é reservado no dialeto de destino não pode ser
+    // mantido.
+    let mut clashes: Vec<_> = declared
+        .iter()
+        .filter(|name| to.keyword_of(name).is_some_and(|k| k.is_reserved()))
+        .collect();
+
+    if !clashes.is_empty() {
+        clashes.sort();
+
+        return Err(format!(
+            "Could not translate the program to {}: {} is a reserved keyword there",
+            to.name, clashes[0]
+        ));
+    }
+
+    let mut out = String::with_capacity(source.len());
+
+    for t in tokenize(source, from) {
+        let text = t.text(source);
+
+        match t.kind {
+            // Nomes declarados, como uma variável chamada `saida`, são
+            // mantidos. Os nomes na AST estão normalizados.
+            TokenKind::Keyword(_) | TokenKind::Ident
+                if declared.contains(text.nfc().collect::<String>().as_str()) =>
+            {
+                out.push_str(text)
+            }
+            TokenKind::Keyword(k) => out.push_str(to.keyword(k)),
+            TokenKind::Ident => {
+                let canonical = from.canonical_function(text).unwrap_or(text);
+                out.push_str(to.function_name(canonical));
+            }
+            _ => out.push_str(text),
+        }
+    }
+
+    // O pragma foi copiado sem mudanças, já que o nome do dialeto não é uma
+    // palavra-chave. O português não precisa de pragma.
+    if pragma(&out).is_some() {
+        let start = out.len() - out.trim_start().len();
+        let end = out[start..].find('\n').map_or(out.len(), |n| start + n + 1);
+
+        out.replace_range(start..end, "");
+    }
+
+    if to.name != PT.name {
+        out.insert_str(0, &format!("#dialeto {}\n", to.name));
+    }
+
+    debug_assert!(parse(&out, to).is_ok());
+
+    Ok(out)
+}
@@ -0,0 +1,135 @@
+//! # isic_front::incremental
+//!
+//! Parse incremental, para editores que refazem o parse a cada tecla.
+//!
+//! Dada a CST de uma versão do código e uma edição, `reparse` encontra o
+//! bloco mais interno (`{ ... }`) que contém a edição e refaz o lexer e o
+//! parse só do texto dele. O resto da árvore verde é reaproveitado: só os
+//! nós no caminho da raiz até o bloco são recriados, e os outros são os
+//! mesmos `Arc`s da árvore antiga.
+//!
+//! Quando isso não é possível, como numa edição fora de qualquer bloco, ou
+//! se o bloco editado não é mais um bloco válido, o parse é refeito do zero.
+//! Em qualquer caso, o resultado é igual ao de `parser::parse_cst` no código
+//! editado.
+
+use std::sync::Arc;
+
+use crate::cst::{self, GreenElement, GreenNode, SyntaxKind, SyntaxNode};
+use crate::dialect::Dialect;
+use crate::lexer::{tokenize, Token, TokenKind};
+use crate::parser::{isilang_parser, parse_cst};
+use crate::span::Span;
+
+/// Uma edição de texto: o trecho `span` do código é substituído por `text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub span: Span,
+    pub text: String,
+}
+
+impl TextEdit {
+    /// Aplica a edição em `source`. Entra em pânico se o span não está
+    /// dentro do código, ou se não cai entre dois caracteres, como
+    /// `String::replace_range`.
+    pub fn apply(&self, source: &mut String) {
+        source.replace_range(self.span.start..self.span.end, &self.text);
+    }
+}
+
+/// Refaz o parse de um código depois de uma edição, reaproveitando o que
+/// não mudou de `old`, a CST do código antes da edição.
+pub fn reparse(old: &cst::Parse, edit: &TextEdit, dialect: &Dialect) -> cst::Parse {
+    if old.error.is_none() {
+        if let Some(green) = reparse_block(&old.syntax(), edit, dialect) {
+            return cst::Parse { green, error: None };
+        }
+    }
+
+    let mut source = old.green.to_string();
+    edit.apply(&mut source);
+
+    parse_cst(&source, dialect)
+}
+
+/// O bloco mais interno que contém a edição, sem contar as chaves, e o
+/// caminho até ele: o índice de cada nó entre os filhos do seu pai.
+fn enclosing_block(root: &SyntaxNode, edit: Span) -> Option<(SyntaxNode, Vec<usize>)> {
+    let mut node = root.clone();
+    let mut path = vec![];
+    let mut found = None;
+
+    loop {
+        let next = node.children_with_tokens().enumerate().find_map(|(i, child)| match child {
+            cst::SyntaxElement::Node(n) if n.span().start <= edit.start && edit.end <= n.span().end => {
+                Some((i, n))
+            }
+            _ => None,
+        });
+
+        let Some((i, child)) = next else {
+            return found;
+        };
+
+        path.push(i);
+
+        // A edição não pode tocar nas chaves, ou o trecho parseado de novo
+        // não seria mais um bloco.
+        let span = child.span();
+
+        if child.kind() == SyntaxKind::Block && span.start < edit.start && edit.end < span.end {
+            found = Some((child.clone(), path.clone()));
+        }
+
+        node = child;
+    }
+}
+
+/// Refaz o parse só do bloco que contém a edição, e retorna a nova árvore,
+/// ou None se o parse precisa ser refeito do zero.
+fn reparse_block(root: &SyntaxNode, edit: &TextEdit, dialect: &Dialect) -> Option<Arc<GreenNode>> {
+    let (block, path) = enclosing_block(root, edit.span)?;
+    let start = block.span().start;
+
+    let mut text = block.to_string();
+    let local = TextEdit {
+        span: Span {
+            start: edit.span.start - start,
+            end: edit.span.end - start,
+        },
+        text: edit.text.clone(),
+    };
+    local.apply(&mut text);
+
+    let all = tokenize(&text, dialect);
+
+    // O texto novo precisa terminar exatamente na chave que fecha o bloco.
+    // Senão, como numa string que não foi fechada, os tokens depois do
+    // bloco também mudariam.
+    if all.last().map(|t| (t.kind, t.span.end)) != Some((TokenKind::RBrace, text.len())) {
+        return None;
+    }
+
+    let tokens: Vec<Token> = all.iter().copied().filter(|t| !t.kind.is_trivia()).collect();
+    let draft = isilang_parser::block(&tokens, &text, dialect).ok()?;
+    let green = cst::build(&text, &all, draft);
+
+    Some(replace(root.green(), &path, Arc::new(green)))
+}
+
+/// Uma cópia de `node` com o descendente no caminho `path` substituído por
+/// `new`. Os nós fora do caminho são compartilhados com `node`.
+fn replace(node: &Arc<GreenNode>, path: &[usize], new: Arc<GreenNode>) -> Arc<GreenNode> {
+    let Some((&i, rest)) = path.split_first() else {
+        return new;
+    };
+
+    let mut children = node.children().to_vec();
+
+    let GreenElement::Node(child) = &children[i] else {
+        unreachable!("o caminho só passa por nós");
+    };
+    children[i] = GreenElement::Node(replace(child, rest, new));
+
+    Arc::new(GreenNode::new(node.kind(), children))
+}
@@ -0,0 +1,349 @@
+//! # isic_front::lexer
+//!
+//! O lexer da IsiLang, que divide o código fonte em tokens.
+//!
+//! Nenhum byte do código é descartado: espaços em branco, comentários e
+//! caracteres inválidos também viram tokens, então concatenar o texto dos
+//! tokens dá o código original. O parser ignora os espaços em branco e os
+//! comentários, e ferramentas como highlighters podem usar `tokenize`
+//! diretamente.
+
+use crate::dialect::{Dialect, Keyword};
+use crate::span::Span;
+
+/// O tipo de um token.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// Espaços, tabs e quebras de linha, incluindo `\r`.
+    Whitespace,
+    /// Um comentário, de `//` até o fim da linha.
+    Comment,
+    /// O pragma `#dialeto`, até o fim da linha.
+    Pragma,
+    /// Uma palavra-chave do dialeto.
+    Keyword(Keyword),
+    Ident,
+    Int,
+    Float,
+    Str,
+    Char,
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Dot,
+    Comma,
+    Colon,
+    Assign,
+    Question,
+    Bang,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Caret,
+    Lt,
+    Gt,
+    Leq,
+    Geq,
+    EqEq,
+    Neq,
+    AndAnd,
+    OrOr,
+    Amp,
+    Pipe,
+    Shl,
+    Shr,
+    Xor,
+    IntDiv,
+    /// Um caractere que não começa nenhum token, ou uma string ou caractere
+    /// sem o fim.
+    Error,
+}
+
+impl TokenKind {
+    /// Retorna true para os tokens que o parser ignora.
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, TokenKind::Whitespace | TokenKind::Comment)
+    }
+
+    /// Como o token aparece nas mensagens de erro de sintaxe. As
+    /// palavras-chave dependem do dialeto, e são tratadas pelo parser.
+    pub fn describe(&self) -> &'static str {
+        match self {
+            TokenKind::Whitespace => "whitespace",
+            TokenKind::Comment => "comment",
+            TokenKind::Pragma => "\"#dialeto\"",
+            TokenKind::Keyword(_) => "keyword",
+            TokenKind::Ident => "identifier",
+            TokenKind::Int => "integer",
+            TokenKind::Float => "real number",
+            TokenKind::Str => "string",
+            TokenKind::Char => "character",
+            TokenKind::LParen => "\"(\"",
+            TokenKind::RParen => "\")\"",
+            TokenKind::LBrace => "\"{\"",
+            TokenKind::RBrace => "\"}\"",
+            TokenKind::LBracket => "\"[\"",
+            TokenKind::RBracket => "\"]\"",
+            TokenKind::Dot => "\".\"",
+            TokenKind::Comma => "\",\"",
+            TokenKind::Colon => "\":\"",
+            TokenKind::Assign => "\":=\"",
+            TokenKind::Question => "\"?\"",
+            TokenKind::Bang => "\"!\"",
+            TokenKind::Plus => "\"+\"",
+            TokenKind::Minus => "\"-\"",
+            TokenKind::Star => "\"*\"",
+            TokenKind::Slash => "\"/\"",
+            TokenKind::Percent => "\"%\"",
+            TokenKind::Caret => "\"^\"",
+            TokenKind::Lt => "\"<\"",
+            TokenKind::Gt => "\">\"",
+            TokenKind::Leq => "\"<=\"",
+            TokenKind::Geq => "\">=\"",
+            TokenKind::EqEq => "\"==\"",
+            TokenKind::Neq => "\"!=\"",
+            TokenKind::AndAnd => "\"&&\"",
+            TokenKind::OrOr => "\"||\"",
+            TokenKind::Amp => "\"&\"",
+            TokenKind::Pipe => "\"|\"",
+            TokenKind::Shl => "\"<<\"",
+            TokenKind::Shr => "\">>\"",
+            TokenKind::Xor => "\"xor\"",
+            TokenKind::IntDiv => "\"div\"",
+            TokenKind::Error => "invalid token",
+        }
+    }
+}
+
+/// Um token, com a posição dele no código fonte.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+impl Token {
+    /// O texto do token no código fonte.
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.span.start..self.span.end]
+    }
+}
+
+/// Os caracteres que podem vir depois de uma barra em strings e caracteres.
+/// Uma barra seguida de qualquer outro caractere é mantida literalmente.
+const ESCAPES: &[char] = &['n', 't', '\\', '"', '\''];
+
+/// Substitui as sequências de escape do conteúdo de uma string ou caractere.
+pub fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(&e)) if ESCAPES.contains(&e) => {
+                chars.next();
+                out.push(match e {
+                    'n' => '\n',
+                    't' => '\t',
+                    e => e,
+                });
+            }
+            (c, _) => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Os nomes seguem o padrão do Unicode (UAX #31), com `_` também no
+/// início, então podem ter acentos, como `média`.
+fn is_ident_start(c: char) -> bool {
+    c == '_' || unicode_ident::is_xid_start(c)
+}
+
+fn is_ident_continue(c: char) -> bool {
+    unicode_ident::is_xid_continue(c)
+}
+
+/// O lexer em si. Guarda o código e a posição do próximo caractere.
+struct Lexer<'a> {
+    source: &'a str,
+    pos: usize,
+    dialect: &'a Dialect,
+}
+
+impl<'a> Lexer<'a> {
+    fn peek(&self) -> Option<char> {
+        self.source[self.pos..].chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.source[self.pos..].chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+
+        Some(c)
+    }
+
+    fn eat_while(&mut self, f: impl Fn(char) -> bool) {
+        while self.peek().is_some_and(&f) {
+            self.bump();
+        }
+    }
+
+    /// Lê o resto de uma string ou caractere, depois da aspa de abertura.
+    /// Retorna false se a linha acabou antes da aspa de fechamento.
+    fn quoted(&mut self, quote: char) -> bool {
+        while let Some(c) = self.bump() {
+            match c {
+                '\\' if self.peek().is_some_and(|e| ESCAPES.contains(&e)) => {
+                    self.bump();
+                }
+                '\n' => {
+                    // A quebra de linha não faz parte do token.
+                    self.pos -= 1;
+                    return false;
+                }
+                c if c == quote => return true,
+                _ => {}
+            }
+        }
+
+        false
+    }
+
+    fn word(&mut self, start: usize) -> TokenKind {
+        self.eat_while(is_ident_continue);
+
+        match &self.source[start..self.pos] {
+            "xor" => TokenKind::Xor,
+            "div" => TokenKind::IntDiv,
+            word => match self.dialect.keyword_of(word) {
+                Some(k) => TokenKind::Keyword(k),
+                None => TokenKind::Ident,
+            },
+        }
+    }
+
+    fn number(&mut self) -> TokenKind {
+        self.eat_while(|c| c.is_ascii_digit());
+
+        // Os números reais usam vírgula, como em 3,14.
+        if self.peek() == Some(',') && self.peek_nth(1).is_some_and(|c| c.is_ascii_digit()) {
+            self.bump();
+            self.eat_while(|c| c.is_ascii_digit());
+
+            TokenKind::Float
+        } else {
+            TokenKind::Int
+        }
+    }
+
+    /// Um operador de dois caracteres, se o próximo for `second`, ou senão o
+    /// de um caractere.
+    fn pair(&mut self, second: char, two: TokenKind, one: TokenKind) -> TokenKind {
+        if self.peek() == Some(second) {
+            self.bump();
+            two
+        } else {
+            one
+        }
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        let start = self.pos;
+        let c = self.bump()?;
+
+        let kind = match c {
+            ' ' | '\t' | '\n' | '\r' => {
+                self.eat_while(|c| matches!(c, ' ' | '\t' | '\n' | '\r'));
+                TokenKind::Whitespace
+            }
+            '#' if self.source[start..].starts_with("#dialeto") => {
+                self.eat_while(|c| c != '\n');
+                TokenKind::Pragma
+            }
+            c if is_ident_start(c) => self.word(start),
+            c if c.is_ascii_digit() => self.number(),
+            '"' => {
+                if self.quoted('"') {
+                    TokenKind::Str
+                } else {
+                    TokenKind::Error
+                }
+            }
+            '\'' => {
+                // Exatamente um caractere, ou uma sequência de escape.
+                if self.quoted('\'')
+                    && unescape(&self.source[start + 1..self.pos - 1]).chars().count() == 1
+                {
+                    TokenKind::Char
+                } else {
+                    TokenKind::Error
+                }
+            }
+            '(' => TokenKind::LParen,
+            ')' => TokenKind::RParen,
+            '{' => TokenKind::LBrace,
+            '}' => TokenKind::RBrace,
+            '[' => TokenKind::LBracket,
+            ']' => TokenKind::RBracket,
+            '.' => TokenKind::Dot,
+            ',' => TokenKind::Comma,
+            ':' => self.pair('=', TokenKind::Assign, TokenKind::Colon),
+            '?' => TokenKind::Question,
+            '!' => self.pair('=', TokenKind::Neq, TokenKind::Bang),
+            '+' => TokenKind::Plus,
+            '-' => TokenKind::Minus,
+            '*' => TokenKind::Star,
+            '/' if self.peek() == Some('/') => {
+                self.eat_while(|c| c != '\n');
+                TokenKind::Comment
+            }
+            '/' => TokenKind::Slash,
+            '%' => TokenKind::Percent,
+            '^' => TokenKind::Caret,
+            '<' => match self.peek() {
+                Some('=') => self.pair('=', TokenKind::Leq, TokenKind::Lt),
+                _ => self.pair('<', TokenKind::Shl, TokenKind::Lt),
+            },
+            '>' => match self.peek() {
+                Some('=') => self.pair('=', TokenKind::Geq, TokenKind::Gt),
+                _ => self.pair('>', TokenKind::Shr, TokenKind::Gt),
+            },
+            '=' => self.pair('=', TokenKind::EqEq, TokenKind::Error),
+            '&' => self.pair('&', TokenKind::AndAnd, TokenKind::Amp),
+            '|' => self.pair('|', TokenKind::OrOr, TokenKind::Pipe),
+            _ => TokenKind::Error,
+        };
+
+        Some(Token {
+            kind,
+            span: Span {
+                start,
+                end: self.pos,
+            },
+        })
+    }
+}
+
+/// Divide o código fonte em tokens, reconhecendo as palavras-chave do
+/// dialeto dado. Nunca falha: o que não é um token válido vira um token
+/// TokenKind::Error, que o parser rejeita.
+pub fn tokenize(source: &str, dialect: &Dialect) -> Vec<Token> {
+    let mut lexer = Lexer {
+        source,
+        pos: 0,
+        dialect,
+    };
+
+    std::iter::from_fn(|| lexer.next_token()).collect()
+}
@@ -1,12 +1,19 @@
 //! # isic-front
 //!
-//! Esta crate contém o parser da IsiLang e a AST gerada por ele.
+//! Esta crate contém o parser da IsiLang, a CST gerada por ele e a AST gerada
+//! a partir da CST.
 //!
 //! Nenhuma regra semântica é implementada nesta crate. O único propósito dela é
-//! exportar a AST, parseada a partir do código fonte.
+//! exportar a CST e a AST, parseadas a partir do código fonte.
 
 pub mod ast;
+pub mod cst;
+pub mod dialect;
+pub mod incremental;
+pub mod lexer;
+pub mod lower;
 pub mod parser;
+pub mod source;
 pub mod span;
 pub mod visitor;
 
@@ -0,0 +1,278 @@
+//! # isic_front::lower
+//!
+//! Gera a AST a partir da CST. A CST já foi validada pelo parser, então
+//! aqui só é preciso descartar o que a AST não guarda, como os espaços, os
+//! parênteses e a pontuação, e converter os literais.
+//!
+//! Os Spans da AST são os mesmos de quando ela era gerada diretamente pelo
+//! parser, já que os checkers usam os Spans das expressões como chaves.
+
+use crate::ast;
+use crate::cst::{SyntaxKind, SyntaxNode, SyntaxToken};
+use crate::dialect::{Dialect, Keyword};
+use crate::lexer::{unescape, TokenKind};
+
+/// Gera a AST de um programa. A CST deve ter sido parseada sem erros.
+pub fn program(root: &SyntaxNode, dialect: &Dialect) -> ast::IsiProgram {
+    let lower = Lower { dialect };
+
+    ast::IsiProgram::new(lower.statements(root))
+}
+
+struct Lower<'a> {
+    dialect: &'a Dialect,
+}
+
+/// O primeiro token de um nó que não é espaço nem comentário.
+fn first_token(node: &SyntaxNode) -> SyntaxToken {
+    node.child_tokens().next().expect("nó sem tokens na CST")
+}
+
+/// A string filha de um nó, como o header de um ExternFn.
+fn string_token(node: &SyntaxNode) -> ast::StringLiteral {
+    let t = node.child_tokens().find(|t| t.kind() == TokenKind::Str).unwrap();
+
+    string_literal(&t)
+}
+
+/// As expressões filhas de um nó.
+fn exprs(node: &SyntaxNode) -> Vec<SyntaxNode> {
+    node.children().filter(|n| n.kind().is_expr()).collect()
+}
+
+fn string_literal(t: &SyntaxToken) -> ast::StringLiteral {
+    let text = t.text();
+
+    ast::StringLiteral(unescape(&text[1..text.len() - 1]), t.span())
+}
+
+impl<'a> Lower<'a> {
+    /// Os comandos filhos de um nó, como um Block ou o programa.
+    fn statements(&self, node: &SyntaxNode) -> Vec<ast::Statement> {
+        node.children().filter_map(|n| self.statement(&n)).collect()
+    }
+
+    /// Os comandos do `n`-ésimo Block filho de um nó.
+    fn block(&self, node: &SyntaxNode, n: usize) -> Vec<ast::Statement> {
+        node.children()
+            .filter(|c| c.kind() == SyntaxKind::Block)
+            .nth(n)
+            .map(|b| self.statements(&b))
+            .unwrap_or_default()
+    }
+
+    /// A expressão do Invariant filho de um nó, se houver um.
+    fn invariant(&self, node: &SyntaxNode) -> Option<ast::Expr> {
+        node.children()
+            .find(|c| c.kind() == SyntaxKind::Invariant)
+            .map(|i| self.expr(&exprs(&i)[0]))
+    }
+
+    fn name(&self, node: &SyntaxNode) -> ast::Ident {
+        let t = first_token(node);
+
+        ast::Ident::new(t.text(), t.span())
+    }
+
+    /// Os Names filhos de um nó.
+    fn names(&self, node: &SyntaxNode) -> Vec<ast::Ident> {
+        node.children()
+            .filter(|c| c.kind() == SyntaxKind::Name)
+            .map(|c| self.name(&c))
+            .collect()
+    }
+
+    /// O comando de um nó, ou None se o nó não é um comando.
+    fn statement(&self, node: &SyntaxNode) -> Option<ast::Statement> {
+        let stmt = match node.kind() {
+            SyntaxKind::Decl => ast::Statement::Decl(ast::MultiVarDecl(
+                node.children()
+                    .map(|d| {
+                        let names = self.names(&d);
+                        let [var_name, var_type] = <[_; 2]>::try_from(names).unwrap();
+
+                        ast::VarDecl::new(var_name, var_type, d.span())
+                    })
+                    .collect(),
+            )),
+            SyntaxKind::ExternFn => {
+                let mut names = self.names(node).into_iter();
+                let name = names.next().unwrap();
+
+                let params = node
+                    .children()
+                    .filter(|c| c.kind() == SyntaxKind::Param)
+                    .map(|p| {
+                        let [name, ty] = <[_; 2]>::try_from(self.names(&p)).unwrap();
+
+                        ast::Param { name, ty }
+                    })
+                    .collect();
+
+                ast::Statement::ExternFn(ast::ExternFn {
+                    name,
+                    params,
+                    ret: names.next(),
+                    header: string_token(node),
+                    span: node.span(),
+                })
+            }
+            SyntaxKind::CallStmt => {
+                let call = node.children().next().unwrap();
+
+                ast::Statement::FnCall(self.call(&call))
+            }
+            SyntaxKind::Assignment => {
+                let [target, val] = <[_; 2]>::try_from(exprs(node)).unwrap();
+
+                ast::Statement::Assignment(ast::Assignment::new(self.name(&target), self.expr(&val)))
+            }
+            SyntaxKind::Conditional => ast::Statement::Conditional(ast::Conditional {
+                cond: self.expr(&exprs(node)[0]),
+                taken: self.block(node, 0),
+                not_taken: self.block(node, 1),
+            }),
+            SyntaxKind::WhileLoop => ast::Statement::WhileLoop(ast::WhileLoop {
+                cond: self.expr(&exprs(node)[0]),
+                invariant: self.invariant(node),
+                body: self.block(node, 0),
+            }),
+            SyntaxKind::DoWhileLoop => ast::Statement::DoWhileLoop(ast::DoWhileLoop {
+                cond: self.expr(&exprs(node)[0]),
+                invariant: self.invariant(node),
+                body: self.block(node, 0),
+            }),
+            SyntaxKind::TryCatch => ast::Statement::TryCatch(ast::TryCatch {
+                body: self.block(node, 0),
+                error_var: self.names(node).remove(0),
+                handler: self.block(node, 1),
+                span: node.span(),
+            }),
+            SyntaxKind::Test => {
+                let field = |kind| {
+                    node.children()
+                        .find(|c| c.kind() == kind)
+                        .map(|c| string_token(&c))
+                };
+
+                ast::Statement::Test(ast::TestBlock {
+                    name: string_token(node),
+                    input: field(SyntaxKind::TestInput),
+                    output: field(SyntaxKind::TestOutput),
+                    body: self.statements(node),
+                    span: node.span(),
+                })
+            }
+            SyntaxKind::Contract => {
+                let kind = match first_keyword(node) {
+                    Keyword::Requires => ast::ContractKind::Requires,
+                    _ => ast::ContractKind::Ensures,
+                };
+
+                ast::Statement::Contract(ast::Contract {
+                    kind,
+                    cond: self.expr(&exprs(node)[0]),
+                    span: node.span(),
+                })
+            }
+            _ => return None,
+        };
+
+        Some(stmt)
+    }
+
+    fn call(&self, node: &SyntaxNode) -> ast::FnCall {
+        let mut exprs = exprs(node).into_iter();
+        let fname = self.name(&exprs.next().unwrap());
+
+        // Na AST, as funções embutidas sempre têm o nome em português.
+        let fname = match self.dialect.canonical_function(&fname.name) {
+            Some(name) => ast::Ident::new(name, fname.span),
+            None => fname,
+        };
+
+        ast::FnCall::new(fname, exprs.map(|a| self.expr(&a)).collect())
+    }
+
+    fn expr(&self, node: &SyntaxNode) -> ast::Expr {
+        let boxed = |n: &SyntaxNode| Box::new(self.expr(n));
+
+        match node.kind() {
+            SyntaxKind::Name => ast::Expr::Ident(self.name(node)),
+            SyntaxKind::Literal => {
+                let t = first_token(node);
+                let text = t.text();
+
+                match t.kind() {
+                    TokenKind::Int => ast::Expr::ImmInt(ast::IntLiteral(text.parse().unwrap(), t.span())),
+                    TokenKind::Float => ast::Expr::ImmFloat(ast::FloatLiteral(
+                        text.replace(',', ".").parse().unwrap(),
+                        t.span(),
+                    )),
+                    TokenKind::Str => ast::Expr::ImmString(string_literal(&t)),
+                    _ => {
+                        let c = unescape(&text[1..text.len() - 1]).chars().next().unwrap();
+
+                        ast::Expr::ImmChar(ast::CharLiteral(c, t.span()))
+                    }
+                }
+            }
+            SyntaxKind::BinExpr => {
+                let [lhs, rhs] = <[_; 2]>::try_from(exprs(node)).unwrap();
+
+                let op = match first_token(node).kind() {
+                    TokenKind::OrOr => ast::BinaryOp::Or,
+                    TokenKind::AndAnd => ast::BinaryOp::And,
+                    TokenKind::Leq => ast::BinaryOp::Leq,
+                    TokenKind::Geq => ast::BinaryOp::Geq,
+                    TokenKind::Lt => ast::BinaryOp::Lt,
+                    TokenKind::Gt => ast::BinaryOp::Gt,
+                    TokenKind::EqEq => ast::BinaryOp::Eq,
+                    TokenKind::Neq => ast::BinaryOp::Neq,
+                    TokenKind::Pipe => ast::BinaryOp::BitOr,
+                    TokenKind::Xor => ast::BinaryOp::BitXor,
+                    TokenKind::Amp => ast::BinaryOp::BitAnd,
+                    TokenKind::Shl => ast::BinaryOp::Shl,
+                    TokenKind::Shr => ast::BinaryOp::Shr,
+                    TokenKind::Plus => ast::BinaryOp::Add,
+                    TokenKind::Minus => ast::BinaryOp::Sub,
+                    TokenKind::Star => ast::BinaryOp::Mul,
+                    TokenKind::Slash => ast::BinaryOp::Div,
+                    TokenKind::IntDiv => ast::BinaryOp::IntDiv,
+                    TokenKind::Percent => ast::BinaryOp::Mod,
+                    TokenKind::Caret => ast::BinaryOp::Pow,
+                    k => unreachable!("operador binário inválido na CST: {:?}", k),
+                };
+
+                ast::Expr::BinExpr(ast::BinExpr(op, boxed(&lhs), boxed(&rhs)))
+            }
+            SyntaxKind::Ternary => {
+                let [cond, taken, not_taken] = <[_; 3]>::try_from(exprs(node)).unwrap();
+
+                ast::Expr::Ternary(ast::Ternary::new(boxed(&cond), boxed(&taken), boxed(&not_taken)))
+            }
+            SyntaxKind::Index => {
+                let [target, index] = <[_; 2]>::try_from(exprs(node)).unwrap();
+                let r = node.child_tokens().last().unwrap();
+
+                ast::Expr::Index(ast::Index::new(boxed(&target), boxed(&index), r.span()))
+            }
+            SyntaxKind::Negation => {
+                let bang = first_token(node);
+
+                ast::Expr::Negation(ast::Negation::new(boxed(&exprs(node)[0]), bang.span()))
+            }
+            SyntaxKind::Paren => self.expr(&exprs(node)[0]),
+            SyntaxKind::Call => ast::Expr::FnCall(self.call(node)),
+            k => unreachable!("nó {:?} não é uma expressão", k),
+        }
+    }
+}
+
+/// A primeira palavra-chave de um nó, como o `requer` de um Contract.
+fn first_keyword(node: &SyntaxNode) -> Keyword {
+    match first_token(node).kind() {
+        TokenKind::Keyword(k) => k,
+        k => unreachable!("o nó começa com {:?}, não com uma palavra-chave", k),
+    }
+}
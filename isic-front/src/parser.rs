@@ -1,168 +1,401 @@
+use std::sync::Arc;
+
 use crate::ast;
+use crate::cst::{self, Draft, SyntaxKind};
+use crate::dialect::{Dialect, Keyword};
+use crate::lexer::{tokenize, Token, TokenKind};
+use crate::lower;
 use crate::span::Span;
 
+/// O que pode ser filho de um nó da CST: tokens, nós, e listas ou opcionais
+/// deles.
+trait Children {
+    fn push_into(self, out: &mut Vec<Draft>);
+}
+
+impl Children for Token {
+    fn push_into(self, out: &mut Vec<Draft>) {
+        out.push(Draft::Token(self));
+    }
+}
+
+impl Children for Draft {
+    fn push_into(self, out: &mut Vec<Draft>) {
+        out.push(self);
+    }
+}
+
+impl<T: Children> Children for Vec<T> {
+    fn push_into(self, out: &mut Vec<Draft>) {
+        for c in self {
+            c.push_into(out);
+        }
+    }
+}
+
+impl<T: Children> Children for Option<T> {
+    fn push_into(self, out: &mut Vec<Draft>) {
+        if let Some(c) = self {
+            c.push_into(out);
+        }
+    }
+}
+
+/// Monta um nó da CST: `node!(Kind, filho, filho, ...)`.
+macro_rules! node {
+    ($kind:ident, $($child:expr),* $(,)?) => {{
+        let mut children = vec![];
+        $( Children::push_into($child, &mut children); )*
+
+        Draft::Node(SyntaxKind::$kind, children)
+    }};
+}
+
 peg::parser! {
-    pub grammar isilang_parser() for str {
-        rule spanned<T: std::fmt::Debug + PartialEq + Eq>(r: rule<T>) -> ast::Spanned<T>
-            = start:position!() x:r() end:position!() {
-                ast::Spanned {
-                    span: Span { start, end },
-                    node: x,
-                }
+    /// O parser da IsiLang, que recebe os tokens gerados pelo lexer, sem os
+    /// espaços em branco e comentários, e monta os nós da CST. O código
+    /// fonte é usado para ler o texto dos tokens.
+    pub(crate) grammar isilang_parser(source: &str, dialect: &Dialect) for [Token] {
+        /// Um token do tipo dado.
+        rule tok(k: TokenKind) -> Token
+            = quiet!{ t:[x if x.kind == k] { t } } / expected(k.describe())
+
+        /// Uma palavra-chave do dialeto.
+        rule kw(k: Keyword) -> Token
+            = quiet!{ t:[x if x.kind == TokenKind::Keyword(k)] { t } } / expected(dialect.keyword(k))
+
+        /// Falha, reportando que `name` era esperado. O `[_]` nunca é
+        /// alcançado: ele só mostra ao peg que a regra consome algum token.
+        rule expected(name: &'static str) -> Token
+            = fail(name) t:[_] { t }
+
+        rule fail(name: &'static str) = {? Err(name) }
+
+        /// Uma lista de `item`s separados por vírgulas, com as vírgulas.
+        rule separated(item: rule<Draft>) -> Vec<Draft>
+            = first:item() rest:(c:tok(TokenKind::Comma) i:item() { vec![c.into(), i] })* {
+                let mut items = vec![first];
+                items.extend(rest.into_iter().flatten());
+
+                items
             }
 
-        pub rule num() -> ast::IntLiteral
-            = t0:position!() n:$(['0'..='9']+) t1:position!() {
+        rule num() -> Draft
+            = t:tok(TokenKind::Int) {
                 ? {
-                    let span = Span { start: t0, end: t1 };
-
-                    n
-                        .parse()
-                        .map(|n| ast::IntLiteral(n, span))
+                    t.text(source)
+                        .parse::<u64>()
+                        .map(|_| node!(Literal, t))
                         .or(Err("u64"))
                 }
             }
 
-        pub rule numf() -> ast::FloatLiteral
-            = t0:position!() n:$(['0'..='9']+ "," ['0'..='9']+) t1:position!() {
+        rule numf() -> Draft
+            = t:tok(TokenKind::Float) {
                 ? {
-                    let span = Span { start: t0, end: t1 };
-
-                    n
+                    t.text(source)
                         .replace(",", ".")
-                        .parse()
-                        .map(|n| ast::FloatLiteral(n, span))
-                        .or(Err("f32"))
+                        .parse::<f64>()
+                        .map(|_| node!(Literal, t))
+                        .or(Err("f64"))
                 }
             }
 
-        pub rule text() -> ast::StringLiteral
-            = t0:position!() "\"" t:$(['a'..='z' | 'A'..='Z' | '0'..='9' | ' ']+) "\"" t1:position!() {
-                let span = Span { start: t0, end: t1 };
+        rule text() -> Draft
+            = t:tok(TokenKind::Str) { node!(Literal, t) }
 
-                ast::StringLiteral(String::from(t), span)
-            }
+        rule character() -> Draft
+            = t:tok(TokenKind::Char) { node!(Literal, t) }
 
-        pub rule ident() -> ast::Ident
-            = t0:position!() id:$(['a'..='z' | 'A'..='Z']['a'..='z' | 'A'..='Z' | '0'..='9']*) t1:position!() {
-                let span = Span { start: t0, end: t1 };
+        /// Um nome. As palavras-chave que não são reservadas também podem
+        /// ser usadas como nomes.
+        rule ident() -> Draft
+            = t:(tok(TokenKind::Ident) / quiet!{ [x if matches!(x.kind, TokenKind::Keyword(k) if !k.is_reserved())] }) {
+                node!(Name, t)
+            }
 
-                ast::Ident::new(id, span)
+        rule decl() -> Draft
+            = vname:ident() c:tok(TokenKind::Colon) vtype:ident() {
+                node!(VarDecl, vname, c, vtype)
             }
 
-        rule decl() -> ast::VarDecl
-            = t0:position!() vname:ident() ws() ":" ws() vtype:ident() t1:position!() {
-                let span = Span { start: t0, end: t1 };
+        rule multidecl() -> Draft
+            = k:kw(Keyword::Declare) decls:separated(<decl()>) d:tok(TokenKind::Dot) {
+                node!(Decl, k, decls, d)
+            }
 
-                ast::VarDecl::new(vname, vtype, span)
+        rule param() -> Draft
+            = name:ident() c:tok(TokenKind::Colon) ty:ident() {
+                node!(Param, name, c, ty)
             }
 
-        pub rule multidecl() -> ast::MultiVarDecl
-            = "declare " ws() decls:(decl() ++ ("," ws())) ws() "." {
-                ast::MultiVarDecl(decls)
+        rule extern_fn() -> Draft
+            = k0:kw(Keyword::Extern) k1:kw(Keyword::Function) name:ident()
+              l:tok(TokenKind::LParen) params:separated(<param()>)? r:tok(TokenKind::RParen)
+              ret:(c:tok(TokenKind::Colon) ty:ident() { vec![c.into(), ty] })?
+              k2:kw(Keyword::From) header:tok(TokenKind::Str) d:tok(TokenKind::Dot) {
+                node!(ExternFn, k0, k1, name, l, params, r, ret, k2, header, d)
             }
 
-        pub rule negation() -> ast::Negation
-            = t0:position!() "!" ws() t1:position!() e:expr() {
-                let span = Span { start: t0, end: t1 };
-                ast::Negation::new(Box::new(e), span)
+        rule negation() -> Draft
+            = t:tok(TokenKind::Bang) e:expr() {
+                node!(Negation, t, e)
             }
 
-        pub rule expr() -> ast::Expr = precedence!{
-            lhs:(@) ws() "&&" ws() rhs:@ { ast::Expr::BinExpr(ast::BinExpr(ast::BinaryOp::And, Box::new(lhs), Box::new(rhs))) }
-            lhs:(@) ws() "||" ws() rhs:@ { ast::Expr::BinExpr(ast::BinExpr(ast::BinaryOp::Or, Box::new(lhs), Box::new(rhs))) }
+        /// Expressões da IsiLang. A tabela abaixo vai da menor para a maior
+        /// precedência:
+        ///
+        /// | Operadores                       | Associatividade |
+        /// |----------------------------------|-----------------|
+        /// | `c ? a : b`                      | direita         |
+        /// | `\|\|`                           | esquerda        |
+        /// | `&&`                             | esquerda        |
+        /// | `<` `>` `<=` `>=` `==` `!=`      | esquerda        |
+        /// | `\|`                             | esquerda        |
+        /// | `xor`                            | esquerda        |
+        /// | `&`                              | esquerda        |
+        /// | `<<` `>>`                        | esquerda        |
+        /// | `+` `-`                          | esquerda        |
+        /// | `*` `/` `div` `%`                | esquerda        |
+        /// | `^`                              | direita         |
+        /// | `s[i]`                           | posfixo         |
+        /// | `!`                              | prefixo         |
+        rule expr() -> Draft = precedence!{
+            cond:@ q:tok(TokenKind::Question) taken:expr() c:tok(TokenKind::Colon) not_taken:(@) {
+                node!(Ternary, cond, q, taken, c, not_taken)
+            }
+            --
+            lhs:(@) t:tok(TokenKind::OrOr) rhs:@ { node!(BinExpr, lhs, t, rhs) }
+            --
+            lhs:(@) t:tok(TokenKind::AndAnd) rhs:@ { node!(BinExpr, lhs, t, rhs) }
             --
-            lhs:(@) ws() "<" ws() rhs:@ { ast::Expr::BinExpr(ast::BinExpr(ast::BinaryOp::Lt, Box::new(lhs), Box::new(rhs))) }
-            lhs:(@) ws() ">" ws() rhs:@ { ast::Expr::BinExpr(ast::BinExpr(ast::BinaryOp::Gt, Box::new(lhs), Box::new(rhs))) }
-            lhs:(@) ws() "<=" ws() rhs:@ { ast::Expr::BinExpr(ast::BinExpr(ast::BinaryOp::Leq, Box::new(lhs), Box::new(rhs))) }
-            lhs:(@) ws() ">=" ws() rhs:@ { ast::Expr::BinExpr(ast::BinExpr(ast::BinaryOp::Geq, Box::new(lhs), Box::new(rhs))) }
-            lhs:(@) ws() "==" ws() rhs:@ { ast::Expr::BinExpr(ast::BinExpr(ast::BinaryOp::Eq, Box::new(lhs), Box::new(rhs))) }
-            lhs:(@) ws() "!=" ws() rhs:@ { ast::Expr::BinExpr(ast::BinExpr(ast::BinaryOp::Neq, Box::new(lhs), Box::new(rhs))) }
+            lhs:(@) t:tok(TokenKind::Leq) rhs:@ { node!(BinExpr, lhs, t, rhs) }
+            lhs:(@) t:tok(TokenKind::Geq) rhs:@ { node!(BinExpr, lhs, t, rhs) }
+            lhs:(@) t:tok(TokenKind::Lt) rhs:@ { node!(BinExpr, lhs, t, rhs) }
+            lhs:(@) t:tok(TokenKind::Gt) rhs:@ { node!(BinExpr, lhs, t, rhs) }
+            lhs:(@) t:tok(TokenKind::EqEq) rhs:@ { node!(BinExpr, lhs, t, rhs) }
+            lhs:(@) t:tok(TokenKind::Neq) rhs:@ { node!(BinExpr, lhs, t, rhs) }
             --
-            lhs:(@) ws() "+" ws() rhs:@ { ast::Expr::BinExpr(ast::BinExpr(ast::BinaryOp::Add, Box::new(lhs), Box::new(rhs))) }
-            lhs:(@) ws() "-" ws() rhs:@ { ast::Expr::BinExpr(ast::BinExpr(ast::BinaryOp::Sub, Box::new(lhs), Box::new(rhs))) }
+            lhs:(@) t:tok(TokenKind::Pipe) rhs:@ { node!(BinExpr, lhs, t, rhs) }
             --
-            lhs:(@) ws() "*" ws() rhs:@ { ast::Expr::BinExpr(ast::BinExpr(ast::BinaryOp::Mul, Box::new(lhs), Box::new(rhs))) }
-            lhs:(@) ws() "/" ws() rhs:@ { ast::Expr::BinExpr(ast::BinExpr(ast::BinaryOp::Div, Box::new(lhs), Box::new(rhs))) }
+            lhs:(@) t:tok(TokenKind::Xor) rhs:@ { node!(BinExpr, lhs, t, rhs) }
             --
-            lhs:(@) ws() "%" ws() rhs:@ { ast::Expr::BinExpr(ast::BinExpr(ast::BinaryOp::Mod, Box::new(lhs), Box::new(rhs))) }
+            lhs:(@) t:tok(TokenKind::Amp) rhs:@ { node!(BinExpr, lhs, t, rhs) }
             --
-            f:numf() { ast::Expr::ImmFloat(f) }
-            n:num() { ast::Expr::ImmInt(n) }
-            t:text() { ast::Expr::ImmString(t) }
-            id:ident() { ast::Expr::Ident(id) }
+            lhs:(@) t:tok(TokenKind::Shl) rhs:@ { node!(BinExpr, lhs, t, rhs) }
+            lhs:(@) t:tok(TokenKind::Shr) rhs:@ { node!(BinExpr, lhs, t, rhs) }
             --
-            neg:negation() { ast::Expr::Negation(neg) }
+            lhs:(@) t:tok(TokenKind::Plus) rhs:@ { node!(BinExpr, lhs, t, rhs) }
+            lhs:(@) t:tok(TokenKind::Minus) rhs:@ { node!(BinExpr, lhs, t, rhs) }
+            --
+            lhs:(@) t:tok(TokenKind::Star) rhs:@ { node!(BinExpr, lhs, t, rhs) }
+            lhs:(@) t:tok(TokenKind::Slash) rhs:@ { node!(BinExpr, lhs, t, rhs) }
+            lhs:(@) t:tok(TokenKind::IntDiv) rhs:@ { node!(BinExpr, lhs, t, rhs) }
+            lhs:(@) t:tok(TokenKind::Percent) rhs:@ { node!(BinExpr, lhs, t, rhs) }
+            --
+            lhs:@ t:tok(TokenKind::Caret) rhs:(@) { node!(BinExpr, lhs, t, rhs) }
+            --
+            target:(@) l:tok(TokenKind::LBracket) index:expr() r:tok(TokenKind::RBracket) {
+                node!(Index, target, l, index, r)
+            }
             --
-            "(" ws() e:expr() ws() ")" { e }
+            f:numf() { f }
+            n:num() { n }
+            t:text() { t }
+            c:character() { c }
+            c:call() { c }
+            id:ident() { id }
+            --
+            neg:negation() { neg }
+            --
+            l:tok(TokenKind::LParen) e:expr() r:tok(TokenKind::RParen) { node!(Paren, l, e, r) }
         }
 
-        pub rule fncall() -> ast::FnCall
-            = fname:ident() ws() "(" ws() args:(expr() ** (", " ws())) ws() ")." {
-                ast::FnCall::new(fname, args)
+        rule call() -> Draft
+            = fname:ident() l:tok(TokenKind::LParen) args:separated(<expr()>)? r:tok(TokenKind::RParen) {
+                node!(Call, fname, l, args, r)
             }
 
-        pub rule assignment() -> ast::Assignment
-            = id:ident() ws() ":=" ws() val:expr() ws() "." {
-                ast::Assignment::new(id, val)
+        rule fncall() -> Draft
+            = c:call() d:tok(TokenKind::Dot) {
+                node!(CallStmt, c, d)
             }
 
-        rule cond_taken_block() -> Vec<ast::Statement>
-            = "entao" ws() "{" ws() stmts:(statement() ** ws()) ws() "}" {
-                stmts
+        rule assignment() -> Draft
+            = id:ident() a:tok(TokenKind::Assign) val:expr() d:tok(TokenKind::Dot) {
+                node!(Assignment, id, a, val, d)
             }
 
-        rule cond_not_taken_block() -> Vec<ast::Statement>
-            = "senao" ws() "{" ws() stmts:(statement() ** ws()) ws() "}" {
-                stmts
+        /// Um bloco de comandos entre chaves, que pode ser parseado sozinho
+        /// por `crate::incremental`.
+        pub rule block() -> Draft
+            = l:tok(TokenKind::LBrace) stmts:statement()* r:tok(TokenKind::RBrace) {
+                node!(Block, l, stmts, r)
             }
 
-        pub rule conditional() -> ast::Conditional
-            = "se" ws() "(" ws() cond:expr() ws() ")" ws()
-              taken:cond_taken_block() ws()
-              not_taken:(cond_not_taken_block()?) {
-                  let not_taken = match not_taken {
-                      Some(stmts) => stmts,
-                      None        => vec![],
-                  };
+        rule conditional() -> Draft
+            = k:kw(Keyword::If) l:tok(TokenKind::LParen) cond:expr() r:tok(TokenKind::RParen)
+              t:kw(Keyword::Then) taken:block()
+              not_taken:(e:kw(Keyword::Else) b:block() { vec![e.into(), b] })? {
+                node!(Conditional, k, l, cond, r, t, taken, not_taken)
+            }
 
-                  ast::Conditional {
-                      cond,
-                      taken,
-                      not_taken,
-                  }
-              }
+        rule invariant() -> Draft
+            = k:kw(Keyword::Invariant) l:tok(TokenKind::LParen) inv:expr() r:tok(TokenKind::RParen) {
+                node!(Invariant, k, l, inv, r)
+            }
 
-        pub rule while_loop() -> ast::WhileLoop
-            = "enquanto" ws() "(" ws() cond:expr() ")" ws() "{" ws() stmts:(statement() ** ws()) ws() "}" {
-                ast::WhileLoop {
-                    cond,
-                    body: stmts,
-                }
+        rule while_loop() -> Draft
+            = k:kw(Keyword::While) l:tok(TokenKind::LParen) cond:expr() r:tok(TokenKind::RParen)
+              invariant:invariant()? body:block() {
+                node!(WhileLoop, k, l, cond, r, invariant, body)
             }
 
-        pub rule do_while_loop() -> ast::DoWhileLoop
-            = "faca" ws() "{" ws() stmts:(statement() ** ws()) ws() "}" ws()
-              "enquanto" ws() "(" ws() cond:expr() ws() ")." ws() {
-                ast::DoWhileLoop {
-                    cond,
-                    body: stmts,
-                }
+        rule do_while_loop() -> Draft
+            = k0:kw(Keyword::Do) invariant:invariant()? body:block()
+              k1:kw(Keyword::While) l:tok(TokenKind::LParen) cond:expr() r:tok(TokenKind::RParen) d:tok(TokenKind::Dot) {
+                node!(DoWhileLoop, k0, invariant, body, k1, l, cond, r, d)
+            }
+
+        rule try_catch() -> Draft
+            = k0:kw(Keyword::Try) body:block()
+              k1:kw(Keyword::Catch) l:tok(TokenKind::LParen) error_var:ident() r:tok(TokenKind::RParen)
+              handler:block() {
+                node!(TryCatch, k0, body, k1, l, error_var, r, handler)
             }
 
-        pub rule statement() -> ast::Statement
-            = d:multidecl()     { ast::Statement::Decl(d) }
-            / fc:fncall()       { ast::Statement::FnCall(fc) }
-            / a:assignment()    { ast::Statement::Assignment(a) }
-            / c:conditional()   { ast::Statement::Conditional(c) }
-            / l:while_loop()    { ast::Statement::WhileLoop(l) }
-            / l:do_while_loop() { ast::Statement::DoWhileLoop(l) }
+        rule statement() -> Draft
+            = multidecl()
+            / extern_fn()
+            / fncall()
+            / assignment()
+            / conditional()
+            / while_loop()
+            / do_while_loop()
+            / try_catch()
+
+        rule test_block() -> Draft
+            = k:kw(Keyword::Test) name:tok(TokenKind::Str) l:tok(TokenKind::LBrace)
+              input:(k:kw(Keyword::Input) t:tok(TokenKind::Str) d:tok(TokenKind::Dot) { node!(TestInput, k, t, d) })?
+              output:(k:kw(Keyword::Output) t:tok(TokenKind::Str) d:tok(TokenKind::Dot) { node!(TestOutput, k, t, d) })?
+              body:statement()* r:tok(TokenKind::RBrace) {
+                node!(Test, k, name, l, input, output, body, r)
+            }
 
-        pub rule program() -> ast::IsiProgram
-            = ws() "programa" ws() stmts:(statement() ** ws()) ws() "fimprog." ws() {
-                ast::IsiProgram::new(stmts)
+        rule contract() -> Draft
+            = k:(kw(Keyword::Requires) / kw(Keyword::Ensures))
+              l:tok(TokenKind::LParen) cond:expr() r:tok(TokenKind::RParen) d:tok(TokenKind::Dot) {
+                node!(Contract, k, l, cond, r, d)
+            }
+
+        // Os testes e as pré e pós-condições só podem aparecer fora de
+        // blocos.
+        rule top_statement() -> Draft
+            = test_block()
+            / contract()
+            / statement()
+
+        /// O pragma `#dialeto` já foi lido por quem chamou o parser, então
+        /// aqui só é guardado na árvore.
+        pub rule program() -> Draft
+            = p:tok(TokenKind::Pragma)? k:kw(Keyword::Program) stmts:top_statement()*
+              e:kw(Keyword::EndProgram) d:tok(TokenKind::Dot) {
+                node!(Program, p, k, stmts, e, d)
             };
+    }
+}
+
+/// Um erro de sintaxe, com a posição no código fonte e a mensagem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub span: Span,
+    pub message: String,
+}
+
+/// Faz o parse de um programa no dialeto dado, gerando a CST. A árvore é
+/// gerada mesmo se houver um erro de sintaxe.
+pub fn parse_cst(source: &str, dialect: &Dialect) -> cst::Parse {
+    let all = tokenize(source, dialect);
+    let tokens: Vec<Token> = all.iter().copied().filter(|t| !t.kind.is_trivia()).collect();
+
+    let (green, error) = match isilang_parser::program(&tokens, source, dialect) {
+        Ok(draft) => (cst::build(source, &all, draft), None),
+        Err(e) => (
+            cst::error_tree(source, &all),
+            Some(syntax_error(source, &tokens, e)),
+        ),
+    };
 
-        rule ws() = quiet!{ ([' ' | '\n' | '\t'])* }
+    cst::Parse {
+        green: Arc::new(green),
+        error,
+    }
+}
+
+/// Faz o parse de um programa no dialeto dado.
+pub fn parse(source: &str, dialect: &Dialect) -> Result<ast::IsiProgram, SyntaxError> {
+    let parse = parse_cst(source, dialect);
+
+    match parse.error {
+        Some(e) => Err(e),
+        None => Ok(lower::program(&parse.syntax(), dialect)),
+    }
+}
+
+/// Monta a mensagem de um erro de sintaxe. Alguns erros comuns ganham uma
+/// mensagem própria, já que a lista do que era esperado não ajuda muito.
+fn syntax_error(source: &str, tokens: &[Token], e: peg::error::ParseError<usize>) -> SyntaxError {
+    // Erros no fim do arquivo são marcados no último token.
+    let eof = tokens.last().map_or(Span { start: 0, end: source.len() }, |t| t.span);
+
+    let found = tokens.get(e.location);
+    let prev = e.location.checked_sub(1).and_then(|i| tokens.get(i));
+
+    let reserved = |t: Option<&Token>| {
+        t.filter(|t| matches!(t.kind, TokenKind::Keyword(k) if k.is_reserved()))
+            .copied()
+    };
+
+    // Uma palavra reservada onde era esperado um nome, como em
+    // `declare se: int.`, ou no início de uma atribuição, como em `se := 1.`
+    // (que falha no `:=`, já que `se` começa um condicional).
+    let misused = match found.map(|t| t.kind) {
+        Some(TokenKind::Assign | TokenKind::Colon) => reserved(prev),
+        _ if e.expected.tokens().any(|t| t == TokenKind::Ident.describe()) => reserved(found),
+        _ => None,
+    };
+
+    if let Some(t) = misused {
+        return SyntaxError {
+            span: t.span,
+            message: format!("{} is a reserved keyword and cannot be used as a name", t.text(source)),
+        };
+    }
+
+    match found {
+        Some(t) if t.kind == TokenKind::Error => {
+            let text = t.text(source);
+
+            let message = if text.starts_with('"') {
+                "Unterminated string".to_string()
+            } else if text.starts_with('\'') {
+                "Invalid character literal".to_string()
+            } else {
+                format!("Unexpected character {}", text)
+            };
+
+            SyntaxError {
+                span: t.span,
+                message,
+            }
+        }
+        Some(t) => SyntaxError {
+            span: t.span,
+            message: format!("Expected {}", e.expected),
+        },
+        None => SyntaxError {
+            span: eof,
+            message: format!("Expected {} after this", e.expected),
+        },
     }
 }
@@ -0,0 +1,243 @@
+//! # isic_front::source
+//!
+//! Carrega o código fonte a partir dos bytes de um arquivo.
+//!
+//! Editores no Windows costumam salvar os programas em Latin-1, ou em UTF-8
+//! com BOM, e com quebras de linha `\r\n`. Aqui o código é convertido para
+//! UTF-8, sem o BOM e com as quebras de linha normalizadas para `\n`, que é o
+//! texto que o parser recebe. As posições nesse texto podem ser convertidas
+//! de volta para posições nos bytes originais, para ferramentas que mostram
+//! os erros sobre o arquivo como ele está no disco.
+
+use crate::span::Span;
+
+/// Como o arquivo estava codificado.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    /// UTF-8, começando com o BOM (`EF BB BF`).
+    Utf8Bom,
+    /// Qualquer arquivo que não é UTF-8 válido é lido como Latin-1
+    /// (ISO-8859-1), em que cada byte é um caractere.
+    Latin1,
+}
+
+/// Um código fonte carregado.
+#[derive(Debug, Clone)]
+pub struct SourceText {
+    /// O código em UTF-8, com as quebras de linha normalizadas.
+    pub text: String,
+    pub encoding: Encoding,
+    /// Pares (posição no texto, posição nos bytes originais), ordenados,
+    /// nos pontos em que a diferença entre as duas posições muda.
+    map: Vec<(usize, usize)>,
+}
+
+const BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+
+impl SourceText {
+    /// Carrega os bytes de um arquivo. Nunca falha, já que qualquer
+    /// sequência de bytes é Latin-1 válido.
+    pub fn load(bytes: &[u8]) -> SourceText {
+        let (encoding, body, skipped) = match bytes.strip_prefix(BOM) {
+            Some(body) => (Encoding::Utf8Bom, body, BOM.len()),
+            None if std::str::from_utf8(bytes).is_ok() => (Encoding::Utf8, bytes, 0),
+            None => (Encoding::Latin1, bytes, 0),
+        };
+
+        // Um arquivo com BOM mas com bytes inválidos depois dele é lido com
+        // substituições, já que não pode ser Latin-1.
+        let decoded = match encoding {
+            Encoding::Latin1 => body.iter().map(|&b| b as char).collect(),
+            _ => String::from_utf8_lossy(body).into_owned(),
+        };
+
+        let mut source = SourceText {
+            text: String::with_capacity(decoded.len()),
+            encoding,
+            map: vec![(0, skipped)],
+        };
+
+        // A posição nos bytes originais do próximo caractere.
+        let mut original = skipped;
+        let mut chars = decoded.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            let len = match encoding {
+                Encoding::Latin1 => 1,
+                _ => c.len_utf8(),
+            };
+
+            if c == '\r' && chars.peek() == Some(&'\n') {
+                // O `\r` de um `\r\n` é descartado.
+                original += 1;
+                source.map.push((source.text.len(), original));
+                continue;
+            }
+
+            // Um `\r` sozinho, dos antigos arquivos do Mac, também é uma
+            // quebra de linha.
+            source.text.push(if c == '\r' { '\n' } else { c });
+            original += len;
+
+            if len != c.len_utf8() {
+                source.map.push((source.text.len(), original));
+            }
+        }
+
+        source
+    }
+
+    /// O aviso a ser mostrado ao usuário sobre a codificação do arquivo, se
+    /// houver um.
+    pub fn warning(&self) -> Option<&'static str> {
+        match self.encoding {
+            Encoding::Latin1 => Some(
+                "The file is not valid UTF-8 and was read as Latin-1; save it as UTF-8 to avoid this warning",
+            ),
+            _ => None,
+        }
+    }
+
+    /// A posição nos bytes originais que corresponde à posição `offset` do
+    /// texto.
+    pub fn original_offset(&self, offset: usize) -> usize {
+        let i = self.map.partition_point(|&(text, _)| text <= offset) - 1;
+        let (text, original) = self.map[i];
+
+        original + (offset - text)
+    }
+
+    /// O trecho dos bytes originais que corresponde ao span `span` do texto.
+    pub fn original_span(&self, span: Span) -> Span {
+        let start = self.original_offset(span.start);
+
+        // O fim vem do último caractere do trecho, e não de
+        // original_offset(span.end), que pularia um `\r` descartado logo
+        // depois do trecho.
+        let end = match self.text[span.start..span.end].chars().next_back() {
+            Some(c) => {
+                let len = match self.encoding {
+                    Encoding::Latin1 => 1,
+                    _ => c.len_utf8(),
+                };
+
+                self.original_offset(span.end - c.len_utf8()) + len
+            }
+            None => start,
+        };
+
+        Span { start, end }
+    }
+
+    /// A posição em caracteres que corresponde à posição em bytes `offset`
+    /// do texto. Os Spans guardam posições em bytes, mas o ariadne, usado
+    /// para mostrar os erros, conta caracteres.
+    pub fn char_offset(&self, offset: usize) -> usize {
+        let offset = offset.min(self.text.len());
+
+        self.text[..offset].chars().count()
+    }
+
+    /// O span `span` em caracteres, para o ariadne.
+    pub fn char_range(&self, span: Span) -> std::ops::Range<usize> {
+        self.char_offset(span.start)..self.char_offset(span.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A linha e a coluna, contadas a partir de 1, da posição em caracteres
+    /// `offset` de `text`, como o ariadne mostra.
+    fn line_col(text: &str, offset: usize) -> (usize, usize) {
+        let before: Vec<char> = text.chars().take(offset).collect();
+        let line = before.iter().filter(|&&c| c == '\n').count() + 1;
+        let col = before.iter().rev().take_while(|&&c| c != '\n').count() + 1;
+
+        (line, col)
+    }
+
+    /// A posição do erro em `source`, que aponta para `needle`.
+    fn position(source: &SourceText, needle: &str) -> (usize, usize) {
+        let start = source.text.find(needle).unwrap();
+
+        line_col(&source.text, source.char_offset(start))
+    }
+
+    /// O trecho dos bytes originais que corresponde a `needle` no texto.
+    fn original<'b>(source: &SourceText, bytes: &'b [u8], needle: &str) -> &'b [u8] {
+        let start = source.text.find(needle).unwrap();
+        let span = source.original_span(Span {
+            start,
+            end: start + needle.len(),
+        });
+
+        &bytes[span.start..span.end]
+    }
+
+    #[test]
+    fn bom_is_skipped() {
+        let bytes = b"\xEF\xBB\xBFprograma x fimprog.";
+        let source = SourceText::load(bytes);
+
+        assert_eq!(source.encoding, Encoding::Utf8Bom);
+        assert_eq!(source.text, "programa x fimprog.");
+        assert_eq!(position(&source, "x"), (1, 10));
+        assert!(source.warning().is_none());
+
+        // O BOM ocupa 3 bytes no início do arquivo.
+        assert_eq!(source.original_offset(0), 3);
+        assert_eq!(source.original_offset(9), 12);
+        assert_eq!(source.original_offset(source.text.len()), bytes.len());
+        assert_eq!(original(&source, bytes, "fimprog."), b"fimprog.");
+    }
+
+    #[test]
+    fn crlf_keeps_lines_and_columns() {
+        let bytes = b"programa\r\n  m\xC3\xA9dia\r\n\r  x\r\nfimprog.";
+        let source = SourceText::load(bytes);
+
+        assert_eq!(source.encoding, Encoding::Utf8);
+        assert_eq!(source.text, "programa\n  média\n\n  x\nfimprog.");
+        assert_eq!(position(&source, "dia"), (2, 5));
+        assert_eq!(position(&source, "x"), (4, 3));
+        assert_eq!(position(&source, "fimprog"), (5, 1));
+
+        // Cada `\r` descartado de um `\r\n` soma um byte às posições a partir
+        // do `\n`; o `\r` sozinho vira `\n` e não muda nada.
+        assert_eq!(source.original_offset(7), 7);
+        assert_eq!(source.original_offset(8), 9);
+        assert_eq!(source.original_offset(9), 10);
+        assert_eq!(source.original_offset(14), 15);
+        assert_eq!(source.original_offset(21), 23);
+        assert_eq!(source.original_offset(23), 26);
+        assert_eq!(source.original_offset(source.text.len()), bytes.len());
+        assert_eq!(original(&source, bytes, "média"), "média".as_bytes());
+        assert_eq!(original(&source, bytes, "x\nfim"), b"x\r\nfim");
+    }
+
+    #[test]
+    fn latin1_counts_one_character_per_byte() {
+        // `média` em Latin-1: o é é um byte só, que não é UTF-8 válido.
+        let source = SourceText::load(b"programa\n  m\xE9dia := 1.\nfimprog.");
+
+        assert_eq!(source.encoding, Encoding::Latin1);
+        assert_eq!(source.text, "programa\n  média := 1.\nfimprog.");
+        assert!(source.warning().is_some());
+
+        // Os Spans contam bytes do UTF-8, em que o é tem dois, mas o erro é
+        // mostrado na mesma coluna do arquivo original.
+        let start = source.text.find(":=").unwrap();
+        assert_eq!(start, 18);
+        assert_eq!(source.char_offset(start), 17);
+        assert_eq!(position(&source, ":="), (2, 9));
+
+        // Nos bytes originais, o é também ocupa um só.
+        assert_eq!(source.original_offset(13), 13);
+        assert_eq!(source.original_offset(start), 17);
+        assert_eq!(source.original_offset(source.text.len()), 31);
+        assert_eq!(original(&source, b"programa\n  m\xE9dia := 1.\nfimprog.", "média"), b"m\xE9dia");
+    }
+}
@@ -13,3 +13,29 @@ impl Span {
         }
     }
 }
+
+/// Índice das linhas de um código fonte, para converter as posições
+/// guardadas nos Spans em números de linha.
+#[derive(Debug, Clone, Default)]
+pub struct LineIndex {
+    /// A posição do início de cada linha.
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(src: &str) -> LineIndex {
+        let starts = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        LineIndex { starts }
+    }
+
+    /// O número da linha (começando em 1) que contém a posição `offset`.
+    pub fn line(&self, offset: usize) -> usize {
+        match self.starts.binary_search(&offset) {
+            Ok(line) => line + 1,
+            Err(line) => line,
+        }
+    }
+}
@@ -34,12 +34,16 @@ pub trait IsiVisitor {
 
     fn visit_string_literal(&mut self, lit: &StringLiteral) -> Self::Ret;
 
+    fn visit_char_literal(&mut self, lit: &CharLiteral) -> Self::Ret;
+
     fn visit_ident(&mut self, id: &Ident) -> Self::Ret;
 
     fn visit_decl(&mut self, decl: &VarDecl) -> Self::Ret;
 
     fn visit_multi_decl(&mut self, decls: &MultiVarDecl) -> Self::Ret;
 
+    fn visit_extern_fn(&mut self, ext: &ExternFn) -> Self::Ret;
+
     fn visit_bin_expr(&mut self, bexpr: &BinExpr) -> Self::Ret;
 
     fn visit_expr(&mut self, expr: &Expr) -> Self::Ret {
@@ -48,9 +52,12 @@ pub trait IsiVisitor {
             Expr::ImmInt(imm) => self.visit_int_literal(imm),
             Expr::ImmFloat(imm) => self.visit_float_literal(imm),
             Expr::ImmString(imm) => self.visit_string_literal(imm),
+            Expr::ImmChar(imm) => self.visit_char_literal(imm),
             Expr::BinExpr(bexp) => self.visit_bin_expr(bexp),
             Expr::FnCall(call) => self.visit_fn_call(call),
             Expr::Negation(neg) => self.visit_negation(neg),
+            Expr::Ternary(tern) => self.visit_ternary(tern),
+            Expr::Index(idx) => self.visit_index(idx),
         }
     }
 
@@ -58,6 +65,10 @@ pub trait IsiVisitor {
 
     fn visit_negation(&mut self, neg: &Negation) -> Self::Ret;
 
+    fn visit_ternary(&mut self, tern: &Ternary) -> Self::Ret;
+
+    fn visit_index(&mut self, idx: &Index) -> Self::Ret;
+
     fn visit_assignment(&mut self, assignment: &Assignment) -> Self::Ret;
 
     fn visit_conditional(&mut self, conditional: &Conditional) -> Self::Ret;
@@ -66,14 +77,24 @@ pub trait IsiVisitor {
 
     fn visit_do_while_loop(&mut self, do_while_loop: &DoWhileLoop) -> Self::Ret;
 
+    fn visit_try_catch(&mut self, try_catch: &TryCatch) -> Self::Ret;
+
+    fn visit_test(&mut self, test: &TestBlock) -> Self::Ret;
+
+    fn visit_contract(&mut self, contract: &Contract) -> Self::Ret;
+
     fn visit_statement(&mut self, stmt: &Statement) -> Self::Ret {
         match stmt {
             Statement::Assignment(ass) => self.visit_assignment(ass),
             Statement::Decl(mdecl) => self.visit_multi_decl(mdecl),
+            Statement::ExternFn(ext) => self.visit_extern_fn(ext),
             Statement::FnCall(call) => self.visit_fn_call(call),
             Statement::Conditional(cond) => self.visit_conditional(cond),
             Statement::WhileLoop(l) => self.visit_while_loop(l),
             Statement::DoWhileLoop(l) => self.visit_do_while_loop(l),
+            Statement::TryCatch(t) => self.visit_try_catch(t),
+            Statement::Test(t) => self.visit_test(t),
+            Statement::Contract(c) => self.visit_contract(c),
         }
     }
 
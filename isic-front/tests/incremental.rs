@@ -0,0 +1,209 @@
+//! Testes do parse incremental: depois de edições aleatórias, o resultado
+//! de `incremental::reparse` deve ser igual ao de um parse do zero.
+
+use std::sync::Arc;
+
+use isic_front::cst::{GreenElement, Parse};
+use isic_front::dialect::{self, Dialect};
+use isic_front::incremental::{reparse, TextEdit};
+use isic_front::parser::parse_cst;
+use isic_front::span::Span;
+
+const PROGRAM: &str = r#"programa
+    declare x: int, s: string.
+    x := 0.
+    s := "a { b } c".
+
+    // Um comentário { com chaves }
+    enquanto (x < 10) invariante (x >= 0) {
+        se (x % 2 == 0) entao {
+            escreva(x).
+        } senao {
+            escrevasl("impar").
+            tente {
+                x := x + 1.
+            } capture (e) {
+                escreva(e).
+            }
+        }
+        x := x + 1.
+    }
+
+    faca {
+        x := x - 1.
+    } enquanto (x > 0).
+
+    teste "vazio" {
+        saida "".
+    }
+fimprog.
+"#;
+
+/// Comandos inteiros, que mantêm o programa válido se inseridos entre dois
+/// comandos.
+const STATEMENTS: &[&str] = &[
+    " x := x * 2.",
+    " escreva(x).",
+    " se (x > 1) entao { x := 1. }",
+    " enquanto (x > 100) { x := x - 1. }",
+    " tente { x := 1. } capture (erro) { }",
+    "\n// comentário\n",
+];
+
+/// Pedaços que costumam quebrar o programa.
+const FRAGMENTS: &[&str] = &[
+    "\n", " ", "x", "1", "{", "}", ".", "\"", "'", "(", ":=", "@", "se", "média", "//",
+];
+
+/// O SplitMix64, o mesmo gerador do interpretador.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// Uma posição aleatória de `source` que cai entre dois caracteres.
+fn boundary(rng: &mut Rng, source: &str) -> usize {
+    let mut pos = rng.below(source.len() + 1);
+
+    while !source.is_char_boundary(pos) {
+        pos -= 1;
+    }
+
+    pos
+}
+
+/// Uma edição aleatória. Na metade das vezes, um comando é inserido logo
+/// depois de um `.` ou `{`, o que costuma manter o programa válido e
+/// exercita o parse de um bloco só. Nas outras, um trecho qualquer é
+/// apagado ou trocado.
+fn random_edit(rng: &mut Rng, source: &str) -> TextEdit {
+    let anchors: Vec<usize> = source.match_indices(['.', '{']).map(|(i, _)| i + 1).collect();
+
+    if rng.below(2) == 0 {
+        let pos = anchors[rng.below(anchors.len())];
+
+        return TextEdit {
+            span: Span { start: pos, end: pos },
+            text: STATEMENTS[rng.below(STATEMENTS.len())].to_string(),
+        };
+    }
+
+    let start = boundary(rng, source);
+    let mut end = (start + rng.below(6)).min(source.len());
+
+    while !source.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let text = match rng.below(3) {
+        0 => String::new(),
+        _ => FRAGMENTS[rng.below(FRAGMENTS.len())].to_string(),
+    };
+
+    TextEdit {
+        span: Span { start, end },
+        text,
+    }
+}
+
+fn check(dialect: &'static Dialect, program: &str, seed: u64) {
+    let mut rng = Rng(seed);
+    let mut source = program.to_string();
+    let mut parse = parse_cst(&source, dialect);
+
+    assert!(parse.error.is_none(), "{:?}", parse.error);
+
+    for step in 0..500 {
+        // De tempos em tempos, volta ao programa original, que é válido.
+        if step % 10 == 0 {
+            source = program.to_string();
+            parse = parse_cst(&source, dialect);
+        }
+
+        let edit = random_edit(&mut rng, &source);
+        edit.apply(&mut source);
+
+        let incremental = reparse(&parse, &edit, dialect);
+        let full = parse_cst(&source, dialect);
+
+        assert_eq!(incremental.syntax().to_string(), source);
+        assert_eq!(
+            incremental.green, full.green,
+            "seed {} step {}: {:?} in\n{}",
+            seed, step, edit, source
+        );
+        assert_eq!(incremental.error, full.error, "seed {} step {}: {:?}", seed, step, edit);
+
+        parse = incremental;
+    }
+}
+
+#[test]
+fn random_edits_match_full_reparse() {
+    for seed in 0..8 {
+        check(&dialect::PT, PROGRAM, seed);
+    }
+}
+
+#[test]
+fn random_edits_match_full_reparse_en() {
+    let ast = isic_front::parser::parse(PROGRAM, &dialect::PT).unwrap();
+    let translated = dialect::translate(PROGRAM, &ast, &dialect::PT, &dialect::EN).unwrap();
+
+    for seed in 0..4 {
+        check(&dialect::EN, &translated, seed);
+    }
+}
+
+/// Os filhos da raiz, para comparar com os da árvore antiga.
+fn top_level(parse: &Parse) -> Vec<GreenElement> {
+    parse.green.children().to_vec()
+}
+
+#[test]
+fn unchanged_subtrees_are_shared() {
+    let old = parse_cst(PROGRAM, &dialect::PT);
+
+    // Uma edição dentro do bloco do `faca`.
+    let pos = PROGRAM.find("x := x - 1.").unwrap();
+    let edit = TextEdit {
+        span: Span {
+            start: pos,
+            end: pos + 1,
+        },
+        text: "x := 2. x".to_string(),
+    };
+
+    let new = reparse(&old, &edit, &dialect::PT);
+    assert!(new.error.is_none());
+
+    let old_nodes = top_level(&old);
+    let new_nodes = top_level(&new);
+    assert_eq!(old_nodes.len(), new_nodes.len());
+
+    let shared = old_nodes
+        .iter()
+        .zip(&new_nodes)
+        .filter(|(a, b)| match (a, b) {
+            (GreenElement::Node(a), GreenElement::Node(b)) => Arc::ptr_eq(a, b),
+            (GreenElement::Token(a), GreenElement::Token(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        })
+        .count();
+
+    // Só o `faca` foi recriado.
+    assert_eq!(shared, old_nodes.len() - 1);
+}
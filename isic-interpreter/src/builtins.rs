@@ -0,0 +1,93 @@
+//! # isic_interpreter::builtins
+//!
+//! Implementação das funções embutidas de `isic_middle::builtins`.
+//!
+//! Cada função deve dar exatamente o mesmo resultado que a função de
+//! runtime equivalente no C gerado pelo isic-back. Por isso, algumas
+//! delas não usam diretamente a função equivalente da biblioteca padrão
+//! do Rust (como `to_uppercase`, que segue todas as regras do Unicode).
+
+use isic_middle::ArithMode;
+
+use crate::interpreter::IsiValue;
+
+/// Converte um caractere para maiúscula. Só trata o ASCII e as letras
+/// do Latin-1, como o runtime do C.
+fn upper(c: char) -> char {
+    match c {
+        'a'..='z' | '\u{e0}'..='\u{fe}' if c != '\u{f7}' => char::from_u32(c as u32 - 32).unwrap(),
+        c => c,
+    }
+}
+
+/// Converte um caractere para minúscula. Só trata o ASCII e as letras
+/// do Latin-1, como o runtime do C.
+fn lower(c: char) -> char {
+    match c {
+        'A'..='Z' | '\u{c0}'..='\u{de}' if c != '\u{d7}' => char::from_u32(c as u32 + 32).unwrap(),
+        c => c,
+    }
+}
+
+/// Chama a função embutida `name`. Os argumentos já foram validados e
+/// convertidos pelo type checker.
+///
+/// Os erros retornados ainda não têm a linha do código fonte, que deve
+/// ser adicionada por quem chamou.
+pub fn call(name: &str, args: Vec<IsiValue>, mode: ArithMode) -> Result<IsiValue, String> {
+    use IsiValue::{Int, Real, String as Str};
+
+    let oob = || "index out of bounds".to_string();
+
+    let val = match (name, args.as_slice()) {
+        ("tamanho", [Str(s)]) => Int(s.chars().count() as i64),
+        ("maiusculas", [Str(s)]) => Str(s.chars().map(upper).collect()),
+        ("minusculas", [Str(s)]) => Str(s.chars().map(lower).collect()),
+        ("sub", [Str(s), Int(i), Int(n)]) => {
+            if *i < 0 || *i > s.chars().count() as i64 || *n < 0 {
+                return Err(oob());
+            }
+
+            Str(s.chars().skip(*i as usize).take(*n as usize).collect())
+        }
+        // Posição em caracteres, e não em bytes.
+        ("busca", [Str(s), Str(t)]) => Int(match s.find(t.as_str()) {
+            Some(b) => s[..b].chars().count() as i64,
+            None => -1,
+        }),
+        ("substitui", [Str(s), Str(from), Str(_)]) if from.is_empty() => Str(s.clone()),
+        ("substitui", [Str(s), Str(from), Str(to)]) => Str(s.replace(from.as_str(), to)),
+        ("divide", [Str(s), Str(sep), Int(i)]) => {
+            let part = match usize::try_from(*i) {
+                // Sem separador, a string inteira é a única parte.
+                Ok(i) if sep.is_empty() => (i == 0).then_some(s.as_str()),
+                Ok(i) => s.split(sep.as_str()).nth(i),
+                Err(_) => None,
+            };
+
+            Str(part.ok_or_else(oob)?.to_string())
+        }
+        ("raiz", [Real(x)]) => Real(x.sqrt()),
+        ("potencia", [Real(x), Real(y)]) => Real(x.powf(*y)),
+        ("abs", [Int(x)]) => Int(match (x.checked_abs(), mode) {
+            (Some(v), _) => v,
+            (None, ArithMode::Checked) => return Err("integer overflow".to_string()),
+            (None, ArithMode::Wrapping) => x.wrapping_abs(),
+        }),
+        ("abs", [Real(x)]) => Real(x.abs()),
+        ("sen", [Real(x)]) => Real(x.sin()),
+        ("cos", [Real(x)]) => Real(x.cos()),
+        ("arredonda", [Real(x)]) => Real(x.round()),
+        ("piso", [Real(x)]) => Real(x.floor()),
+        ("teto", [Real(x)]) => Real(x.ceil()),
+        ("min", [Int(x), Int(y)]) => Int(*x.min(y)),
+        ("min", [Real(x), Real(y)]) => Real(x.min(*y)),
+        ("max", [Int(x), Int(y)]) => Int(*x.max(y)),
+        ("max", [Real(x), Real(y)]) => Real(x.max(*y)),
+        (name, args) => {
+            return Err(format!("Unexpected: wrong arguments for {}: {:?}", name, args))
+        }
+    };
+
+    Ok(val)
+}
@@ -0,0 +1,147 @@
+//! # isic_interpreter::format
+//!
+//! Formatação de valores para a saída, byte a byte igual à do runtime do
+//! C gerado pelo isic-back.
+//!
+//! Números de ponto flutuante são escritos com a menor quantidade de
+//! dígitos significativos que ainda representa o mesmo valor, no estilo
+//! do `%g` do C: `2.5`, `3`, `1e+20`.
+
+use isic_middle::format::{FmtPiece, FmtSpec};
+
+use crate::interpreter::IsiValue;
+
+/// Formata `x` (finito e não negativo) como o `%.{p}g` do C.
+fn format_g(x: f64, p: usize) -> String {
+    let sci = format!("{:.*e}", p - 1, x);
+    let (mantissa, exp) = sci.split_once('e').unwrap();
+    let exp: i32 = exp.parse().unwrap();
+
+    let trim = |s: &str| {
+        if s.contains('.') {
+            s.trim_end_matches('0').trim_end_matches('.').to_string()
+        } else {
+            s.to_string()
+        }
+    };
+
+    if exp < -4 || exp >= p as i32 {
+        format!("{}{}", trim(mantissa), c_exponent(exp))
+    } else {
+        trim(&format!("{:.*}", (p as i32 - 1 - exp) as usize, x))
+    }
+}
+
+/// O expoente no formato do C: `e+05`, `e-10`.
+fn c_exponent(exp: i32) -> String {
+    format!("e{}{:02}", if exp < 0 { '-' } else { '+' }, exp.abs())
+}
+
+/// Formata `x` com a menor quantidade de dígitos que, lida de volta com
+/// `parse`, dá o mesmo valor.
+fn format_shortest(x: f64, max_digits: usize, roundtrips: impl Fn(&str) -> bool) -> String {
+    if x.is_nan() {
+        return "nan".to_string();
+    }
+
+    if x.is_infinite() {
+        return if x < 0.0 { "-inf" } else { "inf" }.to_string();
+    }
+
+    if x.is_sign_negative() {
+        return format!("-{}", format_shortest(-x, max_digits, roundtrips));
+    }
+
+    let s = (1..=max_digits)
+        .map(|p| format_g(x, p))
+        .find(|s| roundtrips(s))
+        .unwrap_or_else(|| format_g(x, max_digits));
+
+    // O `%g` usa notação científica quando o expoente não é menor que a
+    // precisão, o que escreveria 150 como `1.5e+02`. Nesse caso, usamos
+    // precisão suficiente para escrever o número por extenso.
+    match s.split_once('e').map(|(_, exp)| exp.parse::<i32>().unwrap()) {
+        Some(exp) if (0..max_digits as i32).contains(&exp) => format_g(x, exp as usize + 1),
+        _ => s,
+    }
+}
+
+/// Formata um `real` para a saída.
+pub fn format_real(x: f64) -> String {
+    format_shortest(x, 17, |s| s.parse::<f64>() == Ok(x.abs()))
+}
+
+/// Formata um `float` para a saída.
+pub fn format_float(x: f32) -> String {
+    format_shortest(x as f64, 9, |s| s.parse::<f32>() == Ok(x.abs()))
+}
+
+/// Aplica a largura e o alinhamento de `spec` a um texto já formatado.
+fn pad(spec: &FmtSpec, s: String) -> String {
+    let width = spec.width.unwrap_or(0);
+    let len = s.chars().count();
+
+    if len >= width {
+        return s;
+    }
+
+    let fill = width - len;
+
+    if spec.left {
+        format!("{}{}", s, " ".repeat(fill))
+    } else if spec.zero && s.chars().last().is_some_and(|c| c.is_ascii_digit()) {
+        // Os zeros vão depois do sinal. Como no C, `nan` e `inf` são
+        // preenchidos com espaços.
+        let (sign, digits) = s.split_at(if s.starts_with('-') { 1 } else { 0 });
+        format!("{}{}{}", sign, "0".repeat(fill), digits)
+    } else {
+        format!("{}{}", " ".repeat(fill), s)
+    }
+}
+
+/// Formata um número de acordo com a conversão `f` ou `e` do C.
+fn format_fixed(spec: &FmtSpec, x: f64) -> String {
+    if !x.is_finite() {
+        return format_real(x);
+    }
+
+    let precision = spec.precision.unwrap_or(6);
+
+    if spec.conv == 'f' {
+        return format!("{:.*}", precision, x);
+    }
+
+    let sci = format!("{:.*e}", precision, x);
+    let (mantissa, exp) = sci.split_once('e').unwrap();
+
+    format!("{}{}", mantissa, c_exponent(exp.parse().unwrap()))
+}
+
+/// Monta o texto da chamada `escrevaf` com os pedaços do formato e os
+/// argumentos, já convertidos pelo type checker.
+pub fn render(pieces: &[FmtPiece], args: &[IsiValue]) -> Result<String, String> {
+    let mut out = String::new();
+    let mut args = args.iter();
+
+    for piece in pieces {
+        let spec = match piece {
+            FmtPiece::Text(t) => {
+                out.push_str(t);
+                continue;
+            }
+            FmtPiece::Spec(spec) => spec,
+        };
+
+        let s = match (spec.conv, args.next()) {
+            ('d', Some(IsiValue::Int(i))) => i.to_string(),
+            ('f' | 'e', Some(IsiValue::Real(x))) => format_fixed(spec, *x),
+            ('s', Some(IsiValue::String(s))) => s.clone(),
+            ('c', Some(IsiValue::Char(c))) => c.to_string(),
+            (conv, arg) => return Err(format!("Unexpected: wrong argument for %{}: {:?}", conv, arg)),
+        };
+
+        out.push_str(&pad(spec, s));
+    }
+
+    Ok(out)
+}
@@ -0,0 +1,207 @@
+//! # isic_interpreter::fs
+//!
+//! Os arquivos usados pelas funções `abra`, `leialinha`, `escrevaarq`,
+//! `fimdearquivo` e `feche`.
+//!
+//! O interpretador não acessa o sistema de arquivos diretamente, e sim por
+//! meio de um `IsiFs`. A CLI usa o `StdFs`, que lê e escreve arquivos de
+//! verdade, e o playground e o `isic test` usam o `MemFs`, que guarda os
+//! arquivos na memória.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Cursor, Write},
+    rc::Rc,
+};
+
+/// Um sistema de arquivos onde o programa pode ler e escrever.
+pub trait IsiFs {
+    /// Abre o arquivo `path` para leitura.
+    fn open_read(&mut self, path: &str) -> io::Result<Box<dyn BufRead>>;
+
+    /// Abre o arquivo `path` para escrita, criando o arquivo se ele não
+    /// existir. Se `append` for false, o conteúdo anterior é apagado.
+    fn open_write(&mut self, path: &str, append: bool) -> io::Result<Box<dyn Write>>;
+}
+
+/// O sistema de arquivos da máquina.
+pub struct StdFs;
+
+impl IsiFs for StdFs {
+    fn open_read(&mut self, path: &str) -> io::Result<Box<dyn BufRead>> {
+        Ok(Box::new(BufReader::new(File::open(path)?)))
+    }
+
+    fn open_write(&mut self, path: &str, append: bool) -> io::Result<Box<dyn Write>> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)?;
+
+        Ok(Box::new(BufWriter::new(file)))
+    }
+}
+
+/// Um sistema de arquivos em memória.
+#[derive(Default)]
+pub struct MemFs {
+    files: HashMap<String, Rc<RefCell<Vec<u8>>>>,
+}
+
+/// Escreve no fim de um arquivo do MemFs.
+struct MemWriter(Rc<RefCell<Vec<u8>>>);
+
+impl Write for MemWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl MemFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cria (ou substitui) o arquivo `path` com o conteúdo dado.
+    pub fn insert(&mut self, path: &str, contents: &str) {
+        self.files.insert(path.to_string(), Rc::new(RefCell::new(contents.into())));
+    }
+
+    /// O conteúdo atual do arquivo `path`, se ele existir.
+    pub fn get(&self, path: &str) -> Option<String> {
+        self.files
+            .get(path)
+            .map(|f| String::from_utf8_lossy(&f.borrow()).into_owned())
+    }
+}
+
+impl IsiFs for MemFs {
+    fn open_read(&mut self, path: &str) -> io::Result<Box<dyn BufRead>> {
+        // A leitura vê o conteúdo do momento em que o arquivo foi aberto.
+        let file = self.files.get(path).ok_or(io::ErrorKind::NotFound)?;
+
+        Ok(Box::new(Cursor::new(file.borrow().clone())))
+    }
+
+    fn open_write(&mut self, path: &str, append: bool) -> io::Result<Box<dyn Write>> {
+        let file = self.files.entry(path.to_string()).or_default();
+
+        if !append {
+            file.borrow_mut().clear();
+        }
+
+        Ok(Box::new(MemWriter(file.clone())))
+    }
+}
+
+/// Um arquivo aberto pelo programa.
+enum OpenFile {
+    Read(Box<dyn BufRead>),
+    Write(Box<dyn Write>),
+}
+
+/// Os arquivos abertos pelo programa. Os valores do tipo `arquivo` são
+/// índices nesta tabela.
+///
+/// Os erros retornados ainda não têm a linha do código fonte, que deve ser
+/// adicionada por quem chamou. As mensagens são as mesmas do runtime do C.
+pub struct Files<'a> {
+    fs: &'a mut dyn IsiFs,
+    open: Vec<Option<OpenFile>>,
+}
+
+impl<'a> Files<'a> {
+    pub fn new(fs: &'a mut dyn IsiFs) -> Self {
+        Files { fs, open: vec![] }
+    }
+
+    /// Abre o arquivo `path` no modo dado: `"l"` para leitura, `"e"` para
+    /// escrita e `"a"` para escrita no fim do arquivo.
+    pub fn open(&mut self, path: &str, mode: &str) -> Result<usize, String> {
+        let file = match mode {
+            "l" => self.fs.open_read(path).map(OpenFile::Read),
+            "e" => self.fs.open_write(path, false).map(OpenFile::Write),
+            "a" => self.fs.open_write(path, true).map(OpenFile::Write),
+            _ => return Err("invalid file mode".to_string()),
+        };
+
+        let file = file.map_err(|_| "could not open file".to_string())?;
+        self.open.push(Some(file));
+
+        Ok(self.open.len() - 1)
+    }
+
+    fn get(&mut self, f: usize) -> Result<&mut OpenFile, String> {
+        self.open
+            .get_mut(f)
+            .and_then(|file| file.as_mut())
+            .ok_or_else(|| "invalid file operation".to_string())
+    }
+
+    fn reader(&mut self, f: usize) -> Result<&mut Box<dyn BufRead>, String> {
+        match self.get(f)? {
+            OpenFile::Read(r) => Ok(r),
+            OpenFile::Write(_) => Err("invalid file operation".to_string()),
+        }
+    }
+
+    /// Lê a próxima linha do arquivo, sem a quebra de linha.
+    pub fn read_line(&mut self, f: usize) -> Result<String, String> {
+        let mut line = String::new();
+
+        let n = self
+            .reader(f)?
+            .read_line(&mut line)
+            .map_err(|_| "invalid file operation".to_string())?;
+
+        if n == 0 {
+            return Err("end of file".to_string());
+        }
+
+        let line = line.strip_suffix('\n').unwrap_or(&line);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+
+        Ok(line.to_string())
+    }
+
+    /// Retorna true se não há mais nada a ser lido do arquivo.
+    pub fn eof(&mut self, f: usize) -> Result<bool, String> {
+        let buf = self
+            .reader(f)?
+            .fill_buf()
+            .map_err(|_| "invalid file operation".to_string())?;
+
+        Ok(buf.is_empty())
+    }
+
+    /// Escreve `s` no arquivo.
+    pub fn write(&mut self, f: usize, s: &str) -> Result<(), String> {
+        match self.get(f)? {
+            OpenFile::Write(w) => w
+                .write_all(s.as_bytes())
+                .map_err(|_| "invalid file operation".to_string()),
+            OpenFile::Read(_) => Err("invalid file operation".to_string()),
+        }
+    }
+
+    /// Fecha o arquivo. Usar o arquivo depois disso é um erro.
+    pub fn close(&mut self, f: usize) -> Result<(), String> {
+        self.get(f)?;
+
+        if let Some(OpenFile::Write(mut w)) = self.open[f].take() {
+            w.flush().map_err(|_| "invalid file operation".to_string())?;
+        }
+
+        Ok(())
+    }
+}
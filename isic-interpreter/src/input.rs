@@ -0,0 +1,158 @@
+//! A entrada lida por `leia`.
+//!
+//! A entrada é vista como uma sequência de valores separados por espaços,
+//! que podem estar em uma mesma linha ou em linhas diferentes. Números
+//! aceitam tanto a vírgula quanto o ponto como separador decimal. O runtime
+//! do C gerado (`input.c`, no isic-back) segue exatamente as mesmas regras.
+
+use std::io::BufRead;
+
+use isic_middle::IsiType;
+
+use crate::interpreter::IsiValue;
+
+/// Os espaços que separam os valores. São os mesmos do runtime do C.
+fn is_space(c: char) -> bool {
+    c.is_ascii_whitespace()
+}
+
+/// Verifica se `s` é um número válido: um inteiro com sinal opcional, ou,
+/// se `real`, também com parte decimal e expoente (`-3,14`, `2.5e-3`).
+pub(crate) fn is_number(s: &str, real: bool) -> bool {
+    let mut s = s.strip_prefix(['+', '-']).unwrap_or(s);
+
+    let digits = |s: &mut &str| {
+        let n = s.len() - s.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        *s = &s[n..];
+        n > 0
+    };
+
+    if !digits(&mut s) {
+        return false;
+    }
+
+    if real {
+        if let Some(mut rest) = s.strip_prefix([',', '.']) {
+            if !digits(&mut rest) {
+                return false;
+            }
+            s = rest;
+        }
+
+        if let Some(rest) = s.strip_prefix(['e', 'E']) {
+            let mut rest = rest.strip_prefix(['+', '-']).unwrap_or(rest);
+            if !digits(&mut rest) {
+                return false;
+            }
+            s = rest;
+        }
+    }
+
+    s.is_empty()
+}
+
+pub struct IsiInput<'a, R: BufRead> {
+    /// De onde as linhas são lidas.
+    reader: &'a mut R,
+    /// A linha sendo lida.
+    line: String,
+    /// A posição, em bytes, do que ainda não foi lido em `line`.
+    pos: usize,
+}
+
+impl<'a, R: BufRead> IsiInput<'a, R> {
+    pub fn new(reader: &'a mut R) -> Self {
+        IsiInput {
+            reader,
+            line: String::new(),
+            pos: 0,
+        }
+    }
+
+    /// Pula os espaços até o próximo caractere, lendo novas linhas se
+    /// necessário. Retorna Err(e) no fim da entrada.
+    fn skip_spaces(&mut self) -> Result<(), String> {
+        loop {
+            let rest = &self.line[self.pos..];
+            self.pos += rest.len() - rest.trim_start_matches(is_space).len();
+
+            if self.pos < self.line.len() {
+                return Ok(());
+            }
+
+            self.line.clear();
+            self.pos = 0;
+
+            let n = self
+                .reader
+                .read_line(&mut self.line)
+                .map_err(|_| "invalid input".to_string())?;
+
+            if n == 0 {
+                return Err("unexpected end of input".to_string());
+            }
+        }
+    }
+
+    /// Lê o próximo valor, até o próximo espaço.
+    fn token(&mut self) -> Result<&str, String> {
+        self.skip_spaces()?;
+
+        let start = self.pos;
+        let rest = &self.line[start..];
+        self.pos += rest.find(is_space).unwrap_or(rest.len());
+
+        Ok(&self.line[start..self.pos])
+    }
+
+    /// Descarta o resto da linha atual, depois de uma entrada inválida.
+    pub fn skip_line(&mut self) {
+        self.pos = self.line.len();
+    }
+
+    /// Lê um valor do tipo `ty`. Retorna Ok(None) se a entrada não é um
+    /// valor válido do tipo, e Err(e) no fim da entrada.
+    pub fn read(&mut self, ty: IsiType) -> Result<Option<IsiValue>, String> {
+        let val = match ty {
+            IsiType::Int | IsiType::BigInt | IsiType::Float | IsiType::Real => {
+                let real = matches!(ty, IsiType::Float | IsiType::Real);
+
+                let tok = self.token()?;
+                if !is_number(tok, real) {
+                    return Ok(None);
+                }
+
+                let tok = tok.replace(',', ".");
+                match ty {
+                    // Um int fora do intervalo também é inválido.
+                    IsiType::Int => tok.parse().ok().map(IsiValue::Int),
+                    IsiType::BigInt => tok.parse().ok().map(IsiValue::BigInt),
+                    IsiType::Float => tok.parse().ok().map(IsiValue::Float),
+                    _ => tok.parse().ok().map(IsiValue::Real),
+                }
+            }
+            IsiType::Char => {
+                self.skip_spaces()?;
+
+                let c = self.line[self.pos..].chars().next().unwrap();
+                self.pos += c.len_utf8();
+
+                Some(IsiValue::Char(c))
+            }
+            IsiType::String => {
+                // Strings ocupam o resto da linha.
+                self.skip_spaces()?;
+
+                let s = self.line[self.pos..].trim_end_matches(is_space).to_string();
+                self.skip_line();
+
+                Some(IsiValue::String(s))
+            }
+            IsiType::File | IsiType::Bool | IsiType::Unit => {
+                unreachable!("Unexpected: leia on a variable of type {:?}", ty)
+            }
+        };
+
+        Ok(val)
+    }
+}
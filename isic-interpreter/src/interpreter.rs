@@ -1,13 +1,23 @@
-use std::{collections::HashMap, io::{Write, BufRead}, fmt::Display};
+use std::{collections::{HashMap, HashSet}, io::{Write, BufRead}, fmt::Display};
 
-use isic_front::{ast::{IsiProgram, Ident, BinaryOp, Expr}, visitor::IsiVisitor};
-use isic_middle::IsiType;
+use isic_front::{ast::{IsiProgram, Ident, BinaryOp, Contract, ContractKind, Expr, Statement, TestBlock}, span::{LineIndex, Span}, visitor::IsiVisitor};
+use isic_middle::{ArithMode, InputMode, IsiType, RuntimeOptions, builtins, ops::{self, OpEval}};
+use num_bigint::BigInt;
+use num_traits::{Signed, ToPrimitive, Zero};
+
+use crate::{format, fs::{Files, IsiFs}, input::{is_number, IsiInput}, random::Rng};
 
 #[derive(Clone, Debug)]
 pub enum IsiValue {
-    Int(u64),
+    Int(i64),
     Float(f32),
+    Real(f64),
+    BigInt(BigInt),
+    Char(char),
     String(String),
+    /// Um arquivo aberto, como um índice na tabela de arquivos do
+    /// interpretador.
+    File(usize),
     Bool(bool),
     Unit,
 }
@@ -16,14 +26,286 @@ impl Display for IsiValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             IsiValue::Int(i) => write!(f, "{}", i),
-            IsiValue::Float(x) => write!(f, "{}", x),
+            IsiValue::Float(x) => write!(f, "{}", format::format_float(*x)),
+            IsiValue::Real(x) => write!(f, "{}", format::format_real(*x)),
+            IsiValue::BigInt(x) => write!(f, "{}", x),
+            IsiValue::Char(c) => write!(f, "{}", c),
             IsiValue::String(s) => write!(f, "{}", s),
+            IsiValue::File(i) => write!(f, "<arquivo {}>", i),
             IsiValue::Bool(b) => write!(f, "{}", b),
             IsiValue::Unit => write!(f, ""),
         }
     }
 }
 
+impl IsiValue {
+    /// Retorna o tipo IsiLang deste valor.
+    pub fn ty(&self) -> IsiType {
+        match self {
+            IsiValue::Int(_) => IsiType::Int,
+            IsiValue::Float(_) => IsiType::Float,
+            IsiValue::Real(_) => IsiType::Real,
+            IsiValue::BigInt(_) => IsiType::BigInt,
+            IsiValue::Char(_) => IsiType::Char,
+            IsiValue::String(_) => IsiType::String,
+            IsiValue::File(_) => IsiType::File,
+            IsiValue::Bool(_) => IsiType::Bool,
+            IsiValue::Unit => IsiType::Unit,
+        }
+    }
+
+    /// Converte este valor para o tipo `ty`. Usado tanto nas conversões
+    /// implícitas inseridas pelo type checker quanto nas funções de
+    /// conversão `inteiro`, `real`, `caractere` e `texto`.
+    pub fn convert(self, ty: IsiType) -> Result<IsiValue, String> {
+        use IsiValue::{BigInt as Big, Char, Float, Int, Real, String as Str};
+
+        let val = match (self, ty) {
+            (v, ty) if v.ty() == ty => v,
+            (Int(i), IsiType::Float) => Float(i as f32),
+            (Int(i), IsiType::Real) => Real(i as f64),
+            // Como no `as` do Rust, valores fora do intervalo saturam e NaN
+            // vira 0. O C gerado faz o mesmo com isi_real_to_int.
+            (Float(x), IsiType::Int) => Int(x as i64),
+            (Float(x), IsiType::Real) => Real(x as f64),
+            (Real(x), IsiType::Int) => Int(x as i64),
+            (Real(x), IsiType::Float) => Float(x as f32),
+            (Int(i), IsiType::BigInt) => Big(BigInt::from(i)),
+            // Assim como no C, ficamos só com os 64 bits menos significativos.
+            (Big(x), IsiType::Int) => Int((x & BigInt::from(u64::MAX)).to_u64().unwrap() as i64),
+            (Big(x), IsiType::Real) => Real(x.to_f64().unwrap()),
+            (Char(c), IsiType::Int) => Int(c as i64),
+            // Valores que não são code points válidos viram U+FFFD.
+            (Int(i), IsiType::Char) => Char(
+                u32::try_from(i)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .unwrap_or(char::REPLACEMENT_CHARACTER),
+            ),
+            // Textos aceitam os mesmos números que `leia`, com espaços nas
+            // pontas.
+            (Str(s), ty @ (IsiType::Int | IsiType::Real | IsiType::BigInt)) => {
+                let text = s.trim_matches(|c: char| c.is_ascii_whitespace());
+                let error = || format!("Cannot convert \"{}\" to {:?}", s, ty);
+
+                if !is_number(text, ty == IsiType::Real) {
+                    return Err(error());
+                }
+
+                let text = text.replace(',', ".");
+                match ty {
+                    IsiType::Int => Int(text.parse().map_err(|_| error())?),
+                    IsiType::BigInt => Big(text.parse().map_err(|_| error())?),
+                    _ => Real(text.parse().map_err(|_| error())?),
+                }
+            }
+            (v, IsiType::String) => Str(v.to_string()),
+            (v, ty) => return Err(format!("Unexpected: cannot convert {:?} to {:?}", v, ty)),
+        };
+
+        Ok(val)
+    }
+}
+
+/// Aplica uma operação aritmética entre dois `int`s, tratando overflow
+/// de acordo com `mode`. Deve se comportar exatamente como as funções
+/// de runtime equivalentes no C gerado pelo isic-back.
+fn int_op(op: BinaryOp, l: i64, r: i64, mode: ArithMode) -> Result<i64, String> {
+    if matches!(op, BinaryOp::Div | BinaryOp::IntDiv | BinaryOp::Mod) && r == 0 {
+        return Err("division by zero".to_string());
+    }
+
+    let checked = match op {
+        BinaryOp::Add => l.checked_add(r),
+        BinaryOp::Sub => l.checked_sub(r),
+        BinaryOp::Mul => l.checked_mul(r),
+        BinaryOp::Div => l.checked_div(r),
+        BinaryOp::IntDiv => l.checked_div(r).map(|q| floor_adjust(q, l.wrapping_rem(r) != 0, l < 0, r < 0)),
+        BinaryOp::Mod => l.checked_rem(r),
+        BinaryOp::Shl => u32::try_from(r).ok().and_then(|r| l.checked_shl(r)),
+        BinaryOp::Shr => u32::try_from(r).ok().and_then(|r| l.checked_shr(r)),
+        BinaryOp::Pow => return int_pow(l, r, mode),
+        op => return Err(format!("Unexpected: {:?} is not an arithmetic operation", op)),
+    };
+
+    match (checked, mode) {
+        (Some(v), _) => Ok(v),
+        (None, ArithMode::Checked) => Err("integer overflow".to_string()),
+        (None, ArithMode::Wrapping) => Ok(match op {
+            BinaryOp::Add => l.wrapping_add(r),
+            BinaryOp::Sub => l.wrapping_sub(r),
+            BinaryOp::Mul => l.wrapping_mul(r),
+            // O único overflow da divisão é i64::MIN / -1, que é exata.
+            BinaryOp::Div | BinaryOp::IntDiv => l.wrapping_div(r),
+            BinaryOp::Mod => l.wrapping_rem(r),
+            BinaryOp::Shl => l.wrapping_shl(r as u32),
+            _ => l.wrapping_shr(r as u32),
+        }),
+    }
+}
+
+/// O quociente truncado `q` arredondado para baixo, como o `div` faz em
+/// todos os tipos: se a divisão não é exata e os sinais são diferentes, o
+/// quociente verdadeiro está entre `q - 1` e `q`.
+fn floor_adjust<T: std::ops::Sub<Output = T> + From<i8>>(q: T, inexact: bool, l_neg: bool, r_neg: bool) -> T {
+    if inexact && l_neg != r_neg {
+        q - T::from(1)
+    } else {
+        q
+    }
+}
+
+/// Potência entre `int`s, por exponenciação rápida. Expoentes negativos
+/// dão o resultado truncado, como na divisão.
+fn int_pow(base: i64, exp: i64, mode: ArithMode) -> Result<i64, String> {
+    if exp < 0 {
+        return match base {
+            0 => Err("division by zero".to_string()),
+            1 => Ok(1),
+            -1 => Ok(if exp % 2 == 0 { 1 } else { -1 }),
+            _ => Ok(0),
+        };
+    }
+
+    let (mut r, mut b, mut e) = (1, base, exp);
+    while e > 0 {
+        if e & 1 == 1 {
+            r = int_op(BinaryOp::Mul, r, b, mode)?;
+        }
+        e >>= 1;
+        if e > 0 {
+            b = int_op(BinaryOp::Mul, b, b, mode)?;
+        }
+    }
+
+    Ok(r)
+}
+
+/// Aplica o operador binário `op` a dois valores já avaliados.
+///
+/// Quais combinações são válidas é decidido pela tabela de
+/// `isic_middle::ops`; aqui só implementamos cada uma delas.
+fn apply_op(op: BinaryOp, lhs: IsiValue, rhs: IsiValue, mode: ArithMode) -> Result<IsiValue, String> {
+    use IsiValue::{BigInt as Big, Bool, Char, Float, Int, Real, String as Str};
+
+    let val = match (op, lhs, rhs) {
+        (BinaryOp::Div | BinaryOp::IntDiv | BinaryOp::Mod, Big(_), Big(r)) if r.is_zero() => {
+            return Err("division by zero".to_string())
+        }
+        (
+            BinaryOp::Add
+            | BinaryOp::Sub
+            | BinaryOp::Mul
+            | BinaryOp::Div
+            | BinaryOp::IntDiv
+            | BinaryOp::Mod
+            | BinaryOp::Pow
+            | BinaryOp::Shl
+            | BinaryOp::Shr,
+            Int(l),
+            Int(r),
+        ) => Int(int_op(op, l, r, mode)?),
+        (BinaryOp::Add, Float(l), Float(r)) => Float(l + r),
+        (BinaryOp::Add, Real(l), Real(r)) => Real(l + r),
+        (BinaryOp::Add, Big(l), Big(r)) => Big(l + r),
+        (BinaryOp::Add, Str(l), Str(r)) => Str(format!("{}{}", l, r)),
+        (BinaryOp::Sub, Float(l), Float(r)) => Float(l - r),
+        (BinaryOp::Sub, Real(l), Real(r)) => Real(l - r),
+        (BinaryOp::Sub, Big(l), Big(r)) => Big(l - r),
+        (BinaryOp::Mul, Float(l), Float(r)) => Float(l * r),
+        (BinaryOp::Mul, Real(l), Real(r)) => Real(l * r),
+        (BinaryOp::Mul, Big(l), Big(r)) => Big(l * r),
+        (BinaryOp::Div, Float(l), Float(r)) => Float(l / r),
+        (BinaryOp::Div, Real(l), Real(r)) => Real(l / r),
+        (BinaryOp::Div, Big(l), Big(r)) => Big(l / r),
+        (BinaryOp::IntDiv, Big(l), Big(r)) => {
+            let inexact = !(&l % &r).is_zero();
+            let (l_neg, r_neg) = (l.is_negative(), r.is_negative());
+
+            Big(floor_adjust(l / r, inexact, l_neg, r_neg))
+        }
+        (BinaryOp::Mod, Big(l), Big(r)) => Big(l % r),
+        (BinaryOp::IntDiv, Float(l), Float(r)) => Float((l / r).floor()),
+        (BinaryOp::IntDiv, Real(l), Real(r)) => Real((l / r).floor()),
+        (BinaryOp::Pow, Float(l), Float(r)) => Float(l.powf(r)),
+        (BinaryOp::Pow, Real(l), Real(r)) => Real(l.powf(r)),
+        (BinaryOp::Pow, Big(_), Big(r)) if r.is_negative() => return Err("negative exponent".to_string()),
+        (BinaryOp::Pow, Big(l), Big(r)) => Big(l.pow(r.to_u32().ok_or("exponent too large")?)),
+        (BinaryOp::BitAnd, Int(l), Int(r)) => Int(l & r),
+        (BinaryOp::BitOr, Int(l), Int(r)) => Int(l | r),
+        (BinaryOp::BitXor, Int(l), Int(r)) => Int(l ^ r),
+        (BinaryOp::Gt, Int(l), Int(r)) => Bool(l > r),
+        (BinaryOp::Gt, Float(l), Float(r)) => Bool(l > r),
+        (BinaryOp::Gt, Real(l), Real(r)) => Bool(l > r),
+        (BinaryOp::Gt, Big(l), Big(r)) => Bool(l > r),
+        (BinaryOp::Gt, Char(l), Char(r)) => Bool(l > r),
+        (BinaryOp::Gt, Str(l), Str(r)) => Bool(l > r),
+        (BinaryOp::Lt, Int(l), Int(r)) => Bool(l < r),
+        (BinaryOp::Lt, Float(l), Float(r)) => Bool(l < r),
+        (BinaryOp::Lt, Real(l), Real(r)) => Bool(l < r),
+        (BinaryOp::Lt, Big(l), Big(r)) => Bool(l < r),
+        (BinaryOp::Lt, Char(l), Char(r)) => Bool(l < r),
+        (BinaryOp::Lt, Str(l), Str(r)) => Bool(l < r),
+        (BinaryOp::Geq, Int(l), Int(r)) => Bool(l >= r),
+        (BinaryOp::Geq, Float(l), Float(r)) => Bool(l >= r),
+        (BinaryOp::Geq, Real(l), Real(r)) => Bool(l >= r),
+        (BinaryOp::Geq, Big(l), Big(r)) => Bool(l >= r),
+        (BinaryOp::Geq, Char(l), Char(r)) => Bool(l >= r),
+        (BinaryOp::Geq, Str(l), Str(r)) => Bool(l >= r),
+        (BinaryOp::Leq, Int(l), Int(r)) => Bool(l <= r),
+        (BinaryOp::Leq, Float(l), Float(r)) => Bool(l <= r),
+        (BinaryOp::Leq, Real(l), Real(r)) => Bool(l <= r),
+        (BinaryOp::Leq, Big(l), Big(r)) => Bool(l <= r),
+        (BinaryOp::Leq, Char(l), Char(r)) => Bool(l <= r),
+        (BinaryOp::Leq, Str(l), Str(r)) => Bool(l <= r),
+        (BinaryOp::Eq, Int(l), Int(r)) => Bool(l == r),
+        (BinaryOp::Eq, Float(l), Float(r)) => Bool(l == r),
+        (BinaryOp::Eq, Real(l), Real(r)) => Bool(l == r),
+        (BinaryOp::Eq, Big(l), Big(r)) => Bool(l == r),
+        (BinaryOp::Eq, Char(l), Char(r)) => Bool(l == r),
+        (BinaryOp::Eq, Str(l), Str(r)) => Bool(l == r),
+        (BinaryOp::Eq, Bool(l), Bool(r)) => Bool(l == r),
+        (BinaryOp::Neq, Int(l), Int(r)) => Bool(l != r),
+        (BinaryOp::Neq, Float(l), Float(r)) => Bool(l != r),
+        (BinaryOp::Neq, Real(l), Real(r)) => Bool(l != r),
+        (BinaryOp::Neq, Big(l), Big(r)) => Bool(l != r),
+        (BinaryOp::Neq, Char(l), Char(r)) => Bool(l != r),
+        (BinaryOp::Neq, Str(l), Str(r)) => Bool(l != r),
+        (BinaryOp::Neq, Bool(l), Bool(r)) => Bool(l != r),
+        (BinaryOp::And, Bool(l), Bool(r)) => Bool(l && r),
+        (BinaryOp::Or, Bool(l), Bool(r)) => Bool(l || r),
+        (op, l, r) => {
+            return Err(format!("Unexpected: wrong values for operation {:?}: {:?} and {:?}", op, l, r))
+        }
+    };
+
+    Ok(val)
+}
+
+/// Por que a execução do programa foi interrompida.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Interrupt {
+    /// Um erro em tempo de execução, como um overflow. Pode ser capturado
+    /// por um `tente`.
+    Error(String),
+    /// Um `afirme`, invariante ou pré ou pós-condição violado. Estes não
+    /// são capturados por um `tente`, já que indicam um erro no programa.
+    Violation(String),
+    /// O programa chamou `saia(codigo)`.
+    Exit(i32),
+}
+
+impl From<String> for Interrupt {
+    fn from(e: String) -> Self {
+        Interrupt::Error(e)
+    }
+}
+
+/// Uma função do programa hospedeiro, chamada pelo interpretador no lugar
+/// de uma função externa de mesmo nome. Os argumentos já têm os tipos da
+/// declaração, e os erros retornados ainda não têm a linha do código fonte.
+pub type HostFn<'a> = Box<dyn FnMut(&[IsiValue]) -> Result<IsiValue, String> + 'a>;
+
 /// O interpretador da IsiLanguage.
 ///
 /// Ele é implementado como um IsiVisitor, e cada função visitadora
@@ -41,197 +323,458 @@ pub struct IsiInterpreter<'a, R: BufRead, W: Write> {
     sym_table: HashMap<Ident, IsiValue>,
     /// Tabela de tipos das variáveis do programa.
     sym_types: HashMap<Ident, IsiType>, // apenas pra scan...
-    /// Referência ao stdin.
-    stdin: &'a mut R,
+    /// Conversões implícitas decididas pelo type checker.
+    coercions: &'a HashMap<Span, IsiType>,
+    /// Como tratar overflow e entradas inválidas.
+    options: RuntimeOptions,
+    /// Índice de linhas do código fonte, para localizar erros.
+    lines: &'a LineIndex,
+    /// A entrada lida por `leia`, a partir do stdin.
+    input: IsiInput<'a, R>,
     /// Referência ao stdout.
     stdout: &'a mut W,
+    /// Os arquivos abertos pelo programa.
+    files: Files<'a>,
+    /// O gerador usado por `semente` e `aleatorio`.
+    rng: Rng,
+    /// Os argumentos do programa, sem o nome do executável.
+    args: Vec<String>,
+    /// As funções externas já declaradas pelo programa.
+    externs: HashSet<String>,
+    /// As funções do hospedeiro que implementam funções externas.
+    host_fns: HashMap<String, HostFn<'a>>,
 }
 
 impl<'a, R: BufRead, W: Write> IsiInterpreter<'a, R, W> {
     /// Cria um novo interpretador.
-    pub fn new(program: &'a IsiProgram, stdin: &'a mut R, stdout: &'a mut W) -> Self {
+    pub fn new(
+        program: &'a IsiProgram,
+        coercions: &'a HashMap<Span, IsiType>,
+        options: RuntimeOptions,
+        lines: &'a LineIndex,
+        stdin: &'a mut R,
+        stdout: &'a mut W,
+        fs: &'a mut dyn IsiFs,
+    ) -> Self {
         IsiInterpreter {
             program,
             sym_table: HashMap::new(),
             sym_types: HashMap::new(),
-            stdin,
+            coercions,
+            options,
+            lines,
+            input: IsiInput::new(stdin),
             stdout,
+            files: Files::new(fs),
+            rng: Rng::default(),
+            args: vec![],
+            externs: HashSet::new(),
+            host_fns: HashMap::new(),
         }
     }
 
-    /// Executa o programa associado.
-    pub fn exec(&mut self) {
-        for i in self.visit_program(&self.program) {
-            i.unwrap();
+    /// Define os argumentos do programa, lidos por `numargs` e
+    /// `argumentos`.
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// Registra uma função do hospedeiro, chamada quando o programa chama a
+    /// função externa `name`. Sem ela, a chamada é um erro, já que o
+    /// interpretador não pode executar código C.
+    pub fn with_host_fn(mut self, name: &str, f: HostFn<'a>) -> Self {
+        self.host_fns.insert(name.to_string(), f);
+        self
+    }
+
+    /// Executa o programa associado. Retorna Ok(codigo) com o código de
+    /// saída do programa (0, a menos que ele tenha chamado `saia`), ou
+    /// Err(e) se a execução foi interrompida por um erro, como um overflow.
+    pub fn exec(&mut self) -> Result<i32, String> {
+        self.run(&self.program.statements, &[])
+    }
+
+    /// Executa o programa associado e, depois dele, os comandos do teste
+    /// dado. Os blocos de teste são ignorados pelo `exec`. Retorna o mesmo
+    /// que o `exec`; se o programa chamar `saia`, os comandos do teste não
+    /// são executados.
+    pub fn exec_test(&mut self, test: &'a TestBlock) -> Result<i32, String> {
+        self.run(&self.program.statements, &test.body)
+    }
+
+    fn run(&mut self, program: &'a [Statement], test: &'a [Statement]) -> Result<i32, String> {
+        let result = self
+            .run_statements(program)
+            .and_then(|_| self.check_postconditions(program))
+            .and_then(|_| self.run_statements(test));
+
+        match result {
+            Ok(()) => Ok(0),
+            Err(Interrupt::Exit(code)) => Ok(code),
+            Err(Interrupt::Error(e) | Interrupt::Violation(e)) => Err(e),
+        }
+    }
+
+    fn run_statements(&mut self, stmts: &'a [Statement]) -> Result<(), Interrupt> {
+        // Não usamos visit_program, já que ele continuaria executando
+        // os próximos comandos depois de um erro.
+        for stmt in stmts {
+            self.visit_statement(stmt)?;
         }
+
+        Ok(())
+    }
+
+    /// Verifica os `garante` do programa, depois que ele chegou ao fim. Um
+    /// programa que termina com `saia` não passa por aqui.
+    fn check_postconditions(&mut self, program: &'a [Statement]) -> Result<(), Interrupt> {
+        if self.options.strip_asserts {
+            return Ok(());
+        }
+
+        for stmt in program {
+            let Statement::Contract(Contract { kind: ContractKind::Ensures, cond, span }) = stmt else {
+                continue;
+            };
+
+            if let IsiValue::Bool(false) = self.visit_expr(cond)? {
+                let msg = format!("postcondition violated at line {}", self.lines.line(span.start));
+
+                return Err(Interrupt::Violation(format!("{}{}", msg, self.var_report(cond))));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Os valores das variáveis usadas em `cond`, um por linha, para
+    /// ajudar a entender por que a condição é falsa.
+    fn var_report(&self, cond: &Expr) -> String {
+        let mut report = String::new();
+
+        for id in cond.vars() {
+            match self.sym_table.get(id) {
+                Some(IsiValue::File(_)) | None => {}
+                Some(val) => report.push_str(&format!("\n  {} = {}", id.name, val)),
+            }
+        }
+
+        report
+    }
+
+    /// Verifica o invariante de um laço, se houver, antes da primeira
+    /// iteração (`iteration` 0) ou depois da iteração dada.
+    fn check_invariant(&mut self, inv: &Option<Expr>, iteration: u64) -> Result<(), Interrupt> {
+        let Some(inv) = inv else {
+            return Ok(());
+        };
+
+        if self.options.strip_asserts {
+            return Ok(());
+        }
+
+        if let IsiValue::Bool(false) = self.visit_expr(inv)? {
+            let msg = format!(
+                "loop invariant violated at line {}, iteration {}",
+                self.lines.line(inv.get_span().start),
+                iteration
+            );
+
+            return Err(Interrupt::Violation(format!("{}{}", msg, self.var_report(inv))));
+        }
+
+        Ok(())
     }
 }
 
 impl<'a, R: BufRead, W: Write> IsiVisitor for IsiInterpreter<'a, R, W> {
-    type Ret = Result<IsiValue, String>;
+    type Ret = Result<IsiValue, Interrupt>;
 
     fn visit_int_literal(&mut self, lit: &isic_front::ast::IntLiteral) -> Self::Ret {
-        Ok(IsiValue::Int(lit.0))
+        Ok(IsiValue::Int(lit.0 as i64))
     }
 
     fn visit_float_literal(&mut self, lit: &isic_front::ast::FloatLiteral) -> Self::Ret {
-        Ok(IsiValue::Float(lit.0))
+        Ok(IsiValue::Float(lit.0 as f32))
     }
 
     fn visit_string_literal(&mut self, lit: &isic_front::ast::StringLiteral) -> Self::Ret {
         Ok(IsiValue::String(lit.0.clone()))
     }
 
+    fn visit_char_literal(&mut self, lit: &isic_front::ast::CharLiteral) -> Self::Ret {
+        Ok(IsiValue::Char(lit.0))
+    }
+
     fn visit_ident(&mut self, id: &Ident) -> Self::Ret {
         self
             .sym_table
             .get(id)
             .cloned()
-            .ok_or_else(|| format!("No value for variable {}", id.name))
+            .ok_or_else(|| format!("No value for variable {}", id.name).into())
     }
 
     fn visit_decl(&mut self, decl: &isic_front::ast::VarDecl) -> Self::Ret {
-        // Replicando mecanismo do type checker, nao gotsei...
-        // Mas aqui tambem precisamos dessa info pra poder
+        // Aqui tambem precisamos dos tipos pra poder
         // parsear as entradas do usuario.
-        let ty = match decl.var_type.name.as_str() {
-            "int" => Ok(IsiType::Int),
-            "float" => Ok(IsiType::Float),
-            "string" => Ok( IsiType::String ),
-            "bool" => Ok( IsiType::Bool ),
-            t => Err(format!("Unknown type {} for variable {}", t, decl.var_name.name))
-        }?;
+        let ty = IsiType::from_name(&decl.var_type.name)
+            .ok_or_else(|| format!("Unknown type {} for variable {}", decl.var_type.name, decl.var_name.name))?;
 
         self.sym_types.insert(decl.var_name.clone(), ty);
 
         Ok(IsiValue::Unit)
     }
 
+    fn visit_expr(&mut self, expr: &Expr) -> Self::Ret {
+        let coercion = self.coercions.get(&expr.get_span()).copied();
+
+        // Literais promovidos para real usam a precisão completa
+        // do literal, ao invés de passar por um float.
+        if let (Expr::ImmFloat(lit), Some(IsiType::Real)) = (expr, coercion) {
+            return Ok(IsiValue::Real(lit.0));
+        }
+
+        let val = match expr {
+            Expr::Ident(ident) => self.visit_ident(ident),
+            Expr::ImmInt(imm) => self.visit_int_literal(imm),
+            Expr::ImmFloat(imm) => self.visit_float_literal(imm),
+            Expr::ImmString(imm) => self.visit_string_literal(imm),
+            Expr::ImmChar(imm) => self.visit_char_literal(imm),
+            Expr::BinExpr(bexp) => self.visit_bin_expr(bexp),
+            Expr::FnCall(call) => self.visit_fn_call(call),
+            Expr::Negation(neg) => self.visit_negation(neg),
+            Expr::Ternary(tern) => self.visit_ternary(tern),
+            Expr::Index(idx) => self.visit_index(idx),
+        }?;
+
+        match coercion {
+            Some(ty) => Ok(val.convert(ty)?),
+            None => Ok(val),
+        }
+    }
+
     fn visit_bin_expr(&mut self, bexpr: &isic_front::ast::BinExpr) -> Self::Ret {
         let lhs = self.visit_expr(&bexpr.1)?;
+
+        if ops::eval_strategy(bexpr.0, lhs.ty()) == Some(OpEval::ShortCircuit) {
+            match (bexpr.0, &lhs) {
+                (BinaryOp::And, IsiValue::Bool(false)) => return Ok(IsiValue::Bool(false)),
+                (BinaryOp::Or, IsiValue::Bool(true)) => return Ok(IsiValue::Bool(true)),
+                _ => {}
+            }
+        }
+
         let rhs = self.visit_expr(&bexpr.2)?;
 
-        match bexpr.0 {
-            BinaryOp::Add => {
-                match (lhs, rhs) {
-                    (IsiValue::Int(l), IsiValue::Int(r)) => Ok(IsiValue::Int(l + r)),
-                    (IsiValue::Float(l), IsiValue::Float(r)) => Ok(IsiValue::Float(l + r)),
-                    (IsiValue::String(l), IsiValue::String(r)) => Ok(IsiValue::String(format!("{}{}", l, r))),
-                    (l, r) => Err(format!("Unexpected: wrong values for operation {:?}: {:?} and {:?}", bexpr.0, l, r))
-                }
-            },
-            BinaryOp::Sub => {
-                match (lhs, rhs) {
-                    (IsiValue::Int(l), IsiValue::Int(r)) => Ok(IsiValue::Int(l - r)),
-                    (IsiValue::Float(l), IsiValue::Float(r)) => Ok(IsiValue::Float(l - r)),
-                    (l, r) => Err(format!("Unexpected: wrong values for operation {:?}: {:?} and {:?}", bexpr.0, l, r))
-                }
-            },
-            BinaryOp::Mul => {
-                match (lhs, rhs) {
-                    (IsiValue::Int(l), IsiValue::Int(r)) => Ok(IsiValue::Int(l * r)),
-                    (IsiValue::Float(l), IsiValue::Float(r)) => Ok(IsiValue::Float(l * r)),
-                    (l, r) => Err(format!("Unexpected: wrong values for operation {:?}: {:?} and {:?}", bexpr.0, l, r))
+        if ops::lookup(bexpr.0, lhs.ty(), rhs.ty()).is_none() {
+            return Err(format!("Unexpected: wrong values for operation {:?}: {:?} and {:?}", bexpr.0, lhs, rhs).into());
+        }
+
+        apply_op(bexpr.0, lhs, rhs, self.options.arith)
+            .map_err(|e| format!("{} at line {}", e, self.lines.line(bexpr.get_span().start)).into())
+    }
+
+    fn visit_fn_call(&mut self, call: &isic_front::ast::FnCall) -> Self::Ret {
+        match call.fname.name.as_str() {
+            "escreva" | "escrevasl" => {
+                // Todos os valores são avaliados antes de qualquer coisa ser
+                // escrita, para que um erro não deixe a saída pela metade.
+                let mut text = String::new();
+                for arg in &call.args {
+                    text.push_str(&self.visit_expr(arg)?.to_string());
                 }
-            },
-            BinaryOp::Div => {
-                match (lhs, rhs) {
-                    (IsiValue::Int(l), IsiValue::Int(r)) => Ok(IsiValue::Int(l / r)),
-                    (IsiValue::Float(l), IsiValue::Float(r)) => Ok(IsiValue::Float(l / r)),
-                    (l, r) => Err(format!("Unexpected: wrong values for operation {:?}: {:?} and {:?}", bexpr.0, l, r))
+
+                if call.fname.name == "escreva" {
+                    text.push('\n');
                 }
+
+                write!(self.stdout, "{}", text).unwrap();
+
+                self.stdout.flush().unwrap();
             },
-            BinaryOp::Mod => {
-                match (lhs, rhs) {
-                    (IsiValue::Int(l), IsiValue::Int(r)) => Ok(IsiValue::Int(l % r)),
-                    (l, r) => Err(format!("Unexpected: wrong values for operation {:?}: {:?} and {:?}", bexpr.0, l, r))
+            "escrevaarq" => {
+                let line = self.lines.line(call.get_span().start);
+
+                let IsiValue::File(f) = self.visit_expr(&call.args[0])? else {
+                    unreachable!("Unexpected: escrevaarq on a non-File")
+                };
+
+                let mut text = String::new();
+                for arg in &call.args[1..] {
+                    text.push_str(&self.visit_expr(arg)?.to_string());
                 }
+                text.push('\n');
+
+                self.files
+                    .write(f, &text)
+                    .map_err(|e| format!("{} at line {}", e, line))?;
             },
-            BinaryOp::Gt => {
-                match (lhs, rhs) {
-                    (IsiValue::Int(l), IsiValue::Int(r)) => Ok(IsiValue::Bool(l > r)),
-                    (IsiValue::Float(l), IsiValue::Float(r)) => Ok(IsiValue::Bool(l > r)),
-                    (l, r) => Err(format!("Unexpected: wrong values for operation {:?}: {:?} and {:?}", bexpr.0, l, r))
-                }
+            "numargs" => return Ok(IsiValue::Int(self.args.len() as i64)),
+            "argumentos" => {
+                let IsiValue::Int(i) = self.visit_expr(&call.args[0])? else {
+                    unreachable!("Unexpected: non-Int argument index")
+                };
+
+                return match usize::try_from(i).ok().and_then(|i| self.args.get(i)) {
+                    Some(arg) => Ok(IsiValue::String(arg.clone())),
+                    None => Err(format!(
+                        "index out of bounds at line {}",
+                        self.lines.line(call.get_span().start)
+                    )
+                    .into()),
+                };
             },
-            BinaryOp::Lt => {
-                match (lhs, rhs) {
-                    (IsiValue::Int(l), IsiValue::Int(r)) => Ok(IsiValue::Bool(l < r)),
-                    (IsiValue::Float(l), IsiValue::Float(r)) => Ok(IsiValue::Bool(l < r)),
-                    (l, r) => Err(format!("Unexpected: wrong values for operation {:?}: {:?} and {:?}", bexpr.0, l, r))
-                }
+            "saia" => {
+                let IsiValue::Int(code) = self.visit_expr(&call.args[0])? else {
+                    unreachable!("Unexpected: non-Int exit code")
+                };
+
+                // Como o exit() do C, só os bits de um int são usados.
+                return Err(Interrupt::Exit(code as i32));
             },
-            BinaryOp::Geq => {
-                match (lhs, rhs) {
-                    (IsiValue::Int(l), IsiValue::Int(r)) => Ok(IsiValue::Bool(l >= r)),
-                    (IsiValue::Float(l), IsiValue::Float(r)) => Ok(IsiValue::Bool(l >= r)),
-                    (l, r) => Err(format!("Unexpected: wrong values for operation {:?}: {:?} and {:?}", bexpr.0, l, r))
+            "semente" | "aleatorio" => {
+                let line = self.lines.line(call.get_span().start);
+
+                let mut args = vec![];
+                for arg in &call.args {
+                    args.push(self.visit_expr(arg)?);
                 }
+
+                return match args.as_slice() {
+                    [IsiValue::Int(seed)] => {
+                        self.rng.seed(*seed);
+                        Ok(IsiValue::Unit)
+                    }
+                    [IsiValue::Int(min), IsiValue::Int(max)] => self
+                        .rng
+                        .range(*min, *max)
+                        .map(IsiValue::Int)
+                        .map_err(|e| format!("{} at line {}", e, line).into()),
+                    args => Err(format!("Unexpected: {} called with {:?}", call.fname.name, args).into()),
+                };
             },
-            BinaryOp::Leq => {
-                match (lhs, rhs) {
-                    (IsiValue::Int(l), IsiValue::Int(r)) => Ok(IsiValue::Bool(l <= r)),
-                    (IsiValue::Float(l), IsiValue::Float(r)) => Ok(IsiValue::Bool(l <= r)),
-                    (l, r) => Err(format!("Unexpected: wrong values for operation {:?}: {:?} and {:?}", bexpr.0, l, r))
+            "abra" | "leialinha" | "fimdearquivo" | "feche" => {
+                use IsiValue::{File, String as Str};
+
+                let line = self.lines.line(call.get_span().start);
+
+                let mut args = vec![];
+                for arg in &call.args {
+                    args.push(self.visit_expr(arg)?);
                 }
+
+                let val = match (call.fname.name.as_str(), args.as_slice()) {
+                    ("abra", [Str(path), Str(mode)]) => self.files.open(path, mode).map(File),
+                    ("leialinha", [File(f)]) => self.files.read_line(*f).map(Str),
+                    ("fimdearquivo", [File(f)]) => self.files.eof(*f).map(IsiValue::Bool),
+                    ("feche", [File(f)]) => self.files.close(*f).map(|_| IsiValue::Unit),
+                    (name, args) => return Err(format!("Unexpected: {} called with {:?}", name, args).into()),
+                };
+
+                return val.map_err(|e| format!("{} at line {}", e, line).into());
             },
-            BinaryOp::Eq => {
-                match (lhs, rhs) {
-                    (IsiValue::Int(l), IsiValue::Int(r)) => Ok(IsiValue::Bool(l == r)),
-                    (IsiValue::Float(l), IsiValue::Float(r)) => Ok(IsiValue::Bool(l == r)),
-                    (l, r) => Err(format!("Unexpected: wrong values for operation {:?}: {:?} and {:?}", bexpr.0, l, r))
+            "afirme" => {
+                if self.options.strip_asserts {
+                    return Ok(IsiValue::Unit);
                 }
-            },
-            BinaryOp::Neq => {
-                match (lhs, rhs) {
-                    (IsiValue::Int(l), IsiValue::Int(r)) => Ok(IsiValue::Bool(l != r)),
-                    (IsiValue::Float(l), IsiValue::Float(r)) => Ok(IsiValue::Bool(l != r)),
-                    (l, r) => Err(format!("Unexpected: wrong values for operation {:?}: {:?} and {:?}", bexpr.0, l, r))
+
+                if let IsiValue::Bool(true) = self.visit_expr(&call.args[0])? {
+                    return Ok(IsiValue::Unit);
                 }
-            },
-            BinaryOp::And => {
-                match (lhs, rhs) {
-                    (IsiValue::Bool(l), IsiValue::Bool(r)) => Ok(IsiValue::Bool(l && r)),
-                    (l, r) => Err(format!("Unexpected: wrong values for operation {:?}: {:?} and {:?}", bexpr.0, l, r))
+
+                let mut msg = format!(
+                    "assertion failed at line {}",
+                    self.lines.line(call.get_span().start)
+                );
+
+                if let Some(arg) = call.args.get(1) {
+                    msg.push_str(&format!(": {}", self.visit_expr(arg)?));
                 }
+
+                msg.push_str(&self.var_report(&call.args[0]));
+
+                return Err(Interrupt::Violation(msg));
             },
-            BinaryOp::Or => {
-                match (lhs, rhs) {
-                    (IsiValue::Bool(l), IsiValue::Bool(r)) => Ok(IsiValue::Bool(l || r)),
-                    (l, r) => Err(format!("Unexpected: wrong values for operation {:?}: {:?} and {:?}", bexpr.0, l, r))
+            "escrevaf" => {
+                let mut args = vec![];
+                for arg in &call.args {
+                    args.push(self.visit_expr(arg)?);
                 }
-            },
-        }
-    }
 
-    fn visit_fn_call(&mut self, call: &isic_front::ast::FnCall) -> Self::Ret {
-        match call.fname.name.as_str() {
-            "escreva" => {
-                let val = self.visit_expr(&call.args[0])?;
+                let IsiValue::String(fmt) = &args[0] else {
+                    return Err(format!("Unexpected: non-String format {:?}", args[0]).into());
+                };
+
+                let pieces = isic_middle::format::parse(fmt)?;
 
-                writeln!(self.stdout, "{}", val).unwrap();
+                write!(self.stdout, "{}", format::render(&pieces, &args[1..])?).unwrap();
 
                 self.stdout.flush().unwrap();
             },
             "leia" => {
-                let mut input = String::new();
-                self.stdin.read_line(&mut input).unwrap();
-
-                if let Expr::Ident(ref id) = call.args[0] {
-                    let ty = self.sym_types.get(id).unwrap();
-
-                    let val = match ty {
-                        IsiType::Int => IsiValue::Int(input.trim().parse::<u64>().unwrap()),
-                        IsiType::Float => IsiValue::Float(input.trim().parse::<f32>().unwrap()),
-                        IsiType::String => IsiValue::String(input.trim().to_string()),
-                        IsiType::Bool => IsiValue::Bool(input.trim().parse::<bool>().unwrap()),
-                        IsiType::Unit => IsiValue::Unit,
+                let line = self.lines.line(call.get_span().start);
+
+                for arg in &call.args {
+                    let Expr::Ident(ref id) = arg else {
+                        unreachable!("Unexpected: leia on an expression")
+                    };
+                    let ty = *self.sym_types.get(id).unwrap();
+
+                    let val = loop {
+                        match self.input.read(ty) {
+                            Ok(Some(val)) => break val,
+                            Ok(None) if self.options.input == InputMode::Reprompt => {
+                                self.input.skip_line();
+                                writeln!(self.stdout, "invalid input at line {}, try again", line)
+                                    .unwrap();
+                            }
+                            Ok(None) => return Err(format!("invalid input at line {}", line).into()),
+                            Err(e) => return Err(format!("{} at line {}", e, line).into()),
+                        }
                     };
 
                     self.sym_table.insert(id.clone(), val);
                 }
             },
-            x => return Err(format!("Unknown function name {}", x)),
+            "inteiro" | "real" | "inteirogrande" | "caractere" | "texto" => {
+                let ty = match call.fname.name.as_str() {
+                    "inteiro" => IsiType::Int,
+                    "real" => IsiType::Real,
+                    "inteirogrande" => IsiType::BigInt,
+                    "caractere" => IsiType::Char,
+                    _ => IsiType::String,
+                };
+
+                return self.visit_expr(&call.args[0])?.convert(ty).map_err(|e| {
+                    format!("{} at line {}", e, self.lines.line(call.get_span().start)).into()
+                });
+            }
+            x if self.externs.contains(x) => {
+                let line = self.lines.line(call.get_span().start);
+
+                let mut args = vec![];
+                for arg in &call.args {
+                    args.push(self.visit_expr(arg)?);
+                }
+
+                let Some(f) = self.host_fns.get_mut(x) else {
+                    return Err(format!("function {} is not available in the interpreter at line {}", x, line).into());
+                };
+
+                return f(&args).map_err(|e| format!("{} at line {}", e, line).into());
+            }
+            x if builtins::exists(x) => {
+                let mut args = vec![];
+                for arg in &call.args {
+                    args.push(self.visit_expr(arg)?);
+                }
+
+                return crate::builtins::call(x, args, self.options.arith).map_err(|e| {
+                    format!("{} at line {}", e, self.lines.line(call.get_span().start)).into()
+                });
+            }
+            x => return Err(format!("Unknown function name {}", x).into()),
         }
 
         Ok(IsiValue::Unit)
@@ -265,21 +808,33 @@ impl<'a, R: BufRead, W: Write> IsiVisitor for IsiInterpreter<'a, R, W> {
     }
 
     fn visit_while_loop(&mut self, while_loop: &isic_front::ast::WhileLoop) -> Self::Ret {
+        let mut iteration = 0;
+        self.check_invariant(&while_loop.invariant, iteration)?;
+
         while let IsiValue::Bool(true) = self.visit_expr(&while_loop.cond)? {
             for stmt in &while_loop.body {
                 self.visit_statement(stmt)?;
             }
+
+            iteration += 1;
+            self.check_invariant(&while_loop.invariant, iteration)?;
         }
 
         Ok(IsiValue::Unit)
     }
 
     fn visit_do_while_loop(&mut self, do_while_loop: &isic_front::ast::DoWhileLoop) -> Self::Ret {
+        let mut iteration = 0;
+        self.check_invariant(&do_while_loop.invariant, iteration)?;
+
         loop {
             for stmt in &do_while_loop.body {
                 self.visit_statement(stmt)?;
             }
 
+            iteration += 1;
+            self.check_invariant(&do_while_loop.invariant, iteration)?;
+
             if !matches!(self.visit_expr(&do_while_loop.cond)?, IsiValue::Bool(true)) {
                 break;
             }
@@ -291,7 +846,32 @@ impl<'a, R: BufRead, W: Write> IsiVisitor for IsiInterpreter<'a, R, W> {
     fn visit_negation(&mut self, neg: &isic_front::ast::Negation) -> Self::Ret {
         match self.visit_expr(&neg.expr)? {
             IsiValue::Bool(b) => Ok(IsiValue::Bool(!b)),
-            v => Err(format!("Unexpected: tried to negate a non-Bool value {:?}", v)),
+            v => Err(format!("Unexpected: tried to negate a non-Bool value {:?}", v).into()),
+        }
+    }
+
+    fn visit_ternary(&mut self, tern: &isic_front::ast::Ternary) -> Self::Ret {
+        match self.visit_expr(&tern.cond)? {
+            IsiValue::Bool(true) => self.visit_expr(&tern.taken),
+            IsiValue::Bool(false) => self.visit_expr(&tern.not_taken),
+            v => Err(format!("Unexpected: non-Bool value {:?} as a condition", v).into()),
+        }
+    }
+
+    fn visit_index(&mut self, idx: &isic_front::ast::Index) -> Self::Ret {
+        let target = self.visit_expr(&idx.target)?;
+        let index = self.visit_expr(&idx.index)?;
+
+        match (target, index) {
+            // Os índices contam caracteres (code points), e não bytes.
+            (IsiValue::String(s), IsiValue::Int(i)) => usize::try_from(i)
+                .ok()
+                .and_then(|i| s.chars().nth(i))
+                .map(IsiValue::Char)
+                .ok_or_else(|| {
+                    format!("index out of bounds at line {}", self.lines.line(idx.get_span().start)).into()
+                }),
+            (s, i) => Err(format!("Unexpected: cannot index {:?} with {:?}", s, i).into()),
         }
     }
 
@@ -302,4 +882,169 @@ impl<'a, R: BufRead, W: Write> IsiVisitor for IsiInterpreter<'a, R, W> {
 
         Ok(IsiValue::Unit)
     }
+
+    fn visit_extern_fn(&mut self, ext: &isic_front::ast::ExternFn) -> Self::Ret {
+        self.externs.insert(ext.name.name.clone());
+
+        Ok(IsiValue::Unit)
+    }
+
+    fn visit_test(&mut self, _test: &isic_front::ast::TestBlock) -> Self::Ret {
+        // Os testes só são executados pelo exec_test.
+        Ok(IsiValue::Unit)
+    }
+
+    fn visit_contract(&mut self, contract: &isic_front::ast::Contract) -> Self::Ret {
+        // Os `garante` são verificados no fim do programa.
+        if contract.kind == ContractKind::Ensures || self.options.strip_asserts {
+            return Ok(IsiValue::Unit);
+        }
+
+        if let IsiValue::Bool(false) = self.visit_expr(&contract.cond)? {
+            let msg = format!("precondition violated at line {}", self.lines.line(contract.span.start));
+
+            return Err(Interrupt::Violation(format!("{}{}", msg, self.var_report(&contract.cond))));
+        }
+
+        Ok(IsiValue::Unit)
+    }
+
+    fn visit_try_catch(&mut self, try_catch: &isic_front::ast::TryCatch) -> Self::Ret {
+        let result = try_catch
+            .body
+            .iter()
+            .try_for_each(|stmt| self.visit_statement(stmt).map(|_| ()));
+
+        match result {
+            Ok(()) => {}
+            Err(Interrupt::Error(e)) => {
+                self.sym_table.insert(try_catch.error_var.clone(), IsiValue::String(e));
+
+                let result = try_catch
+                    .handler
+                    .iter()
+                    .try_for_each(|stmt| self.visit_statement(stmt).map(|_| ()));
+
+                // A variável do capture só existe dentro dele, como no type
+                // checker.
+                self.sym_table.remove(&try_catch.error_var);
+
+                result?;
+            }
+            Err(e) => return Err(e),
+        }
+
+        Ok(IsiValue::Unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Um valor qualquer do tipo `ty`, que não faz nenhuma operação falhar.
+    fn sample(ty: IsiType) -> IsiValue {
+        match ty {
+            IsiType::Int => IsiValue::Int(7),
+            IsiType::Float => IsiValue::Float(7.5),
+            IsiType::Real => IsiValue::Real(7.5),
+            IsiType::BigInt => IsiValue::BigInt(BigInt::from(7)),
+            IsiType::Char => IsiValue::Char('b'),
+            IsiType::String => IsiValue::String("b".to_string()),
+            IsiType::Bool => IsiValue::Bool(true),
+            ty => unreachable!("no operator takes a {:?}", ty),
+        }
+    }
+
+    /// Toda assinatura da tabela de operadores, que o type checker aceita,
+    /// é implementada pelo interpretador, com o tipo de resultado da tabela.
+    #[test]
+    fn every_operator_signature_is_implemented() {
+        for sig in ops::OPERATORS {
+            let result = apply_op(sig.op, sample(sig.lhs), sample(sig.rhs), ArithMode::Checked);
+
+            match result {
+                Ok(val) => assert_eq!(val.ty(), sig.result, "{:?}", sig),
+                Err(e) => panic!("{:?}: {}", sig, e),
+            }
+        }
+    }
+
+    /// O `div` arredonda o quociente para baixo em todos os tipos.
+    #[test]
+    fn int_div_floors_for_every_type() {
+        let cases = [
+            (IsiValue::Int(-7), IsiValue::Int(2), IsiValue::Int(-4)),
+            (IsiValue::Int(7), IsiValue::Int(-2), IsiValue::Int(-4)),
+            (IsiValue::Int(-8), IsiValue::Int(2), IsiValue::Int(-4)),
+            (IsiValue::Float(-7.0), IsiValue::Float(2.0), IsiValue::Float(-4.0)),
+            (IsiValue::Real(-7.0), IsiValue::Real(2.0), IsiValue::Real(-4.0)),
+            (
+                IsiValue::BigInt(BigInt::from(-7)),
+                IsiValue::BigInt(BigInt::from(2)),
+                IsiValue::BigInt(BigInt::from(-4)),
+            ),
+            (
+                IsiValue::BigInt(BigInt::from(7)),
+                IsiValue::BigInt(BigInt::from(-2)),
+                IsiValue::BigInt(BigInt::from(-4)),
+            ),
+        ];
+
+        for (l, r, expected) in cases {
+            let result = apply_op(BinaryOp::IntDiv, l.clone(), r.clone(), ArithMode::Checked);
+
+            assert_eq!(result.unwrap().to_string(), expected.to_string(), "{:?} div {:?}", l, r);
+        }
+
+        // O único overflow, i64::MIN div -1, continua sendo reportado.
+        let overflow = apply_op(BinaryOp::IntDiv, IsiValue::Int(i64::MIN), IsiValue::Int(-1), ArithMode::Checked);
+        assert_eq!(overflow.unwrap_err(), "integer overflow");
+    }
+
+    /// A variável do capture deixa de existir depois do handler, mesmo que
+    /// ele termine com um erro.
+    #[test]
+    fn capture_variable_is_removed_after_the_handler() {
+        let text = r#"programa
+    declare x: int.
+    x := 0.
+    tente {
+        x := 1 / x.
+    } capture (erro) {
+        escreva(erro).
+    }
+    tente {
+        tente {
+            x := 1 / x.
+        } capture (erro) {
+            x := x % 0.
+        }
+    } capture (outro) {
+        escreva(outro).
+    }
+fimprog.
+"#;
+        let ast = isic_front::parser::parse(text, &isic_front::dialect::PT).unwrap();
+        let mut typeck = isic_middle::typeck::TypeCk::new(&ast);
+        typeck.check().unwrap();
+        let lines = LineIndex::new(text);
+
+        let mut stdin = &b""[..];
+        let mut stdout = vec![];
+        let mut fs = crate::fs::MemFs::new();
+        let mut interpreter = IsiInterpreter::new(
+            &ast,
+            &typeck.coercions,
+            RuntimeOptions::default(),
+            &lines,
+            &mut stdin,
+            &mut stdout,
+            &mut fs,
+        );
+
+        assert_eq!(interpreter.exec(), Ok(0));
+        let names: Vec<_> = interpreter.sym_table.keys().map(|id| id.name.as_str()).collect();
+        assert_eq!(names, ["x"]);
+    }
 }
@@ -2,4 +2,9 @@
 //!
 //! Esta crate contém o interpretador da IsiLanguage incluído no isic.
 
+pub mod builtins;
+pub mod format;
+pub mod fs;
+pub mod input;
 pub mod interpreter;
+pub mod random;
@@ -0,0 +1,59 @@
+//! # isic_interpreter::random
+//!
+//! O gerador de números pseudoaleatórios usado por `semente` e `aleatorio`.
+//!
+//! O algoritmo é o SplitMix64, e o sorteio de um intervalo descarta os
+//! valores que favoreceriam alguns números. O runtime do C gerado pelo
+//! isic-back implementa exatamente o mesmo algoritmo, então uma mesma
+//! semente dá a mesma sequência no interpretador, no executável e no
+//! playground.
+
+/// O estado do gerador. Um programa que não chama `semente` se comporta
+/// como se tivesse chamado `semente(0)`.
+#[derive(Debug, Clone, Default)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn seed(&mut self, seed: i64) {
+        self.state = seed as u64;
+    }
+
+    /// O próximo número da sequência, com 64 bits.
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+
+        z ^ (z >> 31)
+    }
+
+    /// Sorteia um número entre `min` e `max`, inclusive.
+    pub fn range(&mut self, min: i64, max: i64) -> Result<i64, String> {
+        if min > max {
+            return Err("invalid range".to_string());
+        }
+
+        let size = (max as u64).wrapping_sub(min as u64).wrapping_add(1);
+
+        // O intervalo tem todos os valores de um int.
+        if size == 0 {
+            return Ok(self.next() as i64);
+        }
+
+        // Descarta os primeiros 2^64 % size valores, para que todos os
+        // restos sejam igualmente prováveis.
+        let threshold = size.wrapping_neg() % size;
+
+        loop {
+            let r = self.next();
+
+            if r >= threshold {
+                return Ok(min.wrapping_add((r % size) as i64));
+            }
+        }
+    }
+}
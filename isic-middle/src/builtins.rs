@@ -0,0 +1,131 @@
+//! # isic_middle::builtins
+//!
+//! Tabela de assinaturas das funções embutidas da IsiLang.
+//!
+//! Assim como a tabela de operadores, esta tabela é consultada pelo type
+//! checker, pelo interpretador e pelo emissor de C. As funções com
+//! comportamento especial (`escreva`, `escrevaarq`, `leia` e as funções de
+//! conversão) são tratadas à parte, já que aceitam argumentos de vários tipos.
+//!
+//! Uma função pode ter várias versões, com tipos de parâmetros diferentes
+//! (como `abs`, para `int` e para `real`).
+
+use crate::{ops::OpEval, IsiType};
+
+/// A assinatura de uma função embutida.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Builtin {
+    pub name: &'static str,
+    pub params: &'static [IsiType],
+    pub result: IsiType,
+    /// Como a função é implementada no C gerado. Só OpEval::Runtime e
+    /// OpEval::Checked fazem sentido aqui.
+    pub eval: OpEval,
+}
+
+macro_rules! builtin {
+    ($name:literal ($($param:ident),*) => $result:ident, $eval:expr) => {
+        Builtin {
+            name: $name,
+            params: &[$(IsiType::$param),*],
+            result: IsiType::$result,
+            eval: $eval,
+        }
+    };
+}
+
+/// As funções embutidas com comportamento especial, que não estão na tabela
+/// e são tratadas à parte pelo type checker e pelos backends.
+pub const SPECIAL: &[&str] = &[
+    "escreva",
+    "escrevasl",
+    "escrevaarq",
+    "escrevaf",
+    "leia",
+    "afirme",
+    "inteiro",
+    "real",
+    "inteirogrande",
+    "caractere",
+    "texto",
+];
+
+/// Todas as funções embutidas com assinatura fixa.
+pub const BUILTINS: &[Builtin] = &[
+    builtin!("tamanho"(String) => Int, OpEval::Runtime("isi_str_len")),
+    builtin!("maiusculas"(String) => String, OpEval::Runtime("isi_str_upper")),
+    builtin!("minusculas"(String) => String, OpEval::Runtime("isi_str_lower")),
+    builtin!("sub"(String, Int, Int) => String, OpEval::Checked("isi_str_sub")),
+    builtin!("busca"(String, String) => Int, OpEval::Runtime("isi_str_find")),
+    builtin!("substitui"(String, String, String) => String, OpEval::Runtime("isi_str_replace")),
+    builtin!("divide"(String, String, Int) => String, OpEval::Checked("isi_str_split")),
+    builtin!("raiz"(Real) => Real, OpEval::Runtime("sqrt")),
+    builtin!("potencia"(Real, Real) => Real, OpEval::Runtime("pow")),
+    builtin!("abs"(Int) => Int, OpEval::Checked("isi_absi")),
+    builtin!("abs"(Real) => Real, OpEval::Runtime("fabs")),
+    builtin!("sen"(Real) => Real, OpEval::Runtime("sin")),
+    builtin!("cos"(Real) => Real, OpEval::Runtime("cos")),
+    builtin!("arredonda"(Real) => Real, OpEval::Runtime("round")),
+    builtin!("piso"(Real) => Real, OpEval::Runtime("floor")),
+    builtin!("teto"(Real) => Real, OpEval::Runtime("ceil")),
+    builtin!("min"(Int, Int) => Int, OpEval::Runtime("isi_mini")),
+    builtin!("min"(Real, Real) => Real, OpEval::Runtime("fmin")),
+    builtin!("max"(Int, Int) => Int, OpEval::Runtime("isi_maxi")),
+    builtin!("max"(Real, Real) => Real, OpEval::Runtime("fmax")),
+    builtin!("semente"(Int) => Unit, OpEval::Runtime("isi_rng_seed")),
+    builtin!("aleatorio"(Int, Int) => Int, OpEval::Checked("isi_rng_int")),
+    builtin!("numargs"() => Int, OpEval::Runtime("isi_numargs")),
+    builtin!("argumentos"(Int) => String, OpEval::Checked("isi_arg")),
+    builtin!("saia"(Int) => Unit, OpEval::Runtime("isi_exit")),
+    builtin!("abra"(String, String) => File, OpEval::Checked("isi_file_open")),
+    builtin!("leialinha"(File) => String, OpEval::Checked("isi_file_readline")),
+    builtin!("fimdearquivo"(File) => Bool, OpEval::Checked("isi_file_eof")),
+    builtin!("feche"(File) => Unit, OpEval::Checked("isi_file_close")),
+];
+
+/// As funções embutidas que fazem mais do que calcular o resultado, como
+/// avançar o gerador de números aleatórios ou ler de um arquivo.
+const SIDE_EFFECTS: &[&str] = &[
+    "semente",
+    "aleatorio",
+    "saia",
+    "abra",
+    "leialinha",
+    "fimdearquivo",
+    "feche",
+];
+
+/// Retorna true se a função embutida `name` tem efeitos colaterais. A ordem
+/// em que duas chamadas a ela são feitas muda o resultado do programa.
+pub fn has_side_effects(name: &str) -> bool {
+    SIDE_EFFECTS.contains(&name)
+}
+
+/// Retorna true se existe alguma função embutida com o nome dado.
+pub fn exists(name: &str) -> bool {
+    BUILTINS.iter().any(|b| b.name == name)
+}
+
+/// Busca a versão da função `name` cujos parâmetros são exatamente dos
+/// tipos `args`.
+pub fn lookup(name: &str, args: &[IsiType]) -> Option<&'static Builtin> {
+    BUILTINS
+        .iter()
+        .find(|b| b.name == name && b.params == args)
+}
+
+/// Escolhe a versão da função `name` que deve ser chamada com argumentos
+/// dos tipos `args`: a versão exata, se existir, ou senão a primeira para
+/// a qual os argumentos podem ser promovidos.
+pub fn resolve(name: &str, args: &[IsiType]) -> Option<&'static Builtin> {
+    lookup(name, args).or_else(|| {
+        BUILTINS.iter().find(|b| {
+            b.name == name
+                && b.params.len() == args.len()
+                && b.params
+                    .iter()
+                    .zip(args)
+                    .all(|(param, arg)| arg == param || arg.widens_to(*param))
+        })
+    })
+}
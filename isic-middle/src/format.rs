@@ -0,0 +1,156 @@
+//! # isic_middle::format
+//!
+//! Parser das strings de formato da função `escrevaf`.
+//!
+//! O formato é um subconjunto do `printf` do C: `%[-0][largura][.precisão]c`,
+//! onde `c` é uma das conversões abaixo, e `%%` escreve um `%`.
+//!
+//! | Conversão | Argumento                          |
+//! |-----------|------------------------------------|
+//! | `d`       | `int`                              |
+//! | `f`, `e`  | `int`, `float` ou `real`           |
+//! | `s`       | qualquer valor, escrito como texto |
+//! | `c`       | `caractere`                        |
+//!
+//! A precisão só é aceita em `f` e `e`, e a flag `0` só em `d`, `f` e `e`.
+
+use std::{iter::Peekable, str::Chars};
+
+use crate::IsiType;
+
+/// Uma especificação de conversão, como `%-8.2f`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FmtSpec {
+    /// Alinha à esquerda (flag `-`).
+    pub left: bool,
+    /// Preenche com zeros ao invés de espaços (flag `0`).
+    pub zero: bool,
+    pub width: Option<usize>,
+    pub precision: Option<usize>,
+    pub conv: char,
+}
+
+impl FmtSpec {
+    /// O tipo para o qual o argumento desta conversão deve ser convertido
+    /// antes de ser formatado, se um valor do tipo `ty` for aceito.
+    pub fn accepts(&self, ty: IsiType) -> Option<IsiType> {
+        match (self.conv, ty) {
+            ('d', IsiType::Int) => Some(IsiType::Int),
+            ('f' | 'e', IsiType::Int | IsiType::Float | IsiType::Real) => Some(IsiType::Real),
+            ('s', IsiType::File | IsiType::Unit) => None,
+            ('s', _) => Some(IsiType::String),
+            ('c', IsiType::Char) => Some(IsiType::Char),
+            _ => None,
+        }
+    }
+
+    /// A especificação equivalente no `printf` do C, sem a conversão.
+    pub fn c_flags(&self) -> String {
+        let mut s = String::from("%");
+
+        if self.left {
+            s.push('-');
+        }
+        if self.zero {
+            s.push('0');
+        }
+        if let Some(w) = self.width {
+            s.push_str(&w.to_string());
+        }
+        if let Some(p) = self.precision {
+            s.push_str(&format!(".{}", p));
+        }
+
+        s
+    }
+}
+
+/// Um pedaço de uma string de formato.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FmtPiece {
+    Text(String),
+    Spec(FmtSpec),
+}
+
+/// Lê um número decimal, se houver um.
+fn number(chars: &mut Peekable<Chars>) -> Option<usize> {
+    let mut n = String::new();
+
+    while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+        n.push(*d);
+        chars.next();
+    }
+
+    n.parse().ok()
+}
+
+/// Quebra a string de formato `fmt` em pedaços. Retorna Err com a
+/// descrição do problema se o formato for inválido.
+pub fn parse(fmt: &str) -> Result<Vec<FmtPiece>, String> {
+    let mut pieces = vec![];
+    let mut text = String::new();
+    let mut chars = fmt.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            text.push('%');
+            continue;
+        }
+
+        let mut spec = FmtSpec {
+            left: false,
+            zero: false,
+            width: None,
+            precision: None,
+            conv: ' ',
+        };
+
+        loop {
+            match chars.peek() {
+                Some('-') => spec.left = true,
+                Some('0') => spec.zero = true,
+                _ => break,
+            }
+            chars.next();
+        }
+
+        spec.width = number(&mut chars);
+
+        if chars.peek() == Some(&'.') {
+            chars.next();
+            spec.precision = Some(number(&mut chars).unwrap_or(0));
+        }
+
+        spec.conv = match chars.next() {
+            Some(c @ ('d' | 'f' | 'e' | 's' | 'c')) => c,
+            Some(c) => return Err(format!("Unknown format conversion %{}", c)),
+            None => return Err("Incomplete format conversion at the end".to_string()),
+        };
+
+        if spec.precision.is_some() && !matches!(spec.conv, 'f' | 'e') {
+            return Err(format!("Precision is not allowed in %{}", spec.conv));
+        }
+
+        if spec.zero && !matches!(spec.conv, 'd' | 'f' | 'e') {
+            return Err(format!("Flag 0 is not allowed in %{}", spec.conv));
+        }
+
+        if !text.is_empty() {
+            pieces.push(FmtPiece::Text(std::mem::take(&mut text)));
+        }
+
+        pieces.push(FmtPiece::Spec(spec));
+    }
+
+    if !text.is_empty() {
+        pieces.push(FmtPiece::Text(text));
+    }
+
+    Ok(pieces)
+}
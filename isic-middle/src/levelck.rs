@@ -0,0 +1,483 @@
+//! # isic_middle::levelck
+//!
+//! Os níveis da linguagem, que limitam os recursos que um programa pode usar
+//! em cada etapa de um curso, e o verificador que aplica um nível a uma AST.
+//!
+//! Cada nível é definido pelos tipos de nó, operadores e funções embutidas
+//! que ele libera, além de tudo o que os níveis anteriores já liberaram.
+//! Sem um nível, toda a linguagem está disponível.
+
+use isic_front::{
+    ast::{BinaryOp, ContractKind, Expr, Ident, IsiProgram},
+    span::Span,
+    visitor::IsiVisitor,
+};
+
+use crate::builtins;
+use crate::CheckError;
+
+/// Um recurso da linguagem que não é um operador nem uma função.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Feature {
+    Decl,
+    Assignment,
+    Conditional,
+    Negation,
+    WhileLoop,
+    DoWhileLoop,
+    Ternary,
+    Index,
+    Invariant,
+    Requires,
+    Ensures,
+    TryCatch,
+    ExternFn,
+}
+
+impl Feature {
+    /// Como o recurso aparece no código fonte.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Feature::Decl => "declare",
+            Feature::Assignment => ":=",
+            Feature::Conditional => "se",
+            Feature::Negation => "!",
+            Feature::WhileLoop => "enquanto",
+            Feature::DoWhileLoop => "faca",
+            Feature::Ternary => "?:",
+            Feature::Index => "[]",
+            Feature::Invariant => "invariante",
+            Feature::Requires => "requer",
+            Feature::Ensures => "garante",
+            Feature::TryCatch => "tente",
+            Feature::ExternFn => "externo",
+        }
+    }
+}
+
+/// O que um nível libera, além do que os níveis anteriores já liberaram.
+pub struct LevelProfile {
+    pub features: &'static [Feature],
+    pub ops: &'static [BinaryOp],
+    pub functions: &'static [&'static str],
+}
+
+/// Os níveis, em ordem. O nível 1 é `LEVELS[0]`.
+///
+/// As funções externas (`externo funcao`) não são liberadas por nenhum
+/// nível, e só podem ser usadas sem um nível.
+pub const LEVELS: &[LevelProfile] = &[
+    // 1: declarações, entrada e saída, e condicionais.
+    LevelProfile {
+        features: &[Feature::Decl, Feature::Assignment, Feature::Conditional, Feature::Negation],
+        ops: &[
+            BinaryOp::Add,
+            BinaryOp::Sub,
+            BinaryOp::Mul,
+            BinaryOp::Div,
+            BinaryOp::Mod,
+            BinaryOp::Gt,
+            BinaryOp::Lt,
+            BinaryOp::Geq,
+            BinaryOp::Leq,
+            BinaryOp::Eq,
+            BinaryOp::Neq,
+            BinaryOp::And,
+            BinaryOp::Or,
+        ],
+        functions: &["escreva", "escrevasl", "leia"],
+    },
+    // 2: laços, conversões e as funções de strings e matemáticas.
+    LevelProfile {
+        features: &[Feature::WhileLoop, Feature::DoWhileLoop, Feature::Ternary, Feature::Index],
+        ops: &[BinaryOp::Pow, BinaryOp::IntDiv],
+        functions: &[
+            "escrevaf",
+            "inteiro",
+            "real",
+            "inteirogrande",
+            "caractere",
+            "texto",
+            "tamanho",
+            "maiusculas",
+            "minusculas",
+            "sub",
+            "busca",
+            "substitui",
+            "divide",
+            "raiz",
+            "potencia",
+            "abs",
+            "sen",
+            "cos",
+            "arredonda",
+            "piso",
+            "teto",
+            "min",
+            "max",
+        ],
+    },
+    // 3: asserções e contratos, tratamento de erros, operações com bits,
+    // arquivos, números aleatórios e argumentos.
+    LevelProfile {
+        features: &[
+            Feature::Invariant,
+            Feature::Requires,
+            Feature::Ensures,
+            Feature::TryCatch,
+        ],
+        ops: &[
+            BinaryOp::BitAnd,
+            BinaryOp::BitOr,
+            BinaryOp::BitXor,
+            BinaryOp::Shl,
+            BinaryOp::Shr,
+        ],
+        functions: &[
+            "afirme",
+            "semente",
+            "aleatorio",
+            "abra",
+            "leialinha",
+            "escrevaarq",
+            "fimdearquivo",
+            "feche",
+            "numargs",
+            "argumentos",
+            "saia",
+        ],
+    },
+];
+
+/// Um nível da linguagem, escolhido com a flag `--nivel`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Level(usize);
+
+impl Level {
+    /// Os perfis que este nível inclui: o dele e os dos níveis anteriores.
+    fn profiles(&self) -> &'static [LevelProfile] {
+        &LEVELS[..self.0]
+    }
+
+    pub fn allows_feature(&self, feature: Feature) -> bool {
+        self.profiles().iter().any(|p| p.features.contains(&feature))
+    }
+
+    pub fn allows_op(&self, op: BinaryOp) -> bool {
+        self.profiles().iter().any(|p| p.ops.contains(&op))
+    }
+
+    pub fn allows_function(&self, name: &str) -> bool {
+        self.profiles().iter().any(|p| p.functions.contains(&name))
+    }
+}
+
+impl std::str::FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse() {
+            Ok(n) if (1..=LEVELS.len()).contains(&n) => Ok(Level(n)),
+            _ => Err(format!("Unknown level {} (expected 1 to {})", s, LEVELS.len())),
+        }
+    }
+}
+
+/// O verificador de nível do isic. Reporta os recursos que o programa usa
+/// e que não estão disponíveis no nível escolhido, inclusive dentro dos
+/// blocos de teste.
+pub struct LevelCk<'a> {
+    /// Referencia ao programa a ser verificado.
+    program: &'a IsiProgram,
+    /// O nível escolhido.
+    level: Level,
+    /// Os recursos não disponíveis encontrados.
+    errors: Vec<CheckError>,
+}
+
+impl<'a> LevelCk<'a> {
+    /// Cria um novo verificador de nível.
+    pub fn new(program: &'a IsiProgram, level: Level) -> LevelCk<'a> {
+        LevelCk {
+            program,
+            level,
+            errors: vec![],
+        }
+    }
+
+    /// Faz a verificação da AST. Se o programa só usa recursos do nível,
+    /// retorna Ok(()). Senão, retorna um erro para cada uso de um recurso
+    /// não disponível.
+    pub fn check(&mut self) -> Result<(), Vec<CheckError>> {
+        self.visit_program(self.program);
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    fn unavailable(&mut self, what: String, span: Span) {
+        self.errors.push(CheckError {
+            span,
+            desc: format!("Feature {} is not available at this level", what),
+        });
+    }
+
+    fn check_feature(&mut self, feature: Feature, span: Span) {
+        if !self.level.allows_feature(feature) {
+            self.unavailable(feature.name().to_string(), span);
+        }
+    }
+}
+
+impl<'a> IsiVisitor for LevelCk<'a> {
+    type Ret = ();
+
+    fn visit_int_literal(&mut self, _lit: &isic_front::ast::IntLiteral) -> Self::Ret {}
+
+    fn visit_float_literal(&mut self, _lit: &isic_front::ast::FloatLiteral) -> Self::Ret {}
+
+    fn visit_string_literal(&mut self, _lit: &isic_front::ast::StringLiteral) -> Self::Ret {}
+
+    fn visit_char_literal(&mut self, _lit: &isic_front::ast::CharLiteral) -> Self::Ret {}
+
+    fn visit_ident(&mut self, _id: &Ident) -> Self::Ret {}
+
+    fn visit_decl(&mut self, decl: &isic_front::ast::VarDecl) -> Self::Ret {
+        self.check_feature(Feature::Decl, decl.span);
+    }
+
+    fn visit_multi_decl(&mut self, decls: &isic_front::ast::MultiVarDecl) -> Self::Ret {
+        // Um erro só para a declaração inteira.
+        if let Some(decl) = decls.0.first() {
+            self.visit_decl(decl);
+        }
+    }
+
+    fn visit_extern_fn(&mut self, ext: &isic_front::ast::ExternFn) -> Self::Ret {
+        self.check_feature(Feature::ExternFn, ext.span);
+    }
+
+    fn visit_bin_expr(&mut self, bexpr: &isic_front::ast::BinExpr) -> Self::Ret {
+        if !self.level.allows_op(bexpr.0) {
+            self.unavailable(format!("operator {}", bexpr.0.symbol()), bexpr.get_span());
+        }
+
+        self.visit_expr(&bexpr.1);
+        self.visit_expr(&bexpr.2);
+    }
+
+    fn visit_fn_call(&mut self, call: &isic_front::ast::FnCall) -> Self::Ret {
+        let name = call.fname.name.as_str();
+
+        // Funções desconhecidas são reportadas pelo type checker.
+        let known = builtins::SPECIAL.contains(&name) || builtins::exists(name);
+
+        if known && !self.level.allows_function(name) {
+            self.unavailable(format!("function {}", name), call.fname.span);
+        }
+
+        for arg in &call.args {
+            self.visit_expr(arg);
+        }
+    }
+
+    fn visit_negation(&mut self, neg: &isic_front::ast::Negation) -> Self::Ret {
+        self.check_feature(Feature::Negation, neg.get_span());
+        self.visit_expr(&neg.expr);
+    }
+
+    fn visit_ternary(&mut self, tern: &isic_front::ast::Ternary) -> Self::Ret {
+        self.check_feature(Feature::Ternary, tern.get_span());
+        self.visit_expr(&tern.cond);
+        self.visit_expr(&tern.taken);
+        self.visit_expr(&tern.not_taken);
+    }
+
+    fn visit_index(&mut self, idx: &isic_front::ast::Index) -> Self::Ret {
+        self.check_feature(Feature::Index, idx.get_span());
+        self.visit_expr(&idx.target);
+        self.visit_expr(&idx.index);
+    }
+
+    fn visit_assignment(&mut self, assignment: &isic_front::ast::Assignment) -> Self::Ret {
+        self.check_feature(Feature::Assignment, assignment.get_span());
+        self.visit_expr(&assignment.val);
+    }
+
+    fn visit_conditional(&mut self, conditional: &isic_front::ast::Conditional) -> Self::Ret {
+        self.check_feature(Feature::Conditional, conditional.cond.get_span());
+        self.visit_expr(&conditional.cond);
+
+        for stmt in conditional.taken.iter().chain(&conditional.not_taken) {
+            self.visit_statement(stmt);
+        }
+    }
+
+    fn visit_while_loop(&mut self, while_loop: &isic_front::ast::WhileLoop) -> Self::Ret {
+        self.check_feature(Feature::WhileLoop, while_loop.cond.get_span());
+        self.visit_expr(&while_loop.cond);
+
+        if let Some(inv) = &while_loop.invariant {
+            self.check_feature(Feature::Invariant, inv.get_span());
+            self.visit_expr(inv);
+        }
+
+        for stmt in &while_loop.body {
+            self.visit_statement(stmt);
+        }
+    }
+
+    fn visit_do_while_loop(&mut self, do_while_loop: &isic_front::ast::DoWhileLoop) -> Self::Ret {
+        self.check_feature(Feature::DoWhileLoop, do_while_loop.cond.get_span());
+        self.visit_expr(&do_while_loop.cond);
+
+        if let Some(inv) = &do_while_loop.invariant {
+            self.check_feature(Feature::Invariant, inv.get_span());
+            self.visit_expr(inv);
+        }
+
+        for stmt in &do_while_loop.body {
+            self.visit_statement(stmt);
+        }
+    }
+
+    fn visit_try_catch(&mut self, try_catch: &isic_front::ast::TryCatch) -> Self::Ret {
+        self.check_feature(Feature::TryCatch, try_catch.span);
+
+        for stmt in try_catch.body.iter().chain(&try_catch.handler) {
+            self.visit_statement(stmt);
+        }
+    }
+
+    fn visit_test(&mut self, test: &isic_front::ast::TestBlock) -> Self::Ret {
+        for stmt in &test.body {
+            self.visit_statement(stmt);
+        }
+    }
+
+    fn visit_contract(&mut self, contract: &isic_front::ast::Contract) -> Self::Ret {
+        let feature = match contract.kind {
+            ContractKind::Requires => Feature::Requires,
+            ContractKind::Ensures => Feature::Ensures,
+        };
+
+        self.check_feature(feature, contract.span);
+        self.visit_expr(&contract.cond);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) -> Self::Ret {
+        match expr {
+            Expr::BinExpr(bexpr) => self.visit_bin_expr(bexpr),
+            Expr::FnCall(call) => self.visit_fn_call(call),
+            Expr::Negation(neg) => self.visit_negation(neg),
+            Expr::Ternary(tern) => self.visit_ternary(tern),
+            Expr::Index(idx) => self.visit_index(idx),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verifica `program` no nível `level`, e retorna as mensagens de erro
+    /// com o trecho do código apontado por cada uma.
+    fn errors(program: &str, level: &str) -> Vec<(String, String)> {
+        let ast = isic_front::parser::parse(program, &isic_front::dialect::PT).unwrap();
+
+        let errors = LevelCk::new(&ast, level.parse().unwrap()).check().unwrap_err();
+
+        errors
+            .into_iter()
+            .map(|e| (e.desc, program[e.span.start..e.span.end].to_string()))
+            .collect()
+    }
+
+    fn expected(errors: &[(&str, &str)]) -> Vec<(String, String)> {
+        errors
+            .iter()
+            .map(|(what, code)| (format!("Feature {} is not available at this level", what), code.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn level_1_rejects_loops_div_and_string_functions() {
+        let program = r#"programa
+    declare x: int.
+    x := 7 div 2.
+    enquanto (x > 0) {
+        x := x - 1.
+    }
+    escreva(tamanho("abc")).
+    teste "dentro do teste" {
+        escreva(x ^ 2, texto(x)).
+    }
+fimprog.
+"#;
+
+        assert_eq!(
+            errors(program, "1"),
+            expected(&[
+                ("operator div", "7 div 2"),
+                ("enquanto", "x > 0"),
+                ("function tamanho", "tamanho"),
+                ("operator ^", "x ^ 2"),
+                ("function texto", "texto"),
+            ])
+        );
+    }
+
+    #[test]
+    fn level_2_rejects_error_handling_bit_operators_and_random_numbers() {
+        let program = r#"programa
+    declare x: int.
+    x := 6 xor 3.
+    tente {
+        escreva(x).
+    } capture (erro) {
+        escreva(erro).
+    }
+    escreva(aleatorio(1, 6)).
+    teste "dentro do teste" {
+        afirme(x << 1 == 10, "x").
+    }
+fimprog.
+"#;
+
+        let tente = &program[program.find("tente").unwrap()..program.find("    escreva(aleatorio").unwrap() - 1];
+
+        assert_eq!(
+            errors(program, "2"),
+            expected(&[
+                ("operator xor", "6 xor 3"),
+                ("tente", tente),
+                ("function aleatorio", "aleatorio"),
+                ("function afirme", "afirme"),
+                ("operator <<", "x << 1"),
+            ])
+        );
+    }
+
+    /// O nível 3 libera todos os operadores e funções embutidas, e só as
+    /// funções externas ficam de fora.
+    #[test]
+    fn level_3_rejects_extern_functions() {
+        let program = r#"programa
+    externo funcao agora(): real de "tempo.h".
+    escreva(agora() ^ 2,0, aleatorio(1, 6) xor 1).
+    teste "dentro do teste" {
+        afirme(agora() > 0,0, "agora").
+    }
+fimprog.
+"#;
+
+        assert_eq!(
+            errors(program, "3"),
+            expected(&[("externo", r#"externo funcao agora(): real de "tempo.h"."#)])
+        );
+    }
+}
@@ -4,6 +4,10 @@
 
 use isic_front::span::Span;
 
+pub mod builtins;
+pub mod format;
+pub mod levelck;
+pub mod ops;
 pub mod typeck;
 pub mod usageck;
 
@@ -11,16 +15,173 @@ pub mod usageck;
 pub enum IsiType {
     Int,
     Float,
+    Real,
+    BigInt,
+    Char,
     String,
+    File,
     Bool,
     Unit,
 }
 
+impl IsiType {
+    /// O tipo com o nome dado numa declaração, se existir.
+    pub fn from_name(name: &str) -> Option<IsiType> {
+        match name {
+            "int" => Some(IsiType::Int),
+            "float" => Some(IsiType::Float),
+            "real" => Some(IsiType::Real),
+            "inteirogrande" => Some(IsiType::BigInt),
+            "caractere" => Some(IsiType::Char),
+            "string" => Some(IsiType::String),
+            "arquivo" => Some(IsiType::File),
+            _ => None,
+        }
+    }
+
+    /// Retorna true se um valor deste tipo pode ser convertido
+    /// implicitamente para `other` sem perda de informação.
+    ///
+    /// Os tipos `int`, `float` e `real` formam uma torre, e `int` também
+    /// pode ser promovido para `inteirogrande`. Não há conversão implícita
+    /// entre `inteirogrande` e os tipos de ponto flutuante. Fora da torre,
+    /// um `caractere` pode ser usado onde se espera uma `string`.
+    pub fn widens_to(&self, other: IsiType) -> bool {
+        matches!(
+            (self, other),
+            (IsiType::Int, IsiType::Float)
+                | (IsiType::Int, IsiType::Real)
+                | (IsiType::Int, IsiType::BigInt)
+                | (IsiType::Float, IsiType::Real)
+                | (IsiType::Char, IsiType::String)
+        )
+    }
+
+    /// Retorna true se converter um valor deste tipo para `other`
+    /// pode perder informação.
+    pub fn narrows_to(&self, other: IsiType) -> bool {
+        matches!(
+            (self, other),
+            (IsiType::Float, IsiType::Int)
+                | (IsiType::Real, IsiType::Int)
+                | (IsiType::Real, IsiType::Float)
+                | (IsiType::BigInt, IsiType::Int)
+        )
+    }
+
+    /// Retorna true se um valor deste tipo pode ser convertido
+    /// explicitamente para `other`, com as funções de conversão.
+    pub fn converts_to(&self, other: IsiType) -> bool {
+        match other {
+            IsiType::Int => matches!(
+                self,
+                IsiType::Int
+                    | IsiType::Float
+                    | IsiType::Real
+                    | IsiType::BigInt
+                    | IsiType::Char
+                    | IsiType::String
+            ),
+            IsiType::Real => matches!(
+                self,
+                IsiType::Int | IsiType::Float | IsiType::Real | IsiType::BigInt | IsiType::String
+            ),
+            IsiType::BigInt => matches!(self, IsiType::Int | IsiType::BigInt | IsiType::String),
+            IsiType::Char => matches!(self, IsiType::Int | IsiType::Char),
+            IsiType::String => !matches!(self, IsiType::File | IsiType::Unit),
+            _ => false,
+        }
+    }
+
+    /// O menor tipo para o qual os dois tipos podem ser promovidos,
+    /// ou None se não existir.
+    pub fn promote(lhs: IsiType, rhs: IsiType) -> Option<IsiType> {
+        if lhs == rhs || rhs.widens_to(lhs) {
+            Some(lhs)
+        } else if lhs.widens_to(rhs) {
+            Some(rhs)
+        } else {
+            None
+        }
+    }
+}
+
+/// Como os backends tratam overflow nas operações com `int`.
+///
+/// Divisão por zero sempre interrompe o programa, em qualquer modo.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ArithMode {
+    /// Overflow interrompe o programa com um erro indicando a linha.
+    #[default]
+    Checked,
+    /// Overflow dá a volta, em complemento de dois com 64 bits.
+    Wrapping,
+}
+
+impl std::str::FromStr for ArithMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "checked" => Ok(ArithMode::Checked),
+            "wrapping" => Ok(ArithMode::Wrapping),
+            x => Err(format!("Unknown arithmetic mode {} (expected checked or wrapping)", x)),
+        }
+    }
+}
+
+/// O que `leia` faz quando a entrada não é um valor válido para a variável
+/// sendo lida.
+///
+/// O fim da entrada sempre interrompe o programa, em qualquer modo.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum InputMode {
+    /// Entrada inválida interrompe o programa com um erro indicando a linha.
+    #[default]
+    Error,
+    /// O resto da linha é descartado, e o valor é lido novamente.
+    Reprompt,
+}
+
+impl std::str::FromStr for InputMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(InputMode::Error),
+            "reprompt" => Ok(InputMode::Reprompt),
+            x => Err(format!("Unknown input mode {} (expected error or reprompt)", x)),
+        }
+    }
+}
+
+/// As opções que mudam o comportamento dos programas em tempo de execução,
+/// e que os dois backends respeitam da mesma forma.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct RuntimeOptions {
+    /// Como tratar overflow nas operações com `int`.
+    pub arith: ArithMode,
+    /// O que fazer quando `leia` recebe uma entrada inválida.
+    pub input: InputMode,
+    /// Se true, os `afirme`, os invariantes de laços e as pré e
+    /// pós-condições são removidos do programa, e suas condições nem chegam
+    /// a ser avaliadas.
+    pub strip_asserts: bool,
+}
+
 pub struct SymbolInfo {
     pub ty: IsiType,
     pub declaration: Span,
 }
 
+/// A assinatura de uma função escrita em C, declarada com `externo funcao`.
+pub struct ExternInfo {
+    pub params: Vec<IsiType>,
+    /// O tipo de retorno, ou IsiType::Unit se a função não retorna nada.
+    pub result: IsiType,
+    pub declaration: Span,
+}
+
 #[derive(Debug)]
 pub struct CheckError {
     pub span: Span,
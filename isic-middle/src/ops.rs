@@ -0,0 +1,152 @@
+//! # isic_middle::ops
+//!
+//! Tabela de assinaturas dos operadores binários da IsiLang.
+//!
+//! Esta tabela é a única fonte de verdade sobre quais combinações de
+//! operador e tipos são válidas, qual o tipo do resultado, e como cada
+//! operação deve ser avaliada. O type checker, o interpretador e o emissor
+//! de C consultam esta tabela, de modo que não podem discordar entre si.
+//!
+//! A tabela só contém operandos de tipos iguais. Operandos de tipos
+//! diferentes são antes promovidos pelo type checker (vide
+//! `IsiType::promote`).
+
+use isic_front::ast::BinaryOp;
+
+use crate::IsiType;
+
+/// Como uma operação deve ser avaliada.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OpEval {
+    /// Avalia os dois operandos e aplica a operação nativa do tipo.
+    Native,
+    /// Avalia o operando da esquerda, e só avalia o da direita caso ele
+    /// ainda possa mudar o resultado.
+    ShortCircuit,
+    /// Avalia os dois operandos e chama a função de runtime com o nome
+    /// dado. Usado para operações sem equivalente nativo em C.
+    Runtime(&'static str),
+    /// Como OpEval::Runtime, mas a operação pode falhar (overflow ou
+    /// divisão por zero), então a função também recebe a linha do código
+    /// fonte, para reportar o erro.
+    Checked(&'static str),
+}
+
+/// A assinatura de um operador para um par de tipos de operandos.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct OpSignature {
+    pub op: BinaryOp,
+    pub lhs: IsiType,
+    pub rhs: IsiType,
+    pub result: IsiType,
+    pub eval: OpEval,
+}
+
+macro_rules! sig {
+    ($op:ident, $lhs:ident, $rhs:ident => $result:ident) => {
+        sig!($op, $lhs, $rhs => $result, OpEval::Native)
+    };
+    ($op:ident, $lhs:ident, $rhs:ident => $result:ident, $eval:expr) => {
+        OpSignature {
+            op: BinaryOp::$op,
+            lhs: IsiType::$lhs,
+            rhs: IsiType::$rhs,
+            result: IsiType::$result,
+            eval: $eval,
+        }
+    };
+}
+
+/// Todas as combinações válidas de operador e tipos.
+pub const OPERATORS: &[OpSignature] = &[
+    sig!(Add, Int, Int => Int, OpEval::Checked("isi_add")),
+    sig!(Add, Float, Float => Float),
+    sig!(Add, Real, Real => Real),
+    sig!(Add, BigInt, BigInt => BigInt, OpEval::Runtime("isi_big_add")),
+    sig!(Add, String, String => String, OpEval::Runtime("isi_concat")),
+    sig!(Sub, Int, Int => Int, OpEval::Checked("isi_sub")),
+    sig!(Sub, Float, Float => Float),
+    sig!(Sub, Real, Real => Real),
+    sig!(Sub, BigInt, BigInt => BigInt, OpEval::Runtime("isi_big_sub")),
+    sig!(Mul, Int, Int => Int, OpEval::Checked("isi_mul")),
+    sig!(Mul, Float, Float => Float),
+    sig!(Mul, Real, Real => Real),
+    sig!(Mul, BigInt, BigInt => BigInt, OpEval::Runtime("isi_big_mul")),
+    sig!(Div, Int, Int => Int, OpEval::Checked("isi_div")),
+    sig!(Div, Float, Float => Float),
+    sig!(Div, Real, Real => Real),
+    sig!(Div, BigInt, BigInt => BigInt, OpEval::Checked("isi_big_div")),
+    sig!(IntDiv, Int, Int => Int, OpEval::Checked("isi_floordivi")),
+    sig!(IntDiv, Float, Float => Float, OpEval::Runtime("isi_floordivf")),
+    sig!(IntDiv, Real, Real => Real, OpEval::Runtime("isi_floordiv")),
+    sig!(IntDiv, BigInt, BigInt => BigInt, OpEval::Checked("isi_big_floordiv")),
+    sig!(Mod, Int, Int => Int, OpEval::Checked("isi_mod")),
+    sig!(Mod, BigInt, BigInt => BigInt, OpEval::Checked("isi_big_mod")),
+    sig!(Pow, Int, Int => Int, OpEval::Checked("isi_powi")),
+    sig!(Pow, Float, Float => Float, OpEval::Runtime("powf")),
+    sig!(Pow, Real, Real => Real, OpEval::Runtime("pow")),
+    sig!(Pow, BigInt, BigInt => BigInt, OpEval::Checked("isi_big_pow")),
+    sig!(BitAnd, Int, Int => Int),
+    sig!(BitOr, Int, Int => Int),
+    sig!(BitXor, Int, Int => Int),
+    sig!(Shl, Int, Int => Int, OpEval::Checked("isi_shl")),
+    sig!(Shr, Int, Int => Int, OpEval::Checked("isi_shr")),
+    sig!(Gt, Int, Int => Bool),
+    sig!(Gt, Float, Float => Bool),
+    sig!(Gt, Real, Real => Bool),
+    sig!(Gt, BigInt, BigInt => Bool, OpEval::Runtime("isi_big_gt")),
+    sig!(Gt, Char, Char => Bool),
+    sig!(Gt, String, String => Bool, OpEval::Runtime("isi_strgt")),
+    sig!(Lt, Int, Int => Bool),
+    sig!(Lt, Float, Float => Bool),
+    sig!(Lt, Real, Real => Bool),
+    sig!(Lt, BigInt, BigInt => Bool, OpEval::Runtime("isi_big_lt")),
+    sig!(Lt, Char, Char => Bool),
+    sig!(Lt, String, String => Bool, OpEval::Runtime("isi_strlt")),
+    sig!(Geq, Int, Int => Bool),
+    sig!(Geq, Float, Float => Bool),
+    sig!(Geq, Real, Real => Bool),
+    sig!(Geq, BigInt, BigInt => Bool, OpEval::Runtime("isi_big_geq")),
+    sig!(Geq, Char, Char => Bool),
+    sig!(Geq, String, String => Bool, OpEval::Runtime("isi_strgeq")),
+    sig!(Leq, Int, Int => Bool),
+    sig!(Leq, Float, Float => Bool),
+    sig!(Leq, Real, Real => Bool),
+    sig!(Leq, BigInt, BigInt => Bool, OpEval::Runtime("isi_big_leq")),
+    sig!(Leq, Char, Char => Bool),
+    sig!(Leq, String, String => Bool, OpEval::Runtime("isi_strleq")),
+    sig!(Eq, Int, Int => Bool),
+    sig!(Eq, Float, Float => Bool),
+    sig!(Eq, Real, Real => Bool),
+    sig!(Eq, BigInt, BigInt => Bool, OpEval::Runtime("isi_big_eq")),
+    sig!(Eq, Char, Char => Bool),
+    sig!(Eq, String, String => Bool, OpEval::Runtime("isi_streq")),
+    sig!(Eq, Bool, Bool => Bool),
+    sig!(Neq, Int, Int => Bool),
+    sig!(Neq, Float, Float => Bool),
+    sig!(Neq, Real, Real => Bool),
+    sig!(Neq, BigInt, BigInt => Bool, OpEval::Runtime("isi_big_neq")),
+    sig!(Neq, Char, Char => Bool),
+    sig!(Neq, String, String => Bool, OpEval::Runtime("isi_strneq")),
+    sig!(Neq, Bool, Bool => Bool),
+    sig!(And, Bool, Bool => Bool, OpEval::ShortCircuit),
+    sig!(Or, Bool, Bool => Bool, OpEval::ShortCircuit),
+];
+
+/// Busca a assinatura do operador `op` aplicado a operandos dos tipos
+/// `lhs` e `rhs`. Retorna None se a combinação não for válida.
+pub fn lookup(op: BinaryOp, lhs: IsiType, rhs: IsiType) -> Option<&'static OpSignature> {
+    OPERATORS
+        .iter()
+        .find(|sig| sig.op == op && sig.lhs == lhs && sig.rhs == rhs)
+}
+
+/// Retorna a estratégia de avaliação do operador `op` quando o operando
+/// da esquerda tem tipo `lhs`. Útil para quem precisa decidir se avalia o
+/// operando da direita antes de conhecer o tipo dele.
+pub fn eval_strategy(op: BinaryOp, lhs: IsiType) -> Option<OpEval> {
+    OPERATORS
+        .iter()
+        .find(|sig| sig.op == op && sig.lhs == lhs)
+        .map(|sig| sig.eval)
+}
@@ -1,11 +1,16 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use isic_front::{
-    ast::{BinaryOp, Ident, IsiProgram},
+    ast::{Expr, Ident, IsiProgram},
+    span::Span,
     visitor::IsiVisitor,
 };
 
+use crate::builtins;
+use crate::format::{self, FmtPiece};
+use crate::ops;
 use crate::CheckError;
+use crate::ExternInfo;
 use crate::IsiType;
 use crate::SymbolInfo;
 
@@ -16,6 +21,20 @@ pub struct TypeCk<'a> {
     program: &'a IsiProgram,
     /// Tabela de tipos das variáveis do programa.
     pub sym_table: HashMap<Ident, SymbolInfo>,
+    /// Assinaturas das funções externas declaradas pelo programa.
+    pub externs: HashMap<String, ExternInfo>,
+    /// Tipo de cada expressão do programa, indexado pelo span dela.
+    pub expr_types: HashMap<Span, IsiType>,
+    /// Conversões implícitas inseridas pelo type checker: o tipo para o
+    /// qual a expressão com o span dado deve ser convertida antes de ser
+    /// usada. O interpretador e o emissor aplicam exatamente estas
+    /// conversões.
+    pub coercions: HashMap<Span, IsiType>,
+    /// Os nomes dos testes já declarados.
+    tests: HashSet<String>,
+    /// Avisos gerados durante a checagem, como conversões que podem
+    /// perder informação.
+    pub warnings: Vec<CheckError>,
 }
 
 impl<'a> TypeCk<'a> {
@@ -24,6 +43,11 @@ impl<'a> TypeCk<'a> {
         TypeCk {
             program,
             sym_table: HashMap::new(),
+            externs: HashMap::new(),
+            expr_types: HashMap::new(),
+            coercions: HashMap::new(),
+            tests: HashSet::new(),
+            warnings: vec![],
         }
     }
 
@@ -44,6 +68,44 @@ impl<'a> TypeCk<'a> {
             Err(ret)
         }
     }
+
+    /// O tipo de um parâmetro ou do retorno de uma função externa. Só são
+    /// aceitos os tipos que têm um equivalente direto no C.
+    fn extern_type(ty: &Ident) -> Result<IsiType, CheckError> {
+        match IsiType::from_name(&ty.name) {
+            Some(IsiType::BigInt | IsiType::File) => Err(CheckError {
+                span: ty.span,
+                desc: format!("Type {} cannot be used in an external function", ty.name),
+            }),
+            Some(ty) => Ok(ty),
+            None => Err(CheckError {
+                span: ty.span,
+                desc: format!("Unknown type {}", ty.name),
+            }),
+        }
+    }
+
+    /// Verifica o invariante de um laço, que deve ser um Bool.
+    fn check_invariant(&mut self, inv: &Expr) -> Result<(), CheckError> {
+        let ty = self.visit_expr(inv)?;
+
+        if ty != IsiType::Bool {
+            return Err(CheckError {
+                span: inv.get_span(),
+                desc: format!("The type of invariants must be Bool, found {:?} instead", ty),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Registra que `expr`, de tipo `from`, deve ser convertida
+    /// implicitamente para o tipo `to`.
+    fn coerce(&mut self, expr: &Expr, from: IsiType, to: IsiType) {
+        if from != to {
+            self.coercions.insert(expr.get_span(), to);
+        }
+    }
 }
 
 impl<'a> IsiVisitor for TypeCk<'a> {
@@ -61,9 +123,13 @@ impl<'a> IsiVisitor for TypeCk<'a> {
         Ok(IsiType::String)
     }
 
+    fn visit_char_literal(&mut self, _lit: &isic_front::ast::CharLiteral) -> Self::Ret {
+        Ok(IsiType::Char)
+    }
+
     fn visit_ident(&mut self, id: &Ident) -> Self::Ret {
         match self.sym_table.get(id) {
-            Some(ref sym) => Ok(sym.ty),
+            Some(sym) => Ok(sym.ty),
             None => Err(CheckError {
                 span: id.span,
                 desc: format!("Undefined variable {}", id.name),
@@ -81,15 +147,18 @@ impl<'a> IsiVisitor for TypeCk<'a> {
             });
         }
 
-        let ty = match decl.var_type.name.as_str() {
-            "int" => Ok(IsiType::Int),
-            "float" => Ok(IsiType::Float),
-            "string" => Ok(IsiType::String),
-            t @ _ => Err(CheckError {
+        // Um nome é de uma variável ou de uma função externa, nunca dos dois.
+        if self.externs.contains_key(&decl.var_name.name) {
+            return Err(CheckError {
                 span,
-                desc: format!("Unknown type {}", t),
-            }),
-        }?;
+                desc: format!("{} is already declared as a function", decl.var_name.name),
+            });
+        }
+
+        let ty = IsiType::from_name(&decl.var_type.name).ok_or_else(|| CheckError {
+            span,
+            desc: format!("Unknown type {}", decl.var_type.name),
+        })?;
 
         self.sym_table.insert(
             decl.var_name.clone(),
@@ -102,80 +171,305 @@ impl<'a> IsiVisitor for TypeCk<'a> {
         Ok(ty)
     }
 
+    fn visit_expr(&mut self, expr: &Expr) -> Self::Ret {
+        let ty = match expr {
+            Expr::Ident(ident) => self.visit_ident(ident),
+            Expr::ImmInt(imm) => self.visit_int_literal(imm),
+            Expr::ImmFloat(imm) => self.visit_float_literal(imm),
+            Expr::ImmString(imm) => self.visit_string_literal(imm),
+            Expr::ImmChar(imm) => self.visit_char_literal(imm),
+            Expr::BinExpr(bexp) => self.visit_bin_expr(bexp),
+            Expr::FnCall(call) => self.visit_fn_call(call),
+            Expr::Negation(neg) => self.visit_negation(neg),
+            Expr::Ternary(tern) => self.visit_ternary(tern),
+            Expr::Index(idx) => self.visit_index(idx),
+        }?;
+
+        // Guardamos o tipo para que o emissor consiga escolher
+        // a construção C correta para cada expressão.
+        self.expr_types.insert(expr.get_span(), ty);
+
+        Ok(ty)
+    }
+
     fn visit_bin_expr(&mut self, bexpr: &isic_front::ast::BinExpr) -> Self::Ret {
         let span = bexpr.get_span();
 
         let left = self.visit_expr(&bexpr.1)?;
         let right = self.visit_expr(&bexpr.2)?;
 
+        if let Some(sig) = ops::lookup(bexpr.0, left, right) {
+            return Ok(sig.result);
+        }
+
+        // Operandos numéricos de tipos diferentes são promovidos para o
+        // maior dos dois tipos.
+        if let Some(ty) = IsiType::promote(left, right) {
+            if let Some(sig) = ops::lookup(bexpr.0, ty, ty) {
+                self.coerce(&bexpr.1, left, ty);
+                self.coerce(&bexpr.2, right, ty);
+
+                return Ok(sig.result);
+            }
+        }
+
         if left != right {
-            return Err(CheckError {
+            Err(CheckError {
                 span,
                 desc: format!(
                     "Mismatched types for binary expression: left is {:?}, right is {:?}",
                     left, right
                 ),
-            });
+            })
+        } else {
+            Err(CheckError {
+                span,
+                desc: format!(
+                    "Operator {:?} is not defined between terms of type {:?}",
+                    bexpr.0, left
+                ),
+            })
         }
+    }
 
-        match bexpr.0 {
-            BinaryOp::Add => Ok(left),
-            BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div => match left {
-                IsiType::String | IsiType::Unit => Err(CheckError {
-                    span,
-                    desc: format!(
-                        "Operator {:?} is not defined between terms of type {:?}",
-                        bexpr.0, left
-                    ),
-                }),
-                _ => Ok(left),
-            },
-            BinaryOp::Mod => match left {
-                IsiType::Int => Ok(IsiType::Int),
-                _ => Err(CheckError {
-                    span,
-                    desc: format!(
-                        "Operator % is only defined between terms of type Int"
-                    ),
-                }),
+    fn visit_fn_call(&mut self, call: &isic_front::ast::FnCall) -> Self::Ret {
+        let span = call.get_span();
+
+        let mut args = vec![];
+        for arg in &call.args {
+            args.push(self.visit_expr(arg)?);
+        }
+
+        let name = call.fname.name.as_str();
+
+        match name {
+            "escreva" | "escrevasl" | "escrevaarq" => {
+                // escrevaarq recebe o arquivo antes dos valores.
+                let values = if name == "escrevaarq" {
+                    if args.first() != Some(&IsiType::File) {
+                        return Err(CheckError {
+                            span,
+                            desc: "The first argument of escrevaarq must be a File".to_string(),
+                        });
+                    }
+
+                    1
+                } else {
+                    0
+                };
+
+                for (arg, ty) in call.args.iter().zip(&args).skip(values) {
+                    if matches!(ty, IsiType::File | IsiType::Unit) {
+                        return Err(CheckError {
+                            span: arg.get_span(),
+                            desc: format!("Cannot write a value of type {:?}", ty),
+                        });
+                    }
+                }
+
+                Ok(IsiType::Unit)
+            }
+            "leia" => {
+                if call.args.is_empty() {
+                    return Err(CheckError {
+                        span,
+                        desc: "leia takes at least 1 argument, found 0".to_string(),
+                    });
+                }
+
+                // Só é possível ler para variáveis.
+                for (arg, ty) in call.args.iter().zip(&args) {
+                    let readable = !matches!(ty, IsiType::File | IsiType::Bool | IsiType::Unit);
+                    if !matches!(arg, Expr::Ident(_)) || !readable {
+                        return Err(CheckError {
+                            span: arg.get_span(),
+                            desc: "The arguments of leia must be variables".to_string(),
+                        });
+                    }
+                }
+
+                Ok(IsiType::Unit)
             }
-            BinaryOp::Gt
-            | BinaryOp::Lt
-            | BinaryOp::Geq
-            | BinaryOp::Leq
-            | BinaryOp::Eq
-            | BinaryOp::Neq => Ok(IsiType::Bool),
-            BinaryOp::And | BinaryOp::Or => match left {
-                IsiType::Bool => Ok(IsiType::Bool),
-                _ => Err(CheckError {
+            "afirme" => {
+                if !(1..=2).contains(&args.len()) {
+                    return Err(CheckError {
+                        span,
+                        desc: format!("afirme takes 1 or 2 arguments, found {}", args.len()),
+                    });
+                }
+
+                if args[0] != IsiType::Bool {
+                    return Err(CheckError {
+                        span: call.args[0].get_span(),
+                        desc: format!("The condition of afirme must be a Bool, found {:?}", args[0]),
+                    });
+                }
+
+                // A mensagem é opcional.
+                if let Some(ty) = args.get(1) {
+                    if *ty != IsiType::String && !ty.widens_to(IsiType::String) {
+                        return Err(CheckError {
+                            span: call.args[1].get_span(),
+                            desc: format!("The message of afirme must be a String, found {:?}", ty),
+                        });
+                    }
+
+                    self.coerce(&call.args[1], *ty, IsiType::String);
+                }
+
+                Ok(IsiType::Unit)
+            }
+            "escrevaf" => {
+                // O formato precisa ser um literal, para que possa ser
+                // validado aqui.
+                let Some(Expr::ImmString(fmt)) = call.args.first() else {
+                    return Err(CheckError {
+                        span,
+                        desc: "The first argument of escrevaf must be a string literal".to_string(),
+                    });
+                };
+
+                let pieces = format::parse(&fmt.0).map_err(|desc| CheckError { span: fmt.1, desc })?;
+
+                let specs: Vec<_> = pieces
+                    .iter()
+                    .filter_map(|piece| match piece {
+                        FmtPiece::Spec(spec) => Some(spec),
+                        FmtPiece::Text(_) => None,
+                    })
+                    .collect();
+
+                if specs.len() != args.len() - 1 {
+                    return Err(CheckError {
+                        span,
+                        desc: format!(
+                            "Format string expects {} argument(s), found {}",
+                            specs.len(),
+                            args.len() - 1
+                        ),
+                    });
+                }
+
+                for ((arg, ty), spec) in call.args[1..].iter().zip(&args[1..]).zip(specs) {
+                    let target = spec.accepts(*ty).ok_or_else(|| CheckError {
+                        span: arg.get_span(),
+                        desc: format!("Format %{} does not accept a {:?}", spec.conv, ty),
+                    })?;
+
+                    self.coerce(arg, *ty, target);
+                }
+
+                Ok(IsiType::Unit)
+            }
+            "inteiro" | "real" | "inteirogrande" | "caractere" | "texto" => {
+                let target = match name {
+                    "inteiro" => IsiType::Int,
+                    "real" => IsiType::Real,
+                    "inteirogrande" => IsiType::BigInt,
+                    "caractere" => IsiType::Char,
+                    _ => IsiType::String,
+                };
+
+                if args.len() != 1 {
+                    return Err(CheckError {
+                        span,
+                        desc: format!("Function {} takes 1 argument, found {}", name, args.len()),
+                    });
+                }
+
+                if !args[0].converts_to(target) {
+                    return Err(CheckError {
+                        span,
+                        desc: format!("Cannot convert a {:?} to {:?}", args[0], target),
+                    });
+                }
+
+                Ok(target)
+            }
+            _ if self.externs.contains_key(name) => {
+                let ext = &self.externs[name];
+
+                if args.len() != ext.params.len() {
+                    return Err(CheckError {
+                        span,
+                        desc: format!(
+                            "Function {} takes {} argument(s), found {}",
+                            name,
+                            ext.params.len(),
+                            args.len()
+                        ),
+                    });
+                }
+
+                let params = ext.params.clone();
+                let result = ext.result;
+
+                for ((arg, ty), param) in call.args.iter().zip(&args).zip(params) {
+                    if *ty != param && !ty.widens_to(param) {
+                        return Err(CheckError {
+                            span: arg.get_span(),
+                            desc: format!("Expected a {:?}, found {:?}", param, ty),
+                        });
+                    }
+
+                    self.coerce(arg, *ty, param);
+                }
+
+                Ok(result)
+            }
+            _ if !builtins::exists(name) => Err(CheckError {
+                span: call.fname.span,
+                desc: format!("Unknown function {}", name),
+            }),
+            _ => {
+                let builtin = builtins::resolve(name, &args).ok_or_else(|| CheckError {
                     span,
-                    desc: format!("Operator {:?} is only defined between terms of type Bool", bexpr.0),
-                }),
+                    desc: format!("No version of function {} takes arguments {:?}", name, args),
+                })?;
+
+                for (arg, (ty, param)) in call.args.iter().zip(args.iter().zip(builtin.params)) {
+                    self.coerce(arg, *ty, *param);
+                }
+
+                Ok(builtin.result)
             }
         }
     }
 
-    fn visit_fn_call(&mut self, _call: &isic_front::ast::FnCall) -> Self::Ret {
-        Ok(IsiType::Unit)
-    }
-
     fn visit_assignment(&mut self, assignment: &isic_front::ast::Assignment) -> Self::Ret {
         let span = assignment.get_span();
 
         let left = self.visit_ident(&assignment.ident)?;
         let right = self.visit_expr(&assignment.val)?;
 
-        if left != right {
-            return Err(CheckError {
+        if left == right || right.widens_to(left) {
+            self.coerce(&assignment.val, right, left);
+
+            return Ok(left);
+        }
+
+        if right.narrows_to(left) {
+            // Permitido, mas pode perder informação. Avisamos o usuário.
+            self.coerce(&assignment.val, right, left);
+
+            self.warnings.push(CheckError {
                 span,
                 desc: format!(
-                    "Mismatched types for assignment: tried to assign a {:?} to a {:?}",
+                    "Implicit narrowing conversion from {:?} to {:?} may lose information",
                     right, left
                 ),
             });
+
+            return Ok(left);
         }
 
-        Ok(left)
+        Err(CheckError {
+            span,
+            desc: format!(
+                "Mismatched types for assignment: tried to assign a {:?} to a {:?}",
+                right, left
+            ),
+        })
     }
 
     fn visit_conditional(&mut self, conditional: &isic_front::ast::Conditional) -> Self::Ret {
@@ -217,6 +511,10 @@ impl<'a> IsiVisitor for TypeCk<'a> {
             });
         }
 
+        if let Some(inv) = &while_loop.invariant {
+            self.check_invariant(inv)?;
+        }
+
         for stmt in &while_loop.body {
             self.visit_statement(stmt)?;
         }
@@ -238,6 +536,10 @@ impl<'a> IsiVisitor for TypeCk<'a> {
             });
         }
 
+        if let Some(inv) = &do_while_loop.invariant {
+            self.check_invariant(inv)?;
+        }
+
         for stmt in &do_while_loop.body {
             self.visit_statement(stmt)?;
         }
@@ -261,6 +563,64 @@ impl<'a> IsiVisitor for TypeCk<'a> {
         }
     }
 
+    fn visit_ternary(&mut self, tern: &isic_front::ast::Ternary) -> Self::Ret {
+        let cond_span = tern.cond.get_span();
+        let cond_ty = self.visit_expr(&tern.cond)?;
+
+        if cond_ty != IsiType::Bool {
+            return Err(CheckError {
+                span: cond_span,
+                desc: format!(
+                    "The type of conditionals must be Bool, found {:?} instead",
+                    cond_ty
+                ),
+            });
+        }
+
+        let taken = self.visit_expr(&tern.taken)?;
+        let not_taken = self.visit_expr(&tern.not_taken)?;
+
+        if let Some(ty) = IsiType::promote(taken, not_taken) {
+            self.coerce(&tern.taken, taken, ty);
+            self.coerce(&tern.not_taken, not_taken, ty);
+
+            return Ok(ty);
+        }
+
+        if taken != not_taken {
+            return Err(CheckError {
+                span: tern.get_span(),
+                desc: format!(
+                    "Mismatched types for conditional expression: first branch is {:?}, second is {:?}",
+                    taken, not_taken
+                ),
+            });
+        }
+
+        Ok(taken)
+    }
+
+    fn visit_index(&mut self, idx: &isic_front::ast::Index) -> Self::Ret {
+        let target = self.visit_expr(&idx.target)?;
+        let index = self.visit_expr(&idx.index)?;
+
+        if target != IsiType::String {
+            return Err(CheckError {
+                span: idx.target.get_span(),
+                desc: format!("Only strings can be indexed, found {:?} instead", target),
+            });
+        }
+
+        if index != IsiType::Int {
+            return Err(CheckError {
+                span: idx.index.get_span(),
+                desc: format!("String indices must be of type Int, found {:?} instead", index),
+            });
+        }
+
+        Ok(IsiType::Char)
+    }
+
     fn visit_multi_decl(&mut self, decls: &isic_front::ast::MultiVarDecl) -> Self::Ret {
         for decl in &decls.0 {
             self.visit_decl(decl)?;
@@ -268,4 +628,129 @@ impl<'a> IsiVisitor for TypeCk<'a> {
 
         Ok(IsiType::Unit)
     }
+
+    fn visit_extern_fn(&mut self, ext: &isic_front::ast::ExternFn) -> Self::Ret {
+        let name = &ext.name.name;
+
+        if builtins::SPECIAL.contains(&name.as_str()) || builtins::exists(name) {
+            return Err(CheckError {
+                span: ext.name.span,
+                desc: format!("{} is a builtin function", name),
+            });
+        }
+
+        // O nome é usado como está no C gerado.
+        if !name.is_ascii() {
+            return Err(CheckError {
+                span: ext.name.span,
+                desc: format!("External function {} must have an ASCII name", name),
+            });
+        }
+
+        if self.externs.contains_key(name) {
+            return Err(CheckError {
+                span: ext.name.span,
+                desc: format!("Redeclaration of function {}", name),
+            });
+        }
+
+        if self.sym_table.contains_key(&ext.name) {
+            return Err(CheckError {
+                span: ext.name.span,
+                desc: format!("{} is already declared as a variable", name),
+            });
+        }
+
+        let mut params = vec![];
+        for param in &ext.params {
+            params.push(Self::extern_type(&param.ty)?);
+        }
+
+        let result = match &ext.ret {
+            Some(ty) => Self::extern_type(ty)?,
+            None => IsiType::Unit,
+        };
+
+        self.externs.insert(
+            name.clone(),
+            ExternInfo {
+                params,
+                result,
+                declaration: ext.span,
+            },
+        );
+
+        Ok(IsiType::Unit)
+    }
+
+    fn visit_test(&mut self, test: &isic_front::ast::TestBlock) -> Self::Ret {
+        if !self.tests.insert(test.name.0.clone()) {
+            return Err(CheckError {
+                span: test.name.1,
+                desc: format!("Redeclaration of test {}", test.name.0),
+            });
+        }
+
+        for stmt in &test.body {
+            self.visit_statement(stmt)?;
+        }
+
+        Ok(IsiType::Unit)
+    }
+
+    fn visit_contract(&mut self, contract: &isic_front::ast::Contract) -> Self::Ret {
+        let ty = self.visit_expr(&contract.cond)?;
+
+        if ty != IsiType::Bool {
+            return Err(CheckError {
+                span: contract.cond.get_span(),
+                desc: format!("The type of conditions must be Bool, found {:?} instead", ty),
+            });
+        }
+
+        Ok(IsiType::Unit)
+    }
+
+    fn visit_try_catch(&mut self, try_catch: &isic_front::ast::TryCatch) -> Self::Ret {
+        for stmt in &try_catch.body {
+            self.visit_statement(stmt)?;
+        }
+
+        // A variável do capture é declarada por ele, como uma string, e só
+        // existe dentro do capture. Assim, vários tente podem usar o mesmo
+        // nome.
+        let var = &try_catch.error_var;
+
+        if self.sym_table.contains_key(var) {
+            return Err(CheckError {
+                span: var.span,
+                desc: format!("Redeclaration of variable {}", var.name),
+            });
+        }
+
+        if self.externs.contains_key(&var.name) {
+            return Err(CheckError {
+                span: var.span,
+                desc: format!("{} is already declared as a function", var.name),
+            });
+        }
+
+        self.sym_table.insert(
+            var.clone(),
+            SymbolInfo {
+                ty: IsiType::String,
+                declaration: var.span,
+            },
+        );
+
+        let result = try_catch
+            .handler
+            .iter()
+            .try_for_each(|stmt| self.visit_statement(stmt).map(|_| ()));
+
+        self.sym_table.remove(var);
+        result?;
+
+        Ok(IsiType::Unit)
+    }
 }
@@ -41,7 +41,7 @@ impl<'a> UsageCk<'a> {
     /// Faz a checagem de uso da AST. Retorna um vetor com os problemas
     /// encontrados, possivelmente vazio.
     pub fn check(&mut self) -> Vec<CheckError> {
-        self.visit_program(&self.program);
+        self.visit_program(self.program);
 
         let mut ret = vec![];
 
@@ -90,6 +90,10 @@ impl<'a> IsiVisitor for UsageCk<'a> {
         // do nothing
     }
 
+    fn visit_char_literal(&mut self, _lit: &isic_front::ast::CharLiteral) -> Self::Ret {
+        // do nothing
+    }
+
     fn visit_ident(&mut self, _id: &Ident) -> Self::Ret {
         // do nothing
     }
@@ -118,6 +122,8 @@ impl<'a> IsiVisitor for UsageCk<'a> {
             Expr::Ident(ident) => self.mark_usage(ident, span),
             Expr::BinExpr(bexp) => self.visit_bin_expr(bexp),
             Expr::FnCall(call) => self.visit_fn_call(call),
+            Expr::Ternary(tern) => self.visit_ternary(tern),
+            Expr::Index(idx) => self.visit_index(idx),
             _ => {}
         }
     }
@@ -133,8 +139,10 @@ impl<'a> IsiVisitor for UsageCk<'a> {
             // nos args.
             let span = call.get_span();
 
-            if let Expr::Ident(ref id) = call.args[0] {
-                self.mark_assigment(id, span);
+            for arg in &call.args {
+                if let Expr::Ident(ref id) = arg {
+                    self.mark_assigment(id, span);
+                }
             }
 
             return;
@@ -168,6 +176,10 @@ impl<'a> IsiVisitor for UsageCk<'a> {
     fn visit_while_loop(&mut self, while_loop: &isic_front::ast::WhileLoop) -> Self::Ret {
         self.visit_expr(&while_loop.cond);
 
+        if let Some(inv) = &while_loop.invariant {
+            self.visit_expr(inv);
+        }
+
         for stmt in &while_loop.body {
             self.visit_statement(stmt);
         }
@@ -176,6 +188,10 @@ impl<'a> IsiVisitor for UsageCk<'a> {
     fn visit_do_while_loop(&mut self, do_while_loop: &isic_front::ast::DoWhileLoop) -> Self::Ret {
         self.visit_expr(&do_while_loop.cond);
 
+        if let Some(inv) = &do_while_loop.invariant {
+            self.visit_expr(inv);
+        }
+
         for stmt in &do_while_loop.body {
             self.visit_statement(stmt);
         }
@@ -185,9 +201,51 @@ impl<'a> IsiVisitor for UsageCk<'a> {
         self.visit_expr(&neg.expr);
     }
 
+    fn visit_ternary(&mut self, tern: &isic_front::ast::Ternary) -> Self::Ret {
+        self.visit_expr(&tern.cond);
+        self.visit_expr(&tern.taken);
+        self.visit_expr(&tern.not_taken);
+    }
+
+    fn visit_index(&mut self, idx: &isic_front::ast::Index) -> Self::Ret {
+        self.visit_expr(&idx.target);
+        self.visit_expr(&idx.index);
+    }
+
     fn visit_multi_decl(&mut self, decls: &isic_front::ast::MultiVarDecl) -> Self::Ret {
         for decl in &decls.0 {
             self.visit_decl(decl);
         }
     }
+
+    fn visit_extern_fn(&mut self, _ext: &isic_front::ast::ExternFn) -> Self::Ret {}
+
+    fn visit_test(&mut self, test: &isic_front::ast::TestBlock) -> Self::Ret {
+        for stmt in &test.body {
+            self.visit_statement(stmt);
+        }
+    }
+
+    fn visit_contract(&mut self, contract: &isic_front::ast::Contract) -> Self::Ret {
+        self.visit_expr(&contract.cond);
+    }
+
+    fn visit_try_catch(&mut self, try_catch: &isic_front::ast::TryCatch) -> Self::Ret {
+        for stmt in &try_catch.body {
+            self.visit_statement(stmt);
+        }
+
+        // A variável do capture é declarada e escrita por ele.
+        let var = &try_catch.error_var;
+
+        self.sym_table.entry(var.clone()).or_insert(UsageInfo {
+            declared: var.span,
+            assignments: vec![var.span],
+            uses: vec![],
+        });
+
+        for stmt in &try_catch.handler {
+            self.visit_statement(stmt);
+        }
+    }
 }
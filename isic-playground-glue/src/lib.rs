@@ -3,13 +3,21 @@
 //! Esta crate é apenas uma cola para exportar as facilidades
 //! do compilador isic para programas JavaScript por meio de WebAssembly.
 
+use std::cell::RefCell;
 use std::io::Cursor;
 
-use ariadne::{Source, Report, Label};
+use ariadne::{Color, Label, Report, ReportKind, Source};
 use isic_back::cemitter::CEmitter;
-use isic_interpreter::interpreter::IsiInterpreter;
-use isic_front::parser::isilang_parser;
-use isic_middle::{typeck::TypeCk, usageck::UsageCk};
+use isic_interpreter::{fs::MemFs, interpreter::IsiInterpreter};
+use isic_front::ast::IsiProgram;
+use isic_front::cst;
+use isic_front::dialect::{self, Dialect};
+use isic_front::incremental::{self, TextEdit};
+use isic_front::lower;
+use isic_front::parser::{self, SyntaxError};
+use isic_front::source::SourceText;
+use isic_front::span::{LineIndex, Span};
+use isic_middle::{levelck::{Level, LevelCk}, typeck::TypeCk, usageck::UsageCk, RuntimeOptions};
 use serde::Serialize;
 use wasm_bindgen::{JsValue, prelude::wasm_bindgen};
 
@@ -27,84 +35,235 @@ pub struct InterpretResult {
     warns: Vec<String>,
 }
 
+/// Formata um erro ou warning do compilador como o isic-cli mostra, com o
+/// trecho `span` do código fonte marcado e a mensagem `msg`.
+fn report(kind: ReportKind, title: &str, span: Span, msg: &str, source: &SourceText) -> String {
+    let color = match kind {
+        ReportKind::Error => Color::Red,
+        _ => Color::Yellow,
+    };
+
+    let mut report_bytes: Vec<u8> = vec![];
+
+    Report::build(kind, (), source.char_offset(span.start))
+        .with_message(title)
+        .with_label(
+            Label::new(((), source.char_range(span)))
+                .with_color(color)
+                .with_message(msg),
+        )
+        .finish()
+        .write(Source::from(source.text.as_str()), &mut report_bytes)
+        .unwrap();
+
+    String::from_utf8(report_bytes).unwrap()
+}
+
 #[wasm_bindgen]
 pub fn init() {
     std::panic::set_hook(Box::new(console_error_panic_hook::hook));
 }
 
+/// O código do editor, e a CST dele, guardados entre as chamadas para que
+/// cada edição refaça o parse só do bloco editado.
+struct Document {
+    text: String,
+    dialect: &'static Dialect,
+    parse: cst::Parse,
+}
+
+impl Document {
+    fn apply(&mut self, edit: &TextEdit) {
+        self.parse = incremental::reparse(&self.parse, edit, self.dialect);
+        edit.apply(&mut self.text);
+    }
+}
+
+thread_local! {
+    /// O documento da última chamada. O playground tem um único editor,
+    /// então basta guardar um.
+    static DOCUMENT: RefCell<Option<Document>> = const { RefCell::new(None) };
+}
+
+/// A posição em bytes de `text` que corresponde à posição `offset` contada
+/// em unidades UTF-16, como nas strings do JavaScript.
+fn byte_offset(text: &str, offset: usize) -> usize {
+    let mut units = 0;
+
+    for (i, c) in text.char_indices() {
+        if units >= offset {
+            return i;
+        }
+
+        units += c.len_utf16();
+    }
+
+    text.len()
+}
+
+/// A edição que transforma `old` em `new`: o trecho entre o maior prefixo
+/// e o maior sufixo que os dois têm em comum.
+fn diff(old: &str, new: &str) -> TextEdit {
+    let prefix = old
+        .char_indices()
+        .zip(new.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or(old.len().min(new.len()), |((i, _), _)| i);
+
+    let suffix: usize = old[prefix..]
+        .chars()
+        .rev()
+        .zip(new[prefix..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(c, _)| c.len_utf8())
+        .sum();
+
+    TextEdit {
+        span: Span {
+            start: prefix,
+            end: old.len() - suffix,
+        },
+        text: new[prefix..new.len() - suffix].to_string(),
+    }
+}
+
+/// Avisa que o trecho de `start` a `end` do código, em unidades UTF-16, foi
+/// substituído por `text` no editor. O parse é refeito na hora, só no bloco
+/// editado, e a próxima chamada a `compile_to_c` ou `run_interpreter` com o
+/// código editado reaproveita a árvore.
+#[wasm_bindgen]
+pub fn edit_source(start: usize, end: usize, text: &str) {
+    DOCUMENT.with(|document| {
+        if let Some(doc) = document.borrow_mut().as_mut() {
+            let edit = TextEdit {
+                span: Span {
+                    start: byte_offset(&doc.text, start),
+                    end: byte_offset(&doc.text, end.max(start)),
+                },
+                text: text.to_string(),
+            };
+
+            doc.apply(&edit);
+        }
+    });
+}
+
+/// Faz o parse de `text` como `parser::parse`, mas a partir da CST da
+/// chamada anterior: o código é comparado com o anterior, e só o bloco
+/// editado é refeito com `incremental::reparse`.
+fn parse(text: &str, dialect: &'static Dialect) -> Result<IsiProgram, SyntaxError> {
+    DOCUMENT.with(|document| {
+        let mut document = document.borrow_mut();
+
+        let reusable = matches!(&*document, Some(doc) if std::ptr::eq(doc.dialect, dialect));
+        if !reusable {
+            *document = Some(Document {
+                text: text.to_string(),
+                dialect,
+                parse: parser::parse_cst(text, dialect),
+            });
+        }
+
+        let doc = document.as_mut().unwrap();
+        if doc.text != text {
+            let edit = diff(&doc.text, text);
+            doc.apply(&edit);
+        }
+
+        match &doc.parse.error {
+            Some(e) => Err(e.clone()),
+            None => Ok(lower::program(&doc.parse.syntax(), dialect)),
+        }
+    })
+}
+
 /// Recebe uma string com o conteúdo do programa, chama
 /// a infra do isic, e retorna um objeto contendo o código C
 /// gerado ou os erros e warning resultantes.
 ///
+/// Se `level` for dado ("1", "2" ou "3"), o programa só pode usar os
+/// recursos daquele nível da linguagem.
+///
 /// Efetivamente, repete o código do isic-cli, mas mastigado
 /// para ser facilmente consumido por WASM.
 #[wasm_bindgen]
-pub fn compile_to_c(input_text: &str) -> JsValue {
-    let parse_result = isilang_parser::program(input_text);
-
+pub fn compile_to_c(input_text: &str, level: Option<String>) -> JsValue {
     let mut result = CompileResult {
         output_code: None,
         errors: vec![],
         warns: vec![],
     };
 
-    let mut reporter_src = Source::from(input_text);
+    // Strings vindas do JavaScript são sempre UTF-8 válido, mas podem ter
+    // BOM e quebras de linha \r\n.
+    let source = SourceText::load(input_text.as_bytes());
+    let input_text = source.text.as_str();
+
+    // O dialeto vem do pragma #dialeto, e o padrão é o português.
+    let dialect = match Dialect::detect(input_text, &dialect::PT) {
+        Ok(dialect) => dialect,
+        Err(e) => {
+            result.errors.push(e);
+            return serde_wasm_bindgen::to_value(&result).unwrap();
+        }
+    };
+
+    let parse_result = parse(input_text, dialect);
+
+    let lines = LineIndex::new(input_text);
 
     match parse_result {
         Ok(ast) => 'a: {
+            if let Some(level) = level {
+                let errors = match level.parse::<Level>() {
+                    Ok(level) => LevelCk::new(&ast, level).check().err(),
+                    Err(e) => {
+                        result.errors.push(e);
+                        break 'a;
+                    }
+                };
+
+                for desc in errors.iter().flatten() {
+                    result.errors.push(report(ReportKind::Error, "Level error", desc.span, &desc.desc, &source));
+                }
+
+                if errors.is_some() {
+                    break 'a;
+                }
+            }
+
             let mut typeck = TypeCk::new(&ast);
             if let Err(errors) = typeck.check() {
                 for desc in errors {
-                    let offset = reporter_src.get_offset_line(desc.span.start).unwrap();
-
-                    let mut report_bytes: Vec<u8> = vec![];
-
-                    let _report = Report::build(ariadne::ReportKind::Error, (), offset.1)
-                        .with_message("Type error")
-                        .with_label(
-                            Label::new(((), desc.span.start..desc.span.end))
-                                .with_color(ariadne::Color::Red)
-                                .with_message(desc.desc),
-                        )
-                        .finish()
-                        .write(&mut reporter_src, &mut report_bytes)
-                        .unwrap();
-
-                    let report_str = String::from_utf8(report_bytes).unwrap();
-
-                    result.errors.push(report_str);
+                    result.errors.push(report(ReportKind::Error, "Type error", desc.span, &desc.desc, &source));
                 }
 
                 break 'a;
             }
 
+            for desc in &typeck.warnings {
+                result.warns.push(report(ReportKind::Warning, "Type warning", desc.span, &desc.desc, &source));
+            }
+
             let mut usageck = UsageCk::new(&ast);
             let warns = usageck.check();
 
             for desc in warns {
-                let offset = reporter_src.get_offset_line(desc.span.start).unwrap();
-
-                let mut report_bytes: Vec<u8> = vec![];
-
-                let _report = Report::build(ariadne::ReportKind::Warning, (), offset.1)
-                    .with_message("Usage pattern warning")
-                    .with_label(
-                        Label::new(((), desc.span.start..desc.span.end))
-                            .with_color(ariadne::Color::Yellow)
-                            .with_message(desc.desc),
-                    )
-                    .finish()
-                    .write(&mut reporter_src, &mut report_bytes)
-                    .unwrap();
-
-                let report_str = String::from_utf8(report_bytes).unwrap();
-
-                result.warns.push(report_str);
+                let warn = report(ReportKind::Warning, "Usage pattern warning", desc.span, &desc.desc, &source);
+                result.warns.push(warn);
             }
 
             let mut output_bytes: Vec<u8> = vec![];
 
-            let emitter = CEmitter::new(&ast, &typeck.sym_table, &mut output_bytes);
+            let emitter = CEmitter::new(
+                &ast,
+                &typeck.sym_table,
+                &typeck.expr_types,
+                &typeck.coercions,
+                RuntimeOptions::default(),
+                &lines,
+                &mut output_bytes,
+            );
             emitter.emit().unwrap();
 
             let output_str = String::from_utf8(output_bytes).unwrap();
@@ -112,24 +271,7 @@ pub fn compile_to_c(input_text: &str) -> JsValue {
             result.output_code = Some(output_str);
         }
         Err(e) => {
-            let offset = reporter_src.get_offset_line(e.location.offset).unwrap();
-
-            let mut report_bytes: Vec<u8> = vec![];
-
-            let _report = Report::build(ariadne::ReportKind::Error, (), offset.1)
-                .with_message("Syntax error")
-                .with_label(
-                    Label::new(((), e.location.offset..e.location.offset+1))
-                        .with_color(ariadne::Color::Red)
-                        .with_message(format!("Expected {}", e.expected)),
-                )
-                .finish()
-                .write(&mut reporter_src, &mut report_bytes)
-                .unwrap();
-
-            let report_str = String::from_utf8(report_bytes).unwrap();
-
-            result.errors.push(report_str);
+            result.errors.push(report(ReportKind::Error, "Syntax error", e.span, &e.message, &source));
         }
     }
 
@@ -140,106 +282,186 @@ pub fn compile_to_c(input_text: &str) -> JsValue {
 /// programa, chama a infra do isic, e retorna um objeto contendo a saída do
 /// programa executado ou os erros e warning resultantes.
 ///
+/// Se `level` for dado ("1", "2" ou "3"), o programa só pode usar os
+/// recursos daquele nível da linguagem.
+///
 /// Efetivamente, repete o código do isic-cli, mas mastigado
 /// para ser facilmente consumido por WASM.
 #[wasm_bindgen]
-pub fn run_interpreter(code: &str, input_text: &str) -> JsValue {
-    let parse_result = isilang_parser::program(code);
-
+pub fn run_interpreter(code: &str, input_text: &str, level: Option<String>) -> JsValue {
     let mut result = InterpretResult {
         output: String::new(),
         errors: vec![],
         warns: vec![],
     };
 
-    let mut reporter_src = Source::from(code);
+    // Strings vindas do JavaScript são sempre UTF-8 válido, mas podem ter
+    // BOM e quebras de linha \r\n.
+    let source = SourceText::load(code.as_bytes());
+    let code = source.text.as_str();
+
+    // O dialeto vem do pragma #dialeto, e o padrão é o português.
+    let dialect = match Dialect::detect(code, &dialect::PT) {
+        Ok(dialect) => dialect,
+        Err(e) => {
+            result.errors.push(e);
+            return serde_wasm_bindgen::to_value(&result).unwrap();
+        }
+    };
+
+    let parse_result = parse(code, dialect);
+
+    let lines = LineIndex::new(code);
 
     match parse_result {
         Ok(ast) => 'a: {
+            if let Some(level) = level {
+                let errors = match level.parse::<Level>() {
+                    Ok(level) => LevelCk::new(&ast, level).check().err(),
+                    Err(e) => {
+                        result.errors.push(e);
+                        break 'a;
+                    }
+                };
+
+                for desc in errors.iter().flatten() {
+                    result.errors.push(report(ReportKind::Error, "Level error", desc.span, &desc.desc, &source));
+                }
+
+                if errors.is_some() {
+                    break 'a;
+                }
+            }
+
             let mut typeck = TypeCk::new(&ast);
             if let Err(errors) = typeck.check() {
                 for desc in errors {
-                    let offset = reporter_src.get_offset_line(desc.span.start).unwrap();
-
-                    let mut report_bytes: Vec<u8> = vec![];
-
-                    let _report = Report::build(ariadne::ReportKind::Error, (), offset.1)
-                        .with_message("Type error")
-                        .with_label(
-                            Label::new(((), desc.span.start..desc.span.end))
-                                .with_color(ariadne::Color::Red)
-                                .with_message(desc.desc),
-                        )
-                        .finish()
-                        .write(&mut reporter_src, &mut report_bytes)
-                        .unwrap();
-
-                    let report_str = String::from_utf8(report_bytes).unwrap();
-
-                    result.errors.push(report_str);
+                    result.errors.push(report(ReportKind::Error, "Type error", desc.span, &desc.desc, &source));
                 }
 
                 break 'a;
             }
 
+            for desc in &typeck.warnings {
+                result.warns.push(report(ReportKind::Warning, "Type warning", desc.span, &desc.desc, &source));
+            }
+
             let mut usageck = UsageCk::new(&ast);
             let warns = usageck.check();
 
             for desc in warns {
-                let offset = reporter_src.get_offset_line(desc.span.start).unwrap();
-
-                let mut report_bytes: Vec<u8> = vec![];
-
-                let _report = Report::build(ariadne::ReportKind::Warning, (), offset.1)
-                    .with_message("Usage pattern warning")
-                    .with_label(
-                        Label::new(((), desc.span.start..desc.span.end))
-                            .with_color(ariadne::Color::Yellow)
-                            .with_message(desc.desc),
-                    )
-                    .finish()
-                    .write(&mut reporter_src, &mut report_bytes)
-                    .unwrap();
-
-                let report_str = String::from_utf8(report_bytes).unwrap();
-
-                result.warns.push(report_str);
+                let warn = report(ReportKind::Warning, "Usage pattern warning", desc.span, &desc.desc, &source);
+                result.warns.push(warn);
             }
 
             let mut input_cursor = Cursor::new(input_text.to_string());
             let mut output_bytes: Vec<u8> = vec![];
+            // No navegador não há sistema de arquivos, então os arquivos
+            // do programa ficam na memória, e somem ao fim da execução.
+            let mut fs = MemFs::new();
 
             let mut interpreter = IsiInterpreter::new(
                 &ast,
+                &typeck.coercions,
+                RuntimeOptions::default(),
+                &lines,
                 &mut input_cursor,
                 &mut output_bytes,
+                &mut fs,
             );
 
-            interpreter.exec();
+            let exec = interpreter.exec();
+            drop(interpreter);
+
+            match exec {
+                Ok(0) => {}
+                Ok(code) => result.errors.push(format!("Program exited with code {}", code)),
+                Err(e) => result.errors.push(e),
+            }
 
             result.output = String::from_utf8(output_bytes).unwrap();
         }
         Err(e) => {
-            let offset = reporter_src.get_offset_line(e.location.offset).unwrap();
-
-            let mut report_bytes: Vec<u8> = vec![];
+            result.errors.push(report(ReportKind::Error, "Syntax error", e.span, &e.message, &source));
+        }
+    }
 
-            let _report = Report::build(ariadne::ReportKind::Error, (), offset.1)
-                .with_message("Syntax error")
-                .with_label(
-                    Label::new(((), e.location.offset..e.location.offset+1))
-                        .with_color(ariadne::Color::Red)
-                        .with_message(format!("Expected {}", e.expected)),
-                )
-                .finish()
-                .write(&mut reporter_src, &mut report_bytes)
-                .unwrap();
+    serde_wasm_bindgen::to_value(&result).unwrap()
+}
 
-            let report_str = String::from_utf8(report_bytes).unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            result.errors.push(report_str);
+    const PROGRAM: &str = r#"programa
+    declare x: int.
+    x := 0.
+    enquanto (x < 3) {
+        se (x == 1) entao {
+            escreva("um").
+        }
+        x := x + 1.
+    }
+fimprog.
+"#;
+
+    /// O documento guardado, depois de uma sequência de versões do código,
+    /// é igual a um parse do zero da última versão.
+    #[test]
+    fn parse_reuses_the_previous_document() {
+        let versions = [
+            PROGRAM.to_string(),
+            PROGRAM.replace("\"um\"", "\"dois\", x"),
+            PROGRAM.replace("x + 1", "x + 2"),
+            PROGRAM.replace("x + 1.", "x + ."),
+            PROGRAM.replace("declare x: int.", "declare x: int, é: string."),
+            PROGRAM.to_string(),
+        ];
+
+        for version in &versions {
+            let expected = parser::parse(version, &dialect::PT);
+            assert_eq!(parse(version, &dialect::PT), expected);
+
+            DOCUMENT.with(|document| {
+                let document = document.borrow();
+                let doc = document.as_ref().unwrap();
+
+                assert_eq!(&doc.text, version);
+                assert_eq!(doc.parse.green, parser::parse_cst(version, &dialect::PT).green);
+            });
         }
     }
 
-    serde_wasm_bindgen::to_value(&result).unwrap()
+    #[test]
+    fn edit_source_counts_utf16_units() {
+        parse("programa\n    escreva(\"🎉é\").\nfimprog.\n", &dialect::PT).unwrap();
+
+        // O 🎉 tem duas unidades UTF-16, e o é tem uma.
+        let start = "programa\n    escreva(\"".len();
+        edit_source(start + 2, start + 3, "e, ");
+
+        let text = "programa\n    escreva(\"🎉e, \").\nfimprog.\n";
+        DOCUMENT.with(|document| {
+            let document = document.borrow();
+            let doc = document.as_ref().unwrap();
+
+            assert_eq!(doc.text, text);
+            assert_eq!(doc.parse.green, parser::parse_cst(text, &dialect::PT).green);
+        });
+    }
+
+    #[test]
+    fn diff_finds_the_edited_range() {
+        let edit = diff("abcdef", "abXYef");
+        assert_eq!(edit.span, Span { start: 2, end: 4 });
+        assert_eq!(edit.text, "XY");
+
+        let edit = diff("aaa", "aaaa");
+        assert_eq!(edit.span, Span { start: 3, end: 3 });
+        assert_eq!(edit.text, "a");
+
+        let edit = diff("médio", "média");
+        assert_eq!(edit.span, Span { start: 5, end: 6 });
+        assert_eq!(edit.text, "a");
+    }
 }
@@ -19,7 +19,7 @@ function App() {
   const [warns, setWarns] = useState<string[]>([]);
 
   const compile = (text: string) => {
-    const result = compile_to_c(text);
+    const result = compile_to_c(text, undefined);
 
     setCodeOutput(result.output_code);
     setErrors(result.errors);
@@ -27,7 +27,7 @@ function App() {
   };
 
   const evaluate = (text: string) => {
-    const result = run_interpreter(text, interpreterInput);
+    const result = run_interpreter(text, interpreterInput, undefined);
 
     setInterpreterOutput(result.output);
     setErrors(result.errors);
@@ -5,6 +5,8 @@ import "ace-builds/src-noconflict/mode-java";
 import "ace-builds/src-noconflict/theme-terminal";
 
 import AceEditor from "react-ace";
+import { Ace } from "ace-builds";
+import { edit_source } from "isic-playground-glue";
 import { Button, Select, ButtonsWrapper } from "./editor-window.style";
 import { Window } from "./window";
 
@@ -81,10 +83,28 @@ export type EditorProps = {
     onEvaluate: (text: string) => void;
 };
 
+// A posição de uma linha e coluna do editor no texto, em unidades UTF-16.
+const offset = (text: string, pos: Ace.Point) =>
+    text.split("\n").slice(0, pos.row).reduce((n, line) => n + line.length + 1, 0) + pos.column;
+
 export const EditorWindow: React.FC<EditorProps> = (props: EditorProps) => {
     const [example, setExample] = useState<string>("hello-world");
     const [text, setText] = useState(EXAMPLES.get("hello-world"));
 
+    // Cada edição é repassada ao compilador, que refaz o parse só do bloco
+    // editado.
+    const onChange = (value: string, delta: Ace.Delta) => {
+        const start = offset(text ?? "", delta.start);
+
+        if (delta.action === "insert") {
+            edit_source(start, start, delta.lines.join("\n"));
+        } else {
+            edit_source(start, offset(text ?? "", delta.end), "");
+        }
+
+        setText(value);
+    };
+
     return (
         <Window title="playground.isi - Editor">
             <p>Examples</p>
@@ -99,7 +119,7 @@ export const EditorWindow: React.FC<EditorProps> = (props: EditorProps) => {
             </ButtonsWrapper>
 
             <AceEditor
-                onChange={setText}
+                onChange={onChange}
                 value={text}
             />
 